@@ -0,0 +1,26 @@
+ALTER TABLE games ADD COLUMN stake_mode text not null default 'custodial' check (stake_mode in ('custodial', 'hold'));
+
+CREATE TABLE stake_holds
+(
+  id                 uuid primary key default uuid_generate_v1mc(),
+  user_id            uuid not null references users (id) on delete cascade,
+  game_id            uuid,
+  payment_hash       text unique not null,
+  preimage           text not null,
+  payment_request    text not null,
+  amount             int not null check (amount > 0),
+  payout_destination text,
+  status             text not null default 'pending' check (status in ('pending', 'accepted', 'settled', 'canceled')),
+  resolution         text check (resolution in ('settle', 'cancel')),
+  payout_user_id     uuid references users (id),
+  payout_amount      int check (payout_amount >= 0),
+  fee                int check (fee >= 0),
+  paid_out_at        timestamptz,
+  created_at         timestamptz not null default now(),
+  updated_at         timestamptz
+);
+
+CREATE UNIQUE INDEX stake_holds_game_id_user_id ON stake_holds (game_id, user_id);
+CREATE INDEX stake_holds_user_id ON stake_holds (user_id);
+
+SELECT trigger_updated_at('stake_holds');
@@ -0,0 +1,5 @@
+ALTER TABLE users ADD COLUMN is_admin boolean not null default false;
+
+-- Invoice paid to the winner's payout destination, so reconciliation can
+-- match it against the node's outgoing payments.
+ALTER TABLE stake_holds ADD COLUMN payout_invoice text;
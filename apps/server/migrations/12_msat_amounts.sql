@@ -0,0 +1,18 @@
+-- Ledger amounts are kept in millisatoshis so deposits are credited exactly.
+ALTER TABLE accounts ALTER COLUMN balance TYPE bigint USING balance::bigint * 1000;
+
+ALTER TABLE transfers ALTER COLUMN amount TYPE bigint USING amount::bigint * 1000;
+
+ALTER TABLE entries ALTER COLUMN amount TYPE bigint USING amount::bigint * 1000;
+ALTER TABLE entries ALTER COLUMN last_balance TYPE bigint USING last_balance::bigint * 1000;
+
+ALTER TABLE invoices ALTER COLUMN amount TYPE bigint USING amount::bigint * 1000;
+
+ALTER TABLE withdraw_links ALTER COLUMN amount TYPE bigint USING amount::bigint * 1000;
+
+ALTER TABLE stake_holds ALTER COLUMN amount TYPE bigint USING amount::bigint * 1000;
+ALTER TABLE stake_holds ALTER COLUMN payout_amount TYPE bigint USING payout_amount::bigint * 1000;
+ALTER TABLE stake_holds ALTER COLUMN fee TYPE bigint USING fee::bigint * 1000;
+
+-- Bets stay in whole sats.
+ALTER TABLE games ALTER COLUMN bet_value TYPE bigint;
@@ -0,0 +1,37 @@
+ALTER TABLE users ADD COLUMN password_changed_at timestamptz;
+
+create or replace function set_password_changed_at()
+    returns trigger as
+$$
+begin
+    NEW.password_changed_at = now();
+    return NEW;
+end;
+$$ language plpgsql;
+
+CREATE TRIGGER set_password_changed_at
+    BEFORE UPDATE OF password
+    ON users
+    FOR EACH ROW
+    WHEN (OLD.password is distinct from NEW.password)
+EXECUTE FUNCTION set_password_changed_at();
+
+-- Withdrawals above the review threshold are debited right away and wait
+-- here until an admin approves or rejects them.
+CREATE TABLE withdrawal_reviews
+(
+  id          uuid primary key default uuid_generate_v1mc(),
+  user_id     uuid not null references users (id) on delete cascade,
+  transfer_id uuid not null references transfers (id),
+  amount      bigint not null check (amount > 0),
+  invoice     text not null,
+  status      text not null default 'pending' check (status in ('pending', 'approved', 'rejected')),
+  reviewed_by uuid references users (id),
+  reviewed_at timestamptz,
+  created_at  timestamptz not null default now(),
+  updated_at  timestamptz
+);
+
+CREATE INDEX withdrawal_reviews_status_created_at ON withdrawal_reviews (status, created_at);
+
+SELECT trigger_updated_at('withdrawal_reviews');
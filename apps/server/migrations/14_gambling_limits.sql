@@ -0,0 +1,21 @@
+-- Responsible-gambling limits set by the players themselves. Amounts are in
+-- sats. Loosening a limit only takes effect at pending_effective_at, and a
+-- pending change without an amount removes the limit.
+CREATE TABLE gambling_limits
+(
+  user_id              uuid not null references users (id) on delete cascade,
+  kind                 text not null check (kind in ('deposit', 'stake', 'loss')),
+  amount               bigint not null check (amount > 0),
+  period               text check (period in ('day', 'week', 'month')),
+  pending_amount       bigint check (pending_amount > 0),
+  pending_period       text check (pending_period in ('day', 'week', 'month')),
+  pending_effective_at timestamptz,
+  created_at           timestamptz not null default now(),
+  updated_at           timestamptz,
+  primary key (user_id, kind)
+);
+
+SELECT trigger_updated_at('gambling_limits');
+
+ALTER TABLE users ADD COLUMN exclusion_kind text check (exclusion_kind in ('time_out', 'self_exclusion'));
+ALTER TABLE users ADD COLUMN excluded_until timestamptz;
@@ -0,0 +1,34 @@
+-- Glicko-2 ratings per time-control category. Games created before ratings
+-- existed have no category and are left unrated.
+ALTER TABLE games ADD COLUMN rating_category text check (rating_category in ('bullet', 'blitz', 'rapid', 'classical'));
+
+CREATE TABLE ratings
+(
+  user_id    uuid not null references users (id) on delete cascade,
+  category   text not null check (category in ('bullet', 'blitz', 'rapid', 'classical')),
+  rating     double precision not null,
+  deviation  double precision not null,
+  volatility double precision not null,
+  games      integer not null default 0,
+  created_at timestamptz not null default now(),
+  updated_at timestamptz,
+  primary key (user_id, category)
+);
+
+SELECT trigger_updated_at('ratings');
+
+-- The rating of a player right after each of their rated games.
+CREATE TABLE rating_history
+(
+  id         uuid primary key default uuid_generate_v1mc(),
+  user_id    uuid not null references users (id) on delete cascade,
+  game_id    uuid not null references games (id) on delete cascade,
+  category   text not null check (category in ('bullet', 'blitz', 'rapid', 'classical')),
+  rating     double precision not null,
+  deviation  double precision not null,
+  volatility double precision not null,
+  created_at timestamptz not null default now(),
+  unique (user_id, game_id)
+);
+
+CREATE INDEX rating_history_user_id_created_at ON rating_history (user_id, created_at);
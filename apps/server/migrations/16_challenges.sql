@@ -0,0 +1,19 @@
+-- Games offered to a named user. Stakes are only taken once the challenged
+-- user accepts, when the game is created.
+CREATE TABLE challenges
+(
+  id            uuid primary key default uuid_generate_v1mc(),
+  challenger_id uuid not null references users (id) on delete cascade,
+  challenged_id uuid not null references users (id) on delete cascade,
+  request_key   text not null,
+  status        text not null default 'pending' check (status in ('pending', 'accepted', 'declined', 'canceled')),
+  game_id       uuid references games (id),
+  created_at    timestamptz not null default now(),
+  updated_at    timestamptz,
+  check (challenger_id <> challenged_id)
+);
+
+CREATE UNIQUE INDEX challenges_pending_pair ON challenges (challenger_id, challenged_id) WHERE status = 'pending';
+CREATE INDEX challenges_challenged_id ON challenges (challenged_id);
+
+SELECT trigger_updated_at('challenges');
@@ -0,0 +1,5 @@
+-- Nothing changes passwords yet, so the withdrawal cooldown after a password
+-- change could never apply.
+DROP TRIGGER set_password_changed_at ON users;
+DROP FUNCTION set_password_changed_at();
+ALTER TABLE users DROP COLUMN password_changed_at;
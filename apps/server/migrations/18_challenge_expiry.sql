@@ -0,0 +1,11 @@
+-- Challenges expire like seeks and invites. Expired ones are marked when
+-- the challenger sends the same user a new one.
+ALTER TABLE challenges ADD COLUMN expires_at timestamptz;
+
+UPDATE challenges SET expires_at = created_at + interval '1 day';
+
+ALTER TABLE challenges ALTER COLUMN expires_at SET NOT NULL;
+
+ALTER TABLE challenges DROP CONSTRAINT challenges_status_check;
+ALTER TABLE challenges ADD CONSTRAINT challenges_status_check
+  CHECK (status in ('pending', 'accepted', 'declined', 'canceled', 'expired'));
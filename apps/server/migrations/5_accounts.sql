@@ -0,0 +1,39 @@
+CREATE TABLE accounts
+(
+  id         uuid primary key default uuid_generate_v1mc(),
+  user_id    uuid unique not null references users (id) on delete cascade,
+  balance    int not null check (balance >= 0) default 0,
+  created_at timestamptz not null default now(),
+  updated_at timestamptz
+);
+
+SELECT trigger_updated_at('accounts');
+
+INSERT INTO accounts (user_id, balance)
+SELECT DISTINCT ON (user_id) user_id, last_balance
+FROM transactions
+ORDER BY user_id, created_at DESC;
+
+INSERT INTO accounts (user_id)
+SELECT id FROM users
+ON CONFLICT (user_id) DO NOTHING;
+
+-- Every ledger row must be written after its account row was updated in the
+-- same transaction, so `last_balance` always mirrors `accounts.balance`.
+create or replace function check_last_balance()
+    returns trigger as
+$$
+begin
+    if NEW.last_balance is distinct from (SELECT balance FROM accounts WHERE user_id = NEW.user_id) then
+        raise exception 'last_balance % does not match account balance of user %', NEW.last_balance, NEW.user_id;
+    end if;
+
+    return NEW;
+end;
+$$ language plpgsql;
+
+CREATE TRIGGER check_last_balance
+    BEFORE INSERT
+    ON transactions
+    FOR EACH ROW
+EXECUTE FUNCTION check_last_balance();
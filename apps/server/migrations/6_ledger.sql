@@ -0,0 +1,84 @@
+-- Double-entry ledger: every transfer moves money between two accounts, so the
+-- balances of all accounts (users, game escrows, platform and the lightning
+-- node, which goes negative as money comes in) always sum to zero.
+ALTER TABLE accounts ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE accounts ADD COLUMN kind text not null default 'user' check (kind in ('user', 'escrow', 'platform', 'lightning'));
+ALTER TABLE accounts ADD COLUMN game_id uuid unique;
+ALTER TABLE accounts DROP CONSTRAINT accounts_balance_check;
+ALTER TABLE accounts ADD CONSTRAINT accounts_balance_check check (kind = 'lightning' or balance >= 0);
+ALTER TABLE accounts ADD CONSTRAINT accounts_owner_check check (
+  (kind = 'user') = (user_id is not null) and (kind = 'escrow') = (game_id is not null)
+);
+
+CREATE UNIQUE INDEX accounts_singleton_kind ON accounts (kind) WHERE kind in ('platform', 'lightning');
+
+INSERT INTO accounts (kind) VALUES ('platform'), ('lightning');
+
+CREATE TABLE transfers
+(
+  id         uuid primary key default uuid_generate_v1mc(),
+  type       text not null check (type in ('deposit', 'withdrawal', 'stake', 'payout', 'refund', 'fee')),
+  game_id    uuid,
+  invoice    text,
+  amount     int not null check (amount >= 0),
+  created_at timestamptz not null default now()
+);
+
+CREATE TABLE entries
+(
+  id           uuid primary key default uuid_generate_v1mc(),
+  transfer_id  uuid not null references transfers (id),
+  account_id   uuid not null references accounts (id),
+  amount       int not null,
+  last_balance int not null,
+  created_at   timestamptz not null default clock_timestamp()
+);
+
+CREATE INDEX entries_account_id_created_at ON entries (account_id, created_at);
+
+create or replace function check_transfer_balanced()
+    returns trigger as
+$$
+begin
+    if (SELECT sum(amount) FROM entries WHERE transfer_id = NEW.transfer_id) <> 0 then
+        raise exception 'transfer % is not balanced', NEW.transfer_id;
+    end if;
+
+    return NULL;
+end;
+$$ language plpgsql;
+
+CREATE CONSTRAINT TRIGGER check_transfer_balanced
+    AFTER INSERT
+    ON entries
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW
+EXECUTE FUNCTION check_transfer_balanced();
+
+-- Carry over current balances as deposits from the lightning account.
+DO
+$$
+declare
+    lightning_id     uuid;
+    lightning_balance int := 0;
+    transfer_id      uuid;
+    account          record;
+begin
+    SELECT id INTO lightning_id FROM accounts WHERE kind = 'lightning';
+
+    for account in SELECT id, balance FROM accounts WHERE kind = 'user' AND balance > 0 loop
+        lightning_balance := lightning_balance - account.balance;
+
+        INSERT INTO transfers (type, amount) VALUES ('deposit', account.balance) RETURNING id INTO transfer_id;
+        INSERT INTO entries (transfer_id, account_id, amount, last_balance)
+        VALUES (transfer_id, lightning_id, -account.balance, lightning_balance),
+               (transfer_id, account.id, account.balance, account.balance);
+    end loop;
+
+    UPDATE accounts SET balance = lightning_balance WHERE id = lightning_id;
+end;
+$$;
+
+DROP TRIGGER check_last_balance ON transactions;
+DROP FUNCTION check_last_balance();
+ALTER TABLE transactions RENAME TO legacy_transactions;
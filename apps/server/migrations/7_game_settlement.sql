@@ -0,0 +1,6 @@
+ALTER TABLE games ADD COLUMN termination text check (termination in ('checkmate', 'draw', 'abandoned', 'aborted'));
+ALTER TABLE games ADD COLUMN settled_at timestamptz;
+
+UPDATE games
+SET settled_at = coalesce(updated_at, created_at)
+WHERE state in ('draw', 'white_win', 'black_win');
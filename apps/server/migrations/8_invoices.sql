@@ -0,0 +1,17 @@
+CREATE TABLE invoices
+(
+  id              uuid primary key default uuid_generate_v1mc(),
+  user_id         uuid not null references users (id) on delete cascade,
+  payment_hash    text unique not null,
+  payment_request text not null,
+  amount          int not null check (amount > 0),
+  status          text not null check (status in ('pending', 'paid', 'expired')) default 'pending',
+  expires_at      timestamptz not null,
+  paid_at         timestamptz,
+  created_at      timestamptz not null default now(),
+  updated_at      timestamptz
+);
+
+CREATE INDEX invoices_user_id_created_at ON invoices (user_id, created_at);
+
+SELECT trigger_updated_at('invoices');
@@ -0,0 +1,15 @@
+CREATE TABLE withdraw_links
+(
+  id         uuid primary key default uuid_generate_v1mc(),
+  user_id    uuid not null references users (id) on delete cascade,
+  k1         text unique not null,
+  amount     int not null check (amount > 0),
+  expires_at timestamptz not null,
+  used_at    timestamptz,
+  created_at timestamptz not null default now(),
+  updated_at timestamptz
+);
+
+CREATE INDEX withdraw_links_user_id ON withdraw_links (user_id);
+
+SELECT trigger_updated_at('withdraw_links');
@@ -8,6 +8,7 @@ use std::marker::Sync;
 #[async_trait]
 pub trait HttpClient {
     async fn post<T: Serialize + Sync>(&self, path: &str, body: &T) -> Result<Response>;
+    async fn get(&self, path: &str) -> Result<Response>;
 }
 
 #[async_trait]
@@ -21,4 +22,13 @@ impl HttpClient for Client {
             .and_then(|r| r.error_for_status())
             .map_err(|_| crate::Error::InternalServerError)
     }
+
+    async fn get(&self, path: &str) -> Result<Response> {
+        self.get(format!("https://api.getalby.com{}", path))
+            .header("Authorization", format!("Bearer {}", &Env::get().lsp_token))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|_| crate::Error::InternalServerError)
+    }
 }
@@ -11,9 +11,23 @@ fn get_dotenv_path() -> String {
 pub struct Env {
     pub client_url: String,
     pub server_url: String,
+    pub public_url: String,
     pub database_url: String,
     pub jwt_secret: String,
     pub lsp_token: String,
+    pub deposit_webhook_secret: String,
+    pub rake_tiers: String,
+    pub rake_min_fee: String,
+    pub withdrawal_max: String,
+    pub withdrawal_daily_limit: String,
+    pub withdrawal_weekly_limit: String,
+    pub withdrawal_cooldown_minutes: String,
+    pub withdrawal_review_threshold: String,
+    pub limit_cooling_off_hours: String,
+    pub max_concurrent_games: String,
+    pub seek_ttl_minutes: String,
+    pub invite_ttl_minutes: String,
+    pub challenge_ttl_minutes: String,
 }
 
 impl Env {
@@ -22,12 +36,35 @@ impl Env {
     }
 
     pub fn get() -> Self {
+        let server_url = std::env::var("SERVER_URL").expect("SERVER_URL is void");
+
         Self {
             client_url: std::env::var("CLIENT_URL").expect("CLIENT_URL is void"),
-            server_url: std::env::var("SERVER_URL").expect("SERVER_URL is void"),
+            public_url: std::env::var("PUBLIC_URL")
+                .ok()
+                .filter(|url| !url.is_empty())
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or_else(|| format!("http://{server_url}")),
+            server_url,
             database_url: std::env::var("DATABASE_URL").expect("DATABASE_URL is void"),
             jwt_secret: std::env::var("JWT_SECRET").expect("JWT_SECRET is void"),
             lsp_token: std::env::var("LSP_TOKEN").expect("LSP_TOKEN is void"),
+            deposit_webhook_secret: std::env::var("DEPOSIT_WEBHOOK_SECRET")
+                .expect("DEPOSIT_WEBHOOK_SECRET is void"),
+            rake_tiers: std::env::var("RAKE_TIERS").unwrap_or_default(),
+            rake_min_fee: std::env::var("RAKE_MIN_FEE").unwrap_or_default(),
+            withdrawal_max: std::env::var("WITHDRAWAL_MAX").unwrap_or_default(),
+            withdrawal_daily_limit: std::env::var("WITHDRAWAL_DAILY_LIMIT").unwrap_or_default(),
+            withdrawal_weekly_limit: std::env::var("WITHDRAWAL_WEEKLY_LIMIT").unwrap_or_default(),
+            withdrawal_cooldown_minutes: std::env::var("WITHDRAWAL_COOLDOWN_MINUTES")
+                .unwrap_or_default(),
+            withdrawal_review_threshold: std::env::var("WITHDRAWAL_REVIEW_THRESHOLD")
+                .unwrap_or_default(),
+            limit_cooling_off_hours: std::env::var("LIMIT_COOLING_OFF_HOURS").unwrap_or_default(),
+            max_concurrent_games: std::env::var("MAX_CONCURRENT_GAMES").unwrap_or_default(),
+            seek_ttl_minutes: std::env::var("SEEK_TTL_MINUTES").unwrap_or_default(),
+            invite_ttl_minutes: std::env::var("INVITE_TTL_MINUTES").unwrap_or_default(),
+            challenge_ttl_minutes: std::env::var("CHALLENGE_TTL_MINUTES").unwrap_or_default(),
         }
     }
 }
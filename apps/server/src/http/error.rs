@@ -1,5 +1,4 @@
 #![allow(dead_code)]
-use anyhow;
 use axum::http::header::WWW_AUTHENTICATE;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -14,6 +13,7 @@ pub struct GenericError {
     pub message: String,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(thiserror::Error, Debug, aide::OperationIo, Default)]
 pub enum Error {
     /// Return `400 Bad Request`
@@ -153,10 +153,8 @@ impl From<Error> for String {
 }
 
 impl From<anyhow::Error> for Error {
-    fn from(error: anyhow::Error) -> Self {
-        match error {
-            _ => Self::InternalServerError,
-        }
+    fn from(_: anyhow::Error) -> Self {
+        Self::InternalServerError
     }
 }
 
@@ -0,0 +1,257 @@
+use crate::bad_req;
+use crate::http::{
+    CreateHoldInvoice, CreateInvoice, Error, HoldInvoiceState, LightningClient, NodePayment,
+    PaymentDirection, PaymentStatus, Result,
+};
+use crate::models::{DecodedInvoice, Sats};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::hex::FromHex;
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use lightning_invoice::{Currency, InvoiceBuilder, PaymentSecret};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+#[derive(Default)]
+struct FakeState {
+    invoices: HashMap<String, FakeInvoice>,
+    payments: Vec<String>,
+    failed_payments: Vec<String>,
+    fail_payments: bool,
+    balance: Sats,
+}
+
+struct FakeInvoice {
+    payment_request: String,
+    amount: Sats,
+    hold: bool,
+    state: HoldInvoiceState,
+}
+
+/// In-memory Lightning node for tests. Clones share the same node, so a test
+/// can keep one to play the payer while the service under test owns another.
+#[derive(Clone, Default)]
+pub struct FakeLightning {
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeLightning {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pays an invoice of this node. Hold invoices stay accepted until they
+    /// are settled or cancelled.
+    pub fn pay(&self, payment_request: &str) {
+        let mut state = self.state.lock().unwrap();
+        let invoice = state
+            .invoices
+            .values_mut()
+            .find(|invoice| invoice.payment_request == payment_request)
+            .expect("Unknown invoice");
+
+        invoice.state = match invoice.hold {
+            true => HoldInvoiceState::Accepted,
+            false => HoldInvoiceState::Settled,
+        };
+    }
+
+    pub fn invoice_state(&self, payment_hash: &str) -> Option<HoldInvoiceState> {
+        let state = self.state.lock().unwrap();
+
+        state
+            .invoices
+            .get(payment_hash)
+            .map(|invoice| invoice.state)
+    }
+
+    /// Invoices paid by this node, in order.
+    pub fn payments(&self) -> Vec<String> {
+        self.state.lock().unwrap().payments.clone()
+    }
+
+    pub fn fail_payments(&self) {
+        self.state.lock().unwrap().fail_payments = true;
+    }
+
+    pub fn set_balance(&self, balance: Sats) {
+        self.state.lock().unwrap().balance = balance;
+    }
+
+    fn add_invoice(
+        &self,
+        amount: Sats,
+        payment_hash: sha256::Hash,
+        memo: Option<String>,
+        description_hash: Option<String>,
+        hold: bool,
+    ) -> Result<String> {
+        let builder = InvoiceBuilder::new(Currency::Bitcoin);
+        let builder = match description_hash {
+            Some(hash) => builder.description_hash(
+                sha256::Hash::from_str(&hash).map_err(|_| Error::InternalServerError)?,
+            ),
+            None => builder.description(memo.unwrap_or_default()),
+        };
+
+        let key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let payment_request = builder
+            .payment_hash(payment_hash)
+            .payment_secret(PaymentSecret([7; 32]))
+            .duration_since_epoch(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap(),
+            )
+            .expiry_time(Duration::from_secs(3600))
+            .min_final_cltv_expiry_delta(144)
+            .amount_milli_satoshis(amount.to_msat()?.as_i64() as u64)
+            .build_signed(|message| Secp256k1::new().sign_ecdsa_recoverable(message, &key))
+            .map_err(|_| Error::InternalServerError)?
+            .to_string();
+
+        self.state.lock().unwrap().invoices.insert(
+            payment_hash.to_string(),
+            FakeInvoice {
+                payment_request: payment_request.clone(),
+                amount,
+                hold,
+                state: HoldInvoiceState::Open,
+            },
+        );
+
+        Ok(payment_request)
+    }
+}
+
+impl LightningClient for FakeLightning {
+    async fn create_invoice(&self, invoice: CreateInvoice) -> Result<String> {
+        let payment_hash = sha256::Hash::hash(&rand::random::<[u8; 32]>());
+
+        self.add_invoice(
+            invoice.amount,
+            payment_hash,
+            invoice.memo,
+            invoice.description_hash,
+            false,
+        )
+    }
+
+    async fn get_invoice(&self, payment_hash: String) -> Result<PaymentStatus> {
+        let state = self.state.lock().unwrap();
+        let paid = |payment_requests: &[String]| {
+            payment_requests.iter().any(|payment_request| {
+                DecodedInvoice::decode(payment_request)
+                    .is_ok_and(|invoice| invoice.payment_hash == payment_hash)
+            })
+        };
+
+        match state
+            .invoices
+            .get(&payment_hash)
+            .map(|invoice| invoice.state)
+        {
+            Some(HoldInvoiceState::Settled) => Ok(PaymentStatus::Settled),
+            Some(HoldInvoiceState::Canceled) => Ok(PaymentStatus::Failed),
+            Some(_) => Ok(PaymentStatus::Pending),
+            None if paid(&state.payments) => Ok(PaymentStatus::Settled),
+            None if paid(&state.failed_payments) => Ok(PaymentStatus::Failed),
+            None => Err(Error::NotFound {
+                message: String::from("Invoice not found!"),
+            }),
+        }
+    }
+
+    async fn pay_invoice(&self, invoice: String) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.fail_payments {
+            state.failed_payments.push(invoice);
+
+            return bad_req!("Payment failed");
+        }
+
+        state.payments.push(invoice);
+
+        Ok(())
+    }
+
+    fn supports_hold_invoices(&self) -> bool {
+        true
+    }
+
+    async fn create_hold_invoice(&self, invoice: CreateHoldInvoice) -> Result<String> {
+        let payment_hash = sha256::Hash::from_str(&invoice.payment_hash)
+            .map_err(|_| Error::InternalServerError)?;
+
+        self.add_invoice(invoice.amount, payment_hash, Some(invoice.memo), None, true)
+    }
+
+    async fn get_hold_invoice(&self, payment_hash: String) -> Result<HoldInvoiceState> {
+        self.invoice_state(&payment_hash).ok_or(Error::NotFound {
+            message: String::from("Invoice not found!"),
+        })
+    }
+
+    async fn settle_hold_invoice(&self, preimage: String) -> Result<()> {
+        let preimage = Vec::<u8>::from_hex(&preimage).map_err(|_| Error::InternalServerError)?;
+        let payment_hash = sha256::Hash::hash(&preimage).to_string();
+        let mut state = self.state.lock().unwrap();
+
+        match state.invoices.get_mut(&payment_hash) {
+            Some(invoice) if invoice.hold && invoice.state == HoldInvoiceState::Accepted => {
+                invoice.state = HoldInvoiceState::Settled;
+
+                Ok(())
+            }
+            _ => bad_req!("Hold invoice is not accepted"),
+        }
+    }
+
+    async fn cancel_hold_invoice(&self, payment_hash: String) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        match state.invoices.get_mut(&payment_hash) {
+            Some(invoice) if invoice.hold && invoice.state != HoldInvoiceState::Settled => {
+                invoice.state = HoldInvoiceState::Canceled;
+
+                Ok(())
+            }
+            _ => bad_req!("Hold invoice cannot be cancelled"),
+        }
+    }
+
+    async fn get_balance(&self) -> Result<Sats> {
+        Ok(self.state.lock().unwrap().balance)
+    }
+
+    async fn list_payments(&self) -> Result<Vec<NodePayment>> {
+        let state = self.state.lock().unwrap();
+        let incoming = state
+            .invoices
+            .iter()
+            .map(|(payment_hash, invoice)| NodePayment {
+                payment_hash: payment_hash.clone(),
+                amount: invoice.amount,
+                direction: PaymentDirection::Incoming,
+                status: match invoice.state {
+                    HoldInvoiceState::Settled => PaymentStatus::Settled,
+                    HoldInvoiceState::Canceled => PaymentStatus::Failed,
+                    _ => PaymentStatus::Pending,
+                },
+            });
+        let outgoing = state.payments.iter().map(|payment_request| {
+            let invoice = DecodedInvoice::decode(payment_request)?;
+
+            Ok(NodePayment {
+                payment_hash: invoice.payment_hash,
+                amount: invoice.amount.floor_sats(),
+                direction: PaymentDirection::Outgoing,
+                status: PaymentStatus::Settled,
+            })
+        });
+
+        incoming.map(Ok).chain(outgoing).collect()
+    }
+}
@@ -0,0 +1,220 @@
+use crate::bad_req;
+use crate::http::{Error, HttpClient, Result};
+use crate::models::Sats;
+use mockall::automock;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct CreateInvoice {
+    pub amount: Sats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_hash: Option<String>,
+}
+
+/// An invoice whose payment stays locked until it is settled with the
+/// preimage of `payment_hash` or cancelled.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CreateHoldInvoice {
+    pub amount: Sats,
+    pub payment_hash: String,
+    pub memo: String,
+}
+
+// Only reported by backends that support hold invoices.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HoldInvoiceState {
+    /// Waiting for a payment.
+    Open,
+    /// Paid, with the funds locked until it is settled or cancelled.
+    Accepted,
+    Settled,
+    Canceled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaymentDirection {
+    Incoming,
+    Outgoing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaymentStatus {
+    Pending,
+    Settled,
+    Failed,
+}
+
+/// A payment as the Lightning node records it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodePayment {
+    pub payment_hash: String,
+    pub amount: Sats,
+    pub direction: PaymentDirection,
+    pub status: PaymentStatus,
+}
+
+#[derive(Serialize)]
+struct PayInvoice {
+    invoice: String,
+}
+
+#[derive(Deserialize)]
+struct CreatedInvoice {
+    payment_request: String,
+}
+
+#[derive(Deserialize)]
+struct NodeBalance {
+    balance: Sats,
+}
+
+#[derive(Deserialize)]
+struct ListedInvoice {
+    payment_hash: String,
+    amount: Sats,
+    settled: bool,
+    #[serde(default)]
+    state: String,
+}
+
+impl ListedInvoice {
+    fn into_payment(self, direction: PaymentDirection) -> NodePayment {
+        let status = match (self.settled, self.state.to_lowercase().as_str()) {
+            (true, _) => PaymentStatus::Settled,
+            (false, "failed" | "canceled" | "cancelled" | "expired") => PaymentStatus::Failed,
+            _ => PaymentStatus::Pending,
+        };
+
+        NodePayment {
+            payment_hash: self.payment_hash,
+            amount: self.amount,
+            direction,
+            status,
+        }
+    }
+}
+
+const PAYMENTS_PAGE_SIZE: usize = 100;
+
+/// Operations the wallet needs from the Lightning node.
+#[automock]
+pub trait LightningClient {
+    /// Returns the BOLT11 payment request of the new invoice.
+    async fn create_invoice(&self, invoice: CreateInvoice) -> Result<String>;
+    /// Status of an invoice this node created or paid.
+    async fn get_invoice(&self, payment_hash: String) -> Result<PaymentStatus>;
+    async fn pay_invoice(&self, invoice: String) -> Result<()>;
+    /// Whether the hold invoice calls below work. Non-custodial stakes are
+    /// refused when they don't.
+    fn supports_hold_invoices(&self) -> bool;
+    /// Returns the BOLT11 payment request of the new hold invoice.
+    async fn create_hold_invoice(&self, invoice: CreateHoldInvoice) -> Result<String>;
+    async fn get_hold_invoice(&self, payment_hash: String) -> Result<HoldInvoiceState>;
+    async fn settle_hold_invoice(&self, preimage: String) -> Result<()>;
+    async fn cancel_hold_invoice(&self, payment_hash: String) -> Result<()>;
+    /// Spendable balance of the node.
+    async fn get_balance(&self) -> Result<Sats>;
+    async fn list_payments(&self) -> Result<Vec<NodePayment>>;
+}
+
+/// The Alby API has no hold invoices, so non-custodial stakes need a node
+/// backend that supports them.
+fn hold_invoices_unsupported<T>() -> Result<T> {
+    bad_req!("Hold invoices are not supported by the Lightning backend")
+}
+
+impl LightningClient for Client {
+    async fn create_invoice(&self, invoice: CreateInvoice) -> Result<String> {
+        let CreatedInvoice { payment_request } = HttpClient::post(self, "/invoices", &invoice)
+            .await?
+            .json()
+            .await
+            .map_err(|_| crate::Error::InternalServerError)?;
+
+        Ok(payment_request)
+    }
+
+    async fn get_invoice(&self, payment_hash: String) -> Result<PaymentStatus> {
+        let invoice: ListedInvoice = HttpClient::get(self, &format!("/invoices/{payment_hash}"))
+            .await?
+            .json()
+            .await
+            .map_err(|_| crate::Error::InternalServerError)?;
+
+        Ok(invoice.into_payment(PaymentDirection::Incoming).status)
+    }
+
+    async fn pay_invoice(&self, invoice: String) -> Result<()> {
+        HttpClient::post(self, "/payments/bolt11", &PayInvoice { invoice }).await?;
+
+        Ok(())
+    }
+
+    fn supports_hold_invoices(&self) -> bool {
+        false
+    }
+
+    async fn create_hold_invoice(&self, _: CreateHoldInvoice) -> Result<String> {
+        hold_invoices_unsupported()
+    }
+
+    async fn get_hold_invoice(&self, _: String) -> Result<HoldInvoiceState> {
+        hold_invoices_unsupported()
+    }
+
+    async fn settle_hold_invoice(&self, _: String) -> Result<()> {
+        hold_invoices_unsupported()
+    }
+
+    async fn cancel_hold_invoice(&self, _: String) -> Result<()> {
+        hold_invoices_unsupported()
+    }
+
+    async fn get_balance(&self) -> Result<Sats> {
+        let NodeBalance { balance } = HttpClient::get(self, "/balance")
+            .await?
+            .json()
+            .await
+            .map_err(|_| crate::Error::InternalServerError)?;
+
+        Ok(balance)
+    }
+
+    async fn list_payments(&self) -> Result<Vec<NodePayment>> {
+        let mut payments = vec![];
+
+        for (path, direction) in [
+            ("/invoices/incoming", PaymentDirection::Incoming),
+            ("/invoices/outgoing", PaymentDirection::Outgoing),
+        ] {
+            for page in 1.. {
+                let invoices: Vec<ListedInvoice> = HttpClient::get(
+                    self,
+                    &format!("{path}?items={PAYMENTS_PAGE_SIZE}&page={page}"),
+                )
+                .await?
+                .json()
+                .await
+                .map_err(|_| crate::Error::InternalServerError)?;
+
+                let last_page = invoices.len() < PAYMENTS_PAGE_SIZE;
+
+                payments.extend(
+                    invoices
+                        .into_iter()
+                        .map(|invoice| invoice.into_payment(direction)),
+                );
+
+                if last_page {
+                    break;
+                }
+            }
+        }
+
+        Ok(payments)
+    }
+}
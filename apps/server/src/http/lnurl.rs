@@ -0,0 +1,81 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use crate::models::{
+    description_hash, pay_request_url, secure_url, DecodedInvoice, LnurlResponse, LnurlStatus,
+    LnurlTag, Msat, PayRequest, PayRequestInvoice,
+};
+use mockall::automock;
+use reqwest::{Client, Url};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests to third-party LNURL services.
+#[automock]
+pub trait LnurlClient {
+    async fn get_pay_request(&self, url: Url) -> Result<PayRequest>;
+    async fn get_pay_invoice(&self, callback: String, amount: Msat) -> Result<PayRequestInvoice>;
+}
+
+async fn get<T: DeserializeOwned>(client: &Client, url: Url) -> Result<T> {
+    let Ok(response) = client.get(url).timeout(TIMEOUT).send().await else {
+        return bad_req!("Could not reach the LNURL service");
+    };
+
+    match response.json::<LnurlResponse<T>>().await {
+        Ok(LnurlResponse::Ok(body)) => Ok(body),
+        Ok(LnurlResponse::Status(LnurlStatus::Error { reason })) => bad_req!(reason),
+        _ => bad_req!("Invalid response from the LNURL service"),
+    }
+}
+
+/// Requests an invoice for `amount` from a Lightning Address or LNURL-pay
+/// service and checks that it matches the payment request.
+pub async fn request_pay_invoice<C: LnurlClient>(
+    client: &C,
+    destination: &str,
+    amount: Msat,
+) -> Result<DecodedInvoice> {
+    let pay_request = client
+        .get_pay_request(pay_request_url(destination)?)
+        .await?;
+
+    if pay_request.tag != LnurlTag::PayRequest {
+        return bad_req!("Destination does not accept payments");
+    }
+
+    if !(pay_request.min_sendable..=pay_request.max_sendable).contains(&amount) {
+        return bad_req!(format!(
+            "Amount must be between {} and {} sats",
+            pay_request.min_sendable.ceil_sats(),
+            pay_request.max_sendable.floor_sats()
+        ));
+    }
+
+    let pay_invoice = client.get_pay_invoice(pay_request.callback, amount).await?;
+    let invoice = DecodedInvoice::decode(&pay_invoice.pr)?;
+
+    if invoice.amount != amount
+        || invoice.description_hash != Some(description_hash(&pay_request.metadata))
+    {
+        return bad_req!("Invoice from the destination does not match the payment request");
+    }
+
+    Ok(invoice)
+}
+
+impl LnurlClient for Client {
+    async fn get_pay_request(&self, url: Url) -> Result<PayRequest> {
+        get(self, url).await
+    }
+
+    async fn get_pay_invoice(&self, callback: String, amount: Msat) -> Result<PayRequestInvoice> {
+        let mut url = secure_url(&callback)?;
+
+        url.query_pairs_mut()
+            .append_pair("amount", &amount.to_string());
+
+        get(self, url).await
+    }
+}
@@ -7,4 +7,15 @@ pub use error::*;
 mod config;
 pub use config::*;
 
+mod lightning;
+pub use lightning::*;
+
+mod lnurl;
+pub use lnurl::*;
+
+#[cfg(test)]
+mod fake_lightning;
+#[cfg(test)]
+pub use fake_lightning::*;
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
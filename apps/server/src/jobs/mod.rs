@@ -0,0 +1,14 @@
+use std::time::Duration;
+
+mod reconciliation;
+mod seek_expiry;
+mod stake_holds;
+
+pub(crate) use reconciliation::ReconciliationJob;
+
+/// Starts the background jobs. The database must be initialized first.
+pub fn spawn() {
+    tokio::spawn(stake_holds::run(Duration::from_secs(10)));
+    tokio::spawn(reconciliation::run(Duration::from_secs(60 * 60)));
+    tokio::spawn(seek_expiry::run(Duration::from_secs(30)));
+}
@@ -0,0 +1,270 @@
+use crate::http::{Error, LightningClient, PaymentDirection, PaymentStatus, Result};
+use crate::internal_error;
+use crate::models::{DecodedInvoice, Discrepancy, Msat, ReconciliationReport};
+use crate::repositories::{ReconciliationRepository, ReconciliationRepositoryTrait};
+use chrono::Utc;
+use reqwest::Client;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Compares the ledger with the balance and payments of the Lightning node.
+pub struct ReconciliationJob<R: ReconciliationRepositoryTrait, L: LightningClient> {
+    reconciliation_repository: R,
+    lightning_client: L,
+}
+
+impl<R: ReconciliationRepositoryTrait, L: LightningClient> ReconciliationJob<R, L> {
+    pub fn new(reconciliation_repository: R, lightning_client: L) -> Self {
+        Self {
+            reconciliation_repository,
+            lightning_client,
+        }
+    }
+
+    pub async fn run_once(&self) -> Result<ReconciliationReport> {
+        let ledger = self.reconciliation_repository.get_ledger_totals().await?;
+        let node_balance = self.lightning_client.get_balance().await?;
+        let payments = self.lightning_client.list_payments().await?;
+
+        let credited = self
+            .reconciliation_repository
+            .list_credited_payments()
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let debited = self
+            .reconciliation_repository
+            .list_debited_invoices()
+            .await?
+            .iter()
+            .filter_map(|invoice| DecodedInvoice::decode(invoice).ok())
+            .map(|invoice| invoice.payment_hash)
+            .collect::<HashSet<_>>();
+
+        let mut discrepancies = vec![];
+        let total = [ledger.escrow, ledger.platform, ledger.lightning]
+            .into_iter()
+            .try_fold(ledger.users, Msat::checked_add)
+            .ok_or(internal_error!())?;
+
+        if total != Msat::ZERO {
+            discrepancies.push(Discrepancy::UnbalancedLedger { total });
+        }
+
+        for drift in self.reconciliation_repository.list_account_drifts().await? {
+            discrepancies.push(Discrepancy::BalanceDrift {
+                account_id: drift.account_id,
+                balance: drift.balance,
+                last_balance: drift.last_balance,
+            });
+        }
+
+        // Withdrawals are debited before the node pays them, so until they
+        // settle the node still holds their amount.
+        let mut in_flight_withdrawals = Msat::ZERO;
+
+        for payment in payments {
+            match (payment.direction, payment.status) {
+                (PaymentDirection::Incoming, PaymentStatus::Settled)
+                    if !credited.contains(&payment.payment_hash) =>
+                {
+                    discrepancies.push(Discrepancy::UncreditedPayment {
+                        payment_hash: payment.payment_hash,
+                        amount: payment.amount,
+                    });
+                }
+                (PaymentDirection::Outgoing, PaymentStatus::Settled)
+                    if !debited.contains(&payment.payment_hash) =>
+                {
+                    discrepancies.push(Discrepancy::UndebitedPayment {
+                        payment_hash: payment.payment_hash,
+                        amount: payment.amount,
+                    });
+                }
+                (PaymentDirection::Outgoing, PaymentStatus::Failed)
+                    if debited.contains(&payment.payment_hash) =>
+                {
+                    discrepancies.push(Discrepancy::FailedPayment {
+                        payment_hash: payment.payment_hash,
+                        amount: payment.amount,
+                    });
+                }
+                (PaymentDirection::Outgoing, PaymentStatus::Pending)
+                    if debited.contains(&payment.payment_hash) =>
+                {
+                    in_flight_withdrawals = in_flight_withdrawals
+                        .checked_add(payment.amount.to_msat()?)
+                        .ok_or(internal_error!())?;
+                }
+                _ => {}
+            }
+        }
+
+        let expected = in_flight_withdrawals
+            .checked_sub(ledger.lightning)
+            .ok_or(internal_error!())?
+            .floor_sats();
+
+        if node_balance != expected {
+            discrepancies.push(Discrepancy::BalanceMismatch {
+                expected,
+                actual: node_balance,
+            });
+        }
+
+        Ok(ReconciliationReport {
+            checked_at: Utc::now(),
+            ledger,
+            node_balance,
+            in_flight_withdrawals,
+            discrepancies,
+        })
+    }
+}
+
+pub async fn run(interval: Duration) {
+    let job = ReconciliationJob::new(ReconciliationRepository::new(), Client::new());
+    let mut interval = tokio::time::interval(interval);
+
+    loop {
+        interval.tick().await;
+
+        match job.run_once().await {
+            Ok(report) => {
+                for discrepancy in report.discrepancies {
+                    tracing::warn!("Reconciliation discrepancy: {discrepancy:?}");
+                }
+            }
+            Err(error) => tracing::error!("Reconciliation job failed: {error}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{CreateInvoice, FakeLightning};
+    use crate::models::{LedgerTotals, Sats};
+    use crate::repositories::{AccountDrift, MockReconciliationRepositoryTrait};
+    use uuid::Uuid;
+
+    // BOLT11 specification test vector for 250000 sats.
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+    fn ledger(users: i64) -> LedgerTotals {
+        LedgerTotals {
+            users: Msat::new(users),
+            lightning: Msat::new(-users),
+            ..Default::default()
+        }
+    }
+
+    async fn deposit(lightning: &FakeLightning, amount: i64) -> String {
+        let invoice = lightning
+            .create_invoice(CreateInvoice {
+                amount: Sats::new(amount),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        lightning.pay(&invoice);
+
+        DecodedInvoice::decode(&invoice).unwrap().payment_hash
+    }
+
+    #[tokio::test]
+    async fn test_reconciled_ledger() {
+        let lightning = FakeLightning::new();
+        let mut mock_reconciliation_repository = MockReconciliationRepositoryTrait::new();
+        let payment_hash = deposit(&lightning, 1000).await;
+
+        lightning.pay_invoice(String::from(INVOICE)).await.unwrap();
+        lightning.set_balance(Sats::new(1000));
+
+        // The node only reports whole sats, so the fraction is not a mismatch.
+        mock_reconciliation_repository
+            .expect_get_ledger_totals()
+            .returning(|| Ok(ledger(1_000_500)));
+        mock_reconciliation_repository
+            .expect_list_account_drifts()
+            .returning(|| Ok(vec![]));
+        mock_reconciliation_repository
+            .expect_list_credited_payments()
+            .returning(move || Ok(vec![payment_hash.clone()]));
+        mock_reconciliation_repository
+            .expect_list_debited_invoices()
+            .returning(|| Ok(vec![String::from(INVOICE)]));
+
+        let job = ReconciliationJob::new(mock_reconciliation_repository, lightning);
+        let report = job.run_once().await.unwrap();
+
+        assert_eq!(report.discrepancies, vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_report_discrepancies() {
+        let lightning = FakeLightning::new();
+        let mut mock_reconciliation_repository = MockReconciliationRepositoryTrait::new();
+        let payment_hash = deposit(&lightning, 1000).await;
+        let account_id = Uuid::new_v4();
+
+        lightning.pay_invoice(String::from(INVOICE)).await.unwrap();
+        lightning.set_balance(Sats::new(500));
+
+        mock_reconciliation_repository
+            .expect_get_ledger_totals()
+            .returning(|| {
+                Ok(LedgerTotals {
+                    platform: Msat::new(10),
+                    ..ledger(0)
+                })
+            });
+        mock_reconciliation_repository
+            .expect_list_account_drifts()
+            .returning(move || {
+                Ok(vec![AccountDrift {
+                    account_id,
+                    balance: Msat::new(10),
+                    last_balance: Msat::ZERO,
+                }])
+            });
+        mock_reconciliation_repository
+            .expect_list_credited_payments()
+            .returning(|| Ok(vec![]));
+        mock_reconciliation_repository
+            .expect_list_debited_invoices()
+            .returning(|| Ok(vec![]));
+
+        let job = ReconciliationJob::new(mock_reconciliation_repository, lightning);
+        let report = job.run_once().await.unwrap();
+
+        assert_eq!(
+            report.discrepancies,
+            vec![
+                Discrepancy::UnbalancedLedger {
+                    total: Msat::new(10),
+                },
+                Discrepancy::BalanceDrift {
+                    account_id,
+                    balance: Msat::new(10),
+                    last_balance: Msat::ZERO,
+                },
+                Discrepancy::UncreditedPayment {
+                    payment_hash,
+                    amount: Sats::new(1000),
+                },
+                Discrepancy::UndebitedPayment {
+                    payment_hash: String::from(
+                        "0001020304050607080900010203040506070809000102030405060708090102"
+                    ),
+                    amount: Sats::new(250_000),
+                },
+                Discrepancy::BalanceMismatch {
+                    expected: Sats::ZERO,
+                    actual: Sats::new(500),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::http::Result;
+use crate::models::{Event, LobbyTrait, RoomsManagerTrait, SeekInfo};
+use crate::repositories::{StakeHoldRepositoryTrait, WalletRepositoryTrait};
+use crate::routes::game::cancel_seek::{self, service::CancelSeekService};
+use crate::states::{invite_ttl, seek_ttl};
+use chrono::{TimeDelta, Utc};
+use std::time::Duration;
+
+/// Expires the seeks and invites nobody took in time and refunds their
+/// stakes.
+pub struct SeekExpiryJob<
+    M: RoomsManagerTrait,
+    W: WalletRepositoryTrait,
+    H: StakeHoldRepositoryTrait,
+    B: LobbyTrait,
+> {
+    rooms_manager: M,
+    cancel_seek: CancelSeekService<M, W, H, B>,
+    ttl: TimeDelta,
+    invite_ttl: TimeDelta,
+}
+
+impl<
+        M: RoomsManagerTrait,
+        W: WalletRepositoryTrait,
+        H: StakeHoldRepositoryTrait,
+        B: LobbyTrait,
+    > SeekExpiryJob<M, W, H, B>
+{
+    pub fn new(
+        rooms_manager: M,
+        cancel_seek: CancelSeekService<M, W, H, B>,
+        ttl: TimeDelta,
+        invite_ttl: TimeDelta,
+    ) -> Self {
+        Self {
+            rooms_manager,
+            cancel_seek,
+            ttl,
+            invite_ttl,
+        }
+    }
+
+    pub async fn run_once(&self) -> Result<()> {
+        let now = Utc::now();
+
+        for (game_id, seek) in self
+            .rooms_manager
+            .take_expired_seeks(now - self.ttl, now - self.invite_ttl)
+        {
+            let player_id = seek.player_id;
+
+            // A seek whose refund failed goes back in the pool, so the next
+            // run retries it.
+            if let Err(error) = self
+                .cancel_seek
+                .close(
+                    game_id,
+                    seek.clone(),
+                    Event::SeekExpired(SeekInfo { game_id }),
+                )
+                .await
+            {
+                tracing::error!("Could not refund expired seek {game_id} of {player_id}: {error}");
+                self.rooms_manager.restore_seek(game_id, seek);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn run(interval: Duration) {
+    let job = SeekExpiryJob::new(
+        crate::models::RoomsManager::new(),
+        cancel_seek::resource(),
+        seek_ttl::get(),
+        invite_ttl::get(),
+    );
+    let mut interval = tokio::time::interval(interval);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = job.run_once().await {
+            tracing::error!("Seek expiry job failed: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GameRequest, MockLobbyTrait, MockRoomsManagerTrait, Msat, Seek};
+    use crate::repositories::{
+        MockStakeHoldRepositoryTrait, MockWalletRepositoryTrait, SaveTransfer,
+    };
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_expired_seeks_are_refunded() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_cancel_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_lobby = MockLobbyTrait::new();
+        let game_id = Uuid::new_v4();
+        let seek = Seek::new(
+            Uuid::new_v4(),
+            &GameRequest::from_str("n-10-0-10").unwrap(),
+            1500.0,
+        );
+        let player_id = seek.player_id;
+
+        mock_rooms_manager
+            .expect_take_expired_seeks()
+            .once()
+            .withf(|seeks_before, invites_before| {
+                *seeks_before < Utc::now() - TimeDelta::minutes(9)
+                    && *invites_before < Utc::now() - TimeDelta::hours(23)
+            })
+            .return_const(vec![(game_id, seek)]);
+
+        mock_cancel_rooms_manager
+            .expect_get_room()
+            .returning(|_| Err(crate::http::Error::InternalServerError));
+        mock_cancel_rooms_manager
+            .expect_remove_room()
+            .once()
+            .withf(move |id| *id == game_id)
+            .return_const(());
+
+        mock_wallet_repository
+            .expect_save_transfer()
+            .once()
+            .withf(move |transfer| {
+                *transfer == SaveTransfer::refund(game_id, player_id, Msat::new(10_000))
+            })
+            .returning(|_| Ok(Uuid::new_v4()));
+
+        mock_lobby
+            .expect_notify()
+            .once()
+            .withf(move |id, event| {
+                *id == player_id
+                    && matches!(event, Event::SeekExpired(info) if info.game_id == game_id)
+            })
+            .return_const(());
+
+        let job = SeekExpiryJob::new(
+            mock_rooms_manager,
+            CancelSeekService::new(
+                mock_cancel_rooms_manager,
+                mock_wallet_repository,
+                MockStakeHoldRepositoryTrait::new(),
+                mock_lobby,
+            ),
+            TimeDelta::minutes(10),
+            TimeDelta::days(1),
+        );
+
+        assert!(job.run_once().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_failed_refund_is_retried() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_cancel_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let game_id = Uuid::new_v4();
+        let seek = Seek::new(
+            Uuid::new_v4(),
+            &GameRequest::from_str("n-10-0-10").unwrap(),
+            1500.0,
+        );
+
+        mock_rooms_manager
+            .expect_take_expired_seeks()
+            .once()
+            .return_const(vec![(game_id, seek.clone())]);
+        mock_rooms_manager
+            .expect_restore_seek()
+            .once()
+            .withf(move |id, restored| *id == game_id && *restored == seek)
+            .return_const(());
+
+        mock_cancel_rooms_manager.expect_remove_room().never();
+        mock_wallet_repository
+            .expect_save_transfer()
+            .once()
+            .returning(|_| Err(crate::http::Error::InternalServerError));
+
+        let job = SeekExpiryJob::new(
+            mock_rooms_manager,
+            CancelSeekService::new(
+                mock_cancel_rooms_manager,
+                mock_wallet_repository,
+                MockStakeHoldRepositoryTrait::new(),
+                MockLobbyTrait::new(),
+            ),
+            TimeDelta::minutes(10),
+            TimeDelta::days(1),
+        );
+
+        assert!(job.run_once().await.is_ok());
+    }
+}
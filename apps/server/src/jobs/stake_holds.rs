@@ -0,0 +1,469 @@
+use crate::http::{request_pay_invoice, LightningClient, LnurlClient, PaymentStatus, Result};
+use crate::models::DecodedInvoice;
+use crate::repositories::{
+    HoldPayout, HoldResolution, HoldStatus, SaveTransfer, StakeHold, StakeHoldRepository,
+    StakeHoldRepositoryTrait,
+};
+use chrono::{TimeDelta, Utc};
+use reqwest::Client;
+use std::time::Duration;
+
+/// Holds that never made it into a game are cancelled after this long.
+const STALE_AFTER: TimeDelta = TimeDelta::minutes(30);
+
+/// Settles and cancels the stake holds of finished games, then pays the
+/// winners.
+pub struct StakeHoldJob<H: StakeHoldRepositoryTrait, L: LightningClient, C: LnurlClient> {
+    stake_hold_repository: H,
+    lightning_client: L,
+    lnurl_client: C,
+}
+
+impl<H: StakeHoldRepositoryTrait, L: LightningClient, C: LnurlClient> StakeHoldJob<H, L, C> {
+    pub fn new(stake_hold_repository: H, lightning_client: L, lnurl_client: C) -> Self {
+        Self {
+            stake_hold_repository,
+            lightning_client,
+            lnurl_client,
+        }
+    }
+
+    pub async fn run_once(&self) -> Result<()> {
+        let holds = self
+            .stake_hold_repository
+            .list_unresolved_holds(Utc::now() - STALE_AFTER)
+            .await?;
+
+        for hold in holds {
+            let payment_hash = hold.payment_hash.clone();
+
+            if let Err(error) = self.resolve_hold(hold).await {
+                tracing::warn!("Could not resolve stake hold {payment_hash}: {error}");
+            }
+        }
+
+        for payout in self.stake_hold_repository.list_hold_payouts().await? {
+            let payment_hash = payout.payment_hash.clone();
+
+            if let Err(error) = self.pay_winner(payout).await {
+                tracing::warn!("Could not pay out stake hold {payment_hash}: {error}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn resolve_hold(&self, hold: StakeHold) -> Result<()> {
+        let status = match hold.resolution {
+            Some(HoldResolution::Settle { .. }) => {
+                self.lightning_client
+                    .settle_hold_invoice(hold.preimage)
+                    .await?;
+
+                HoldStatus::Settled
+            }
+            _ => {
+                self.lightning_client
+                    .cancel_hold_invoice(hold.payment_hash.clone())
+                    .await?;
+
+                HoldStatus::Canceled
+            }
+        };
+
+        self.stake_hold_repository
+            .update_hold_status(hold.payment_hash, status)
+            .await
+    }
+
+    /// Pays the winner's destination, or credits their balance when there
+    /// is none or the payment fails. The node keeps the fee either way.
+    async fn pay_winner(&self, payout: HoldPayout) -> Result<()> {
+        if let Some(invoice) = payout.payout_invoice.clone() {
+            return self.resolve_payout(payout, invoice).await;
+        }
+
+        let Some(destination) = &payout.destination else {
+            return self.complete_payout(payout, None).await;
+        };
+
+        let invoice =
+            match request_pay_invoice(&self.lnurl_client, destination, payout.amount).await {
+                Ok(invoice) => invoice.payment_request,
+                Err(error) => {
+                    tracing::warn!("Crediting winnings to the balance instead: {error}");
+
+                    return self.complete_payout(payout, None).await;
+                }
+            };
+
+        // Recorded before paying, so a crash or a failed write afterwards
+        // cannot pay the winner twice.
+        if !self
+            .stake_hold_repository
+            .start_hold_payout(payout.payment_hash.clone(), invoice.clone())
+            .await?
+        {
+            return Ok(());
+        }
+
+        match self.lightning_client.pay_invoice(invoice.clone()).await {
+            Ok(()) => self.complete_payout(payout, Some(invoice)).await,
+            Err(error) => {
+                tracing::warn!("Payout of {} did not go through: {error}", payout.game_id);
+
+                self.resolve_payout(payout, invoice).await
+            }
+        }
+    }
+
+    /// Completes a started payout by what the node says became of it. One
+    /// still in flight is checked again on the next run.
+    async fn resolve_payout(&self, payout: HoldPayout, invoice: String) -> Result<()> {
+        let payment_hash = DecodedInvoice::decode(&invoice)?.payment_hash;
+
+        match self.lightning_client.get_invoice(payment_hash).await? {
+            PaymentStatus::Settled => self.complete_payout(payout, Some(invoice)).await,
+            PaymentStatus::Failed => self.complete_payout(payout, None).await,
+            PaymentStatus::Pending => Ok(()),
+        }
+    }
+
+    /// Marks the payout done. Without a paid invoice the winnings go to the
+    /// winner's balance.
+    async fn complete_payout(
+        &self,
+        payout: HoldPayout,
+        payout_invoice: Option<String>,
+    ) -> Result<()> {
+        let mut transfers = vec![SaveTransfer::hold_fee(payout.game_id, payout.fee)];
+
+        if payout_invoice.is_none() {
+            transfers.push(SaveTransfer::hold_payout(
+                payout.game_id,
+                payout.user_id,
+                payout.amount,
+            ));
+        }
+
+        self.stake_hold_repository
+            .complete_hold_payout(payout.payment_hash, payout_invoice, transfers)
+            .await?;
+
+        Ok(())
+    }
+}
+
+pub async fn run(interval: Duration) {
+    let job = StakeHoldJob::new(StakeHoldRepository::new(), Client::new(), Client::new());
+    let mut interval = tokio::time::interval(interval);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(error) = job.run_once().await {
+            tracing::error!("Stake hold job failed: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{CreateHoldInvoice, FakeLightning, HoldInvoiceState, MockLnurlClient};
+    use crate::models::{LnurlTag, Msat, PayRequest, PayRequestInvoice, Sats};
+    use crate::repositories::MockStakeHoldRepositoryTrait;
+    use uuid::{uuid, Uuid};
+
+    const PREIMAGE: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+    // SHA-256 of the preimage above.
+    const PAYMENT_HASH: &str = "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925";
+    const GAME_ID: Uuid = uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a");
+    const WINNER_ID: Uuid = uuid!("5d6cc3e8-8eec-4dab-881f-fddfb831cc41");
+
+    // BOLT11 specification test vector for 2000000 sats committing to the hash
+    // of the description below.
+    const INVOICE: &str = "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqs9qrsgq7ea976txfraylvgzuxs8kgcw23ezlrszfnh8r6qtfpr6cxga50aj6txm9rxrydzd06dfeawfk6swupvz4erwnyutnjq7x39ymw6j38gp7ynn44";
+    const METADATA: &str = "One piece of chocolate cake, one icecream cone, one pickle, one slice of swiss cheese, one slice of salami, one lollypop, one piece of cherry pie, one sausage, one cupcake, and one slice of watermelon";
+
+    async fn paid_hold(lightning: &FakeLightning) {
+        let invoice = lightning
+            .create_hold_invoice(CreateHoldInvoice {
+                amount: Sats::new(100),
+                payment_hash: String::from(PAYMENT_HASH),
+                memo: String::new(),
+            })
+            .await
+            .unwrap();
+
+        lightning.pay(&invoice);
+    }
+
+    fn stake_hold(resolution: Option<HoldResolution>) -> StakeHold {
+        StakeHold {
+            user_id: Uuid::new_v4(),
+            game_id: Some(GAME_ID),
+            payment_hash: String::from(PAYMENT_HASH),
+            preimage: String::from(PREIMAGE),
+            amount: Msat::new(100_000),
+            status: HoldStatus::Accepted,
+            resolution,
+        }
+    }
+
+    fn payout(destination: Option<&str>) -> HoldPayout {
+        HoldPayout {
+            payment_hash: String::from(PAYMENT_HASH),
+            game_id: GAME_ID,
+            user_id: WINNER_ID,
+            amount: Msat::new(2_000_000_000),
+            fee: Msat::new(10_000),
+            destination: destination.map(String::from),
+            payout_invoice: None,
+        }
+    }
+
+    fn pay_request() -> PayRequest {
+        PayRequest {
+            tag: LnurlTag::PayRequest,
+            callback: String::from("https://chesu.com/lnurl/pay/bob/callback"),
+            min_sendable: Msat::new(1_000),
+            max_sendable: Msat::new(10_000_000_000),
+            metadata: String::from(METADATA),
+        }
+    }
+
+    fn pay_invoice() -> PayRequestInvoice {
+        PayRequestInvoice {
+            pr: String::from(INVOICE),
+            routes: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_settle_loser_hold() {
+        let lightning = FakeLightning::new();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+
+        paid_hold(&lightning).await;
+
+        mock_stake_hold_repository
+            .expect_list_unresolved_holds()
+            .once()
+            .returning(|_| {
+                Ok(vec![stake_hold(Some(HoldResolution::Settle {
+                    payout_user_id: WINNER_ID,
+                    payout_amount: Msat::new(90_000),
+                    fee: Msat::new(10_000),
+                }))])
+            });
+
+        mock_stake_hold_repository
+            .expect_update_hold_status()
+            .once()
+            .withf(|payment_hash, status| {
+                payment_hash == PAYMENT_HASH && *status == HoldStatus::Settled
+            })
+            .returning(|_, _| Ok(()));
+
+        mock_stake_hold_repository
+            .expect_list_hold_payouts()
+            .returning(|| Ok(vec![]));
+
+        let job = StakeHoldJob::new(
+            mock_stake_hold_repository,
+            lightning.clone(),
+            MockLnurlClient::new(),
+        );
+
+        assert!(job.run_once().await.is_ok());
+        assert_eq!(
+            lightning.invoice_state(PAYMENT_HASH),
+            Some(HoldInvoiceState::Settled)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancel_hold() {
+        let lightning = FakeLightning::new();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+
+        paid_hold(&lightning).await;
+
+        mock_stake_hold_repository
+            .expect_list_unresolved_holds()
+            .once()
+            .returning(|_| Ok(vec![stake_hold(Some(HoldResolution::Cancel))]));
+
+        mock_stake_hold_repository
+            .expect_update_hold_status()
+            .once()
+            .withf(|_, status| *status == HoldStatus::Canceled)
+            .returning(|_, _| Ok(()));
+
+        mock_stake_hold_repository
+            .expect_list_hold_payouts()
+            .returning(|| Ok(vec![]));
+
+        let job = StakeHoldJob::new(
+            mock_stake_hold_repository,
+            lightning.clone(),
+            MockLnurlClient::new(),
+        );
+
+        assert!(job.run_once().await.is_ok());
+        assert_eq!(
+            lightning.invoice_state(PAYMENT_HASH),
+            Some(HoldInvoiceState::Canceled)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pay_winner_destination() {
+        let lightning = FakeLightning::new();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+        let mut mock_lnurl_client = MockLnurlClient::new();
+
+        mock_stake_hold_repository
+            .expect_list_unresolved_holds()
+            .returning(|_| Ok(vec![]));
+
+        mock_stake_hold_repository
+            .expect_list_hold_payouts()
+            .returning(|| Ok(vec![payout(Some("bob@chesu.com"))]));
+
+        mock_lnurl_client
+            .expect_get_pay_request()
+            .returning(|_| Ok(pay_request()));
+
+        mock_lnurl_client
+            .expect_get_pay_invoice()
+            .returning(|_, _| Ok(pay_invoice()));
+
+        mock_stake_hold_repository
+            .expect_start_hold_payout()
+            .once()
+            .withf(|payment_hash, payout_invoice| {
+                payment_hash == PAYMENT_HASH && payout_invoice == INVOICE
+            })
+            .returning(|_, _| Ok(true));
+
+        mock_stake_hold_repository
+            .expect_complete_hold_payout()
+            .once()
+            .withf(|_, payout_invoice, transfers| {
+                payout_invoice.as_deref() == Some(INVOICE)
+                    && *transfers == vec![SaveTransfer::hold_fee(GAME_ID, Msat::new(10_000))]
+            })
+            .returning(|_, _, _| Ok(true));
+
+        let job = StakeHoldJob::new(
+            mock_stake_hold_repository,
+            lightning.clone(),
+            mock_lnurl_client,
+        );
+
+        assert!(job.run_once().await.is_ok());
+        assert_eq!(lightning.payments(), vec![String::from(INVOICE)]);
+    }
+
+    #[tokio::test]
+    async fn test_credit_winner_when_payment_fails() {
+        let lightning = FakeLightning::new();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+        let mut mock_lnurl_client = MockLnurlClient::new();
+
+        mock_stake_hold_repository
+            .expect_list_unresolved_holds()
+            .returning(|_| Ok(vec![]));
+
+        mock_stake_hold_repository
+            .expect_list_hold_payouts()
+            .returning(|| Ok(vec![payout(Some("bob@chesu.com"))]));
+
+        mock_lnurl_client
+            .expect_get_pay_request()
+            .returning(|_| Ok(pay_request()));
+
+        mock_lnurl_client
+            .expect_get_pay_invoice()
+            .returning(|_, _| Ok(pay_invoice()));
+
+        mock_stake_hold_repository
+            .expect_start_hold_payout()
+            .once()
+            .withf(|payment_hash, payout_invoice| {
+                payment_hash == PAYMENT_HASH && payout_invoice == INVOICE
+            })
+            .returning(|_, _| Ok(true));
+
+        lightning.fail_payments();
+
+        mock_stake_hold_repository
+            .expect_complete_hold_payout()
+            .once()
+            .withf(|_, payout_invoice, transfers| {
+                payout_invoice.is_none()
+                    && *transfers
+                        == vec![
+                            SaveTransfer::hold_fee(GAME_ID, Msat::new(10_000)),
+                            SaveTransfer::hold_payout(GAME_ID, WINNER_ID, Msat::new(2_000_000_000)),
+                        ]
+            })
+            .returning(|_, _, _| Ok(true));
+
+        let job = StakeHoldJob::new(
+            mock_stake_hold_repository,
+            lightning.clone(),
+            mock_lnurl_client,
+        );
+
+        assert!(job.run_once().await.is_ok());
+        assert!(lightning.payments().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_started_payout_is_not_paid_again() {
+        let lightning = FakeLightning::new();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+        let mut mock_lnurl_client = MockLnurlClient::new();
+
+        lightning.pay_invoice(String::from(INVOICE)).await.unwrap();
+
+        mock_stake_hold_repository
+            .expect_list_unresolved_holds()
+            .returning(|_| Ok(vec![]));
+
+        mock_stake_hold_repository
+            .expect_list_hold_payouts()
+            .returning(|| {
+                Ok(vec![HoldPayout {
+                    payout_invoice: Some(String::from(INVOICE)),
+                    ..payout(Some("bob@chesu.com"))
+                }])
+            });
+
+        mock_lnurl_client.expect_get_pay_invoice().never();
+        mock_stake_hold_repository
+            .expect_start_hold_payout()
+            .never();
+
+        mock_stake_hold_repository
+            .expect_complete_hold_payout()
+            .once()
+            .withf(|_, payout_invoice, transfers| {
+                payout_invoice.as_deref() == Some(INVOICE)
+                    && *transfers == vec![SaveTransfer::hold_fee(GAME_ID, Msat::new(10_000))]
+            })
+            .returning(|_, _, _| Ok(true));
+
+        let job = StakeHoldJob::new(
+            mock_stake_hold_repository,
+            lightning.clone(),
+            mock_lnurl_client,
+        );
+
+        assert!(job.run_once().await.is_ok());
+        assert_eq!(lightning.payments(), vec![String::from(INVOICE)]);
+    }
+}
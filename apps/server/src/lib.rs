@@ -1,6 +1,7 @@
 use http::Error;
 
 pub mod app;
+pub mod jobs;
 pub mod states;
 
 mod http;
@@ -1,10 +1,20 @@
-use server::states::db;
-use server::{app::make_app, Env};
+use server::states::{
+    challenge_ttl, cooling_off, db, invite_ttl, max_concurrent_games, rake, seek_ttl,
+    withdrawal_limits,
+};
+use server::{app::make_app, jobs, Env};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     Env::init();
+    rake::init();
+    max_concurrent_games::init();
+    seek_ttl::init();
+    invite_ttl::init();
+    challenge_ttl::init();
+    cooling_off::init();
+    withdrawal_limits::init();
     db::init().await;
 
     tracing_subscriber::registry()
@@ -13,6 +23,8 @@ async fn main() -> anyhow::Result<()> {
 
     sqlx::migrate!().run(&db::get()).await?;
 
+    jobs::spawn();
+
     let listener = tokio::net::TcpListener::bind(&Env::get().server_url).await?;
 
     tracing::debug!("listening on {}", listener.local_addr()?);
@@ -1,20 +1,37 @@
-use super::game::GameState;
+use super::game::{GameState, Termination};
+use super::money::Msat;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(tag = "event", content = "data")]
 pub enum Event {
     PlayMove(MoveInfo),
     Disconnect(DisconnectInfo),
     GameChangeState(GameState),
+    GameResult(GameResultInfo),
     Join,
+    /// Sent on the lobby socket to withdraw the player's open seek.
+    CancelSeek,
+    SeekCanceled(SeekInfo),
+    /// The seek waited too long for an opponent and its stake was refunded.
+    SeekExpired(SeekInfo),
+    ChallengeReceived(ChallengeInfo),
+    /// The game of the challenge was created and both stakes were taken.
+    ChallengeAccepted(ChallengeInfo),
+    ChallengeDeclined(ChallengeInfo),
+    ChallengeCanceled(ChallengeInfo),
+    OfferRematch(RematchInfo),
+    /// Accepts the rematch the opponent offered.
+    AcceptRematch(RematchInfo),
+    /// Both players agreed on a rematch, to be played in `new_game_id`.
+    RematchStarted(RematchGameInfo),
 }
 
 impl Event {
     pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
-        serde_json::from_str::<Event>(&json)
+        serde_json::from_str::<Event>(json)
     }
 
     pub fn json(&self) -> String {
@@ -28,9 +45,43 @@ pub struct DisconnectInfo {
     pub player_id: Uuid,
 }
 
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SeekInfo {
+    pub game_id: Uuid,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChallengeInfo {
+    pub challenge_id: Uuid,
+    pub game_id: Option<Uuid>,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RematchInfo {
+    pub game_id: Uuid,
+    /// Player of the socket's session, whatever the client sends.
+    #[serde(default)]
+    pub player_id: Uuid,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RematchGameInfo {
+    pub game_id: Uuid,
+    pub new_game_id: Uuid,
+}
+
 #[derive(Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MoveInfo {
     pub game_id: Uuid,
     pub player_id: Uuid,
     pub move_played: String,
 }
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GameResultInfo {
+    pub game_id: Uuid,
+    pub state: GameState,
+    pub termination: Termination,
+    #[serde(rename = "fee_msat")]
+    pub fee: Msat,
+}
@@ -0,0 +1,415 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use crate::Env;
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::money::{Msat, Sats};
+
+const DEFAULT_COOLING_OFF_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    /// Total of the deposits in a period.
+    Deposit,
+    /// Largest stake of a single game.
+    Stake,
+    /// Net amount lost in games in a period.
+    Loss,
+}
+
+impl LimitKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Deposit => "deposit",
+            Self::Stake => "stake",
+            Self::Loss => "loss",
+        }
+    }
+
+    pub fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "deposit" => Ok(Self::Deposit),
+            "stake" => Ok(Self::Stake),
+            "loss" => Ok(Self::Loss),
+            _ => Err(Error::InternalServerError),
+        }
+    }
+}
+
+/// Rolling window a deposit or loss limit applies to.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+impl LimitPeriod {
+    pub fn duration(self) -> Duration {
+        match self {
+            Self::Day => Duration::days(1),
+            Self::Week => Duration::days(7),
+            Self::Month => Duration::days(30),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+
+    pub fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            "month" => Ok(Self::Month),
+            _ => Err(Error::InternalServerError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Limit {
+    pub amount: Sats,
+    /// Unset for stake limits, which apply to every game.
+    pub period: Option<LimitPeriod>,
+}
+
+impl Limit {
+    /// Whether this limit allows nothing `other` doesn't.
+    fn is_within(&self, other: &Limit) -> bool {
+        self.amount <= other.amount && self.period >= other.period
+    }
+}
+
+/// A loosened limit waiting out the cooling-off delay. `limit` is unset when
+/// the limit is being removed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PendingLimit {
+    pub limit: Option<Limit>,
+    pub effective_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerLimit {
+    pub kind: LimitKind,
+    pub limit: Limit,
+    pub pending: Option<PendingLimit>,
+}
+
+impl PlayerLimit {
+    pub fn effective(&self, now: DateTime<Utc>) -> Option<Limit> {
+        match self.pending {
+            Some(pending) if pending.effective_at <= now => pending.limit,
+            _ => Some(self.limit),
+        }
+    }
+
+    /// Applies a change requested by the user to `current`. Tighter limits
+    /// take effect at once, while looser ones, including removing the limit,
+    /// only after `cooling_off`. Returns `None` once there is no limit left.
+    pub fn change(
+        kind: LimitKind,
+        current: Option<&PlayerLimit>,
+        requested: Option<Limit>,
+        now: DateTime<Utc>,
+        cooling_off: Duration,
+    ) -> Result<Option<PlayerLimit>> {
+        if let Some(limit) = requested {
+            if !limit.amount.is_positive() {
+                return bad_req!("Limits must be greater than zero");
+            }
+
+            if (kind == LimitKind::Stake) != limit.period.is_none() {
+                return bad_req!("Deposit and loss limits need a period, stake limits don't");
+            }
+        }
+
+        let Some(effective) = current.and_then(|current| current.effective(now)) else {
+            return Ok(requested.map(|limit| PlayerLimit {
+                kind,
+                limit,
+                pending: None,
+            }));
+        };
+
+        Ok(Some(match requested {
+            Some(limit) if limit.is_within(&effective) => PlayerLimit {
+                kind,
+                limit,
+                pending: None,
+            },
+            _ => PlayerLimit {
+                kind,
+                limit: effective,
+                pending: Some(PendingLimit {
+                    limit: requested,
+                    effective_at: now + cooling_off,
+                }),
+            },
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionKind {
+    /// A short break from playing.
+    TimeOut,
+    SelfExclusion,
+}
+
+impl ExclusionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TimeOut => "time_out",
+            Self::SelfExclusion => "self_exclusion",
+        }
+    }
+
+    pub fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "time_out" => Ok(Self::TimeOut),
+            "self_exclusion" => Ok(Self::SelfExclusion),
+            _ => Err(Error::InternalServerError),
+        }
+    }
+
+    /// Range of days the exclusion can be taken for.
+    pub fn days(self) -> std::ops::RangeInclusive<i64> {
+        match self {
+            Self::TimeOut => 1..=42,
+            Self::SelfExclusion => 180..=1825,
+        }
+    }
+}
+
+/// Blocks pairing and deposits until `until`. It can be extended but never
+/// shortened.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Exclusion {
+    pub kind: ExclusionKind,
+    pub until: DateTime<Utc>,
+}
+
+/// Everything the responsible-gambling checks of a user need: their limits,
+/// exclusion and how much of the deposit and loss limits is already used.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PlayerProtection {
+    pub limits: Vec<PlayerLimit>,
+    pub exclusion: Option<Exclusion>,
+    pub deposited: Msat,
+    pub lost: Msat,
+}
+
+impl PlayerProtection {
+    pub fn limit(&self, kind: LimitKind, now: DateTime<Utc>) -> Option<Limit> {
+        self.limits
+            .iter()
+            .find(|limit| limit.kind == kind)
+            .and_then(|limit| limit.effective(now))
+    }
+
+    fn ensure_not_excluded(&self, now: DateTime<Utc>) -> Result<()> {
+        match self.exclusion {
+            Some(exclusion) if exclusion.until > now => bad_req!(format!(
+                "Your account is closed for deposits and games until {}",
+                exclusion.until.to_rfc3339()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_deposit(&self, amount: Msat, now: DateTime<Utc>) -> Result<()> {
+        self.ensure_not_excluded(now)?;
+
+        if let Some(limit) = self.limit(LimitKind::Deposit, now) {
+            if exceeds(self.deposited, amount, limit.amount)? {
+                return bad_req!(format!(
+                    "This deposit exceeds your deposit limit of {} sats",
+                    limit.amount
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The whole stake counts towards the loss limit, as it is what the game
+    /// can lose.
+    pub fn check_stake(&self, stake: Msat, now: DateTime<Utc>) -> Result<()> {
+        self.ensure_not_excluded(now)?;
+
+        if let Some(limit) = self.limit(LimitKind::Stake, now) {
+            if stake > limit.amount.to_msat()? {
+                return bad_req!(format!(
+                    "The stake exceeds your limit of {} sats per game",
+                    limit.amount
+                ));
+            }
+        }
+
+        if let Some(limit) = self.limit(LimitKind::Loss, now) {
+            if exceeds(self.lost, stake, limit.amount)? {
+                return bad_req!(format!(
+                    "Losing this game would exceed your loss limit of {} sats",
+                    limit.amount
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn exceeds(used: Msat, amount: Msat, limit: Sats) -> Result<bool> {
+    let limit = limit.to_msat()?;
+
+    Ok(used.checked_add(amount).is_none_or(|total| total > limit))
+}
+
+/// Delay before a loosened limit takes effect.
+pub fn cooling_off() -> Duration {
+    match Env::get().limit_cooling_off_hours.trim() {
+        "" => Duration::hours(DEFAULT_COOLING_OFF_HOURS),
+        hours => hours
+            .parse()
+            .ok()
+            .filter(|hours| *hours >= 0)
+            .and_then(Duration::try_hours)
+            .expect("LIMIT_COOLING_OFF_HOURS is invalid"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily(amount: i64) -> Option<Limit> {
+        Some(Limit {
+            amount: Sats::new(amount),
+            period: Some(LimitPeriod::Day),
+        })
+    }
+
+    fn limit(kind: LimitKind, limit: Option<Limit>) -> PlayerLimit {
+        PlayerLimit {
+            kind,
+            limit: limit.unwrap(),
+            pending: None,
+        }
+    }
+
+    #[test]
+    fn test_tighter_limit_applies_at_once() {
+        let now = Utc::now();
+        let current = limit(LimitKind::Deposit, daily(1000));
+
+        let changed = PlayerLimit::change(
+            LimitKind::Deposit,
+            Some(&current),
+            daily(500),
+            now,
+            Duration::hours(24),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(changed.effective(now), daily(500));
+        assert_eq!(changed.pending, None);
+    }
+
+    #[test]
+    fn test_looser_limit_waits_for_cooling_off() {
+        let now = Utc::now();
+        let weekly = Some(Limit {
+            amount: Sats::new(1000),
+            period: Some(LimitPeriod::Week),
+        });
+        let current = limit(LimitKind::Deposit, weekly);
+
+        for requested in [daily(1000), daily(2000), None] {
+            let changed = PlayerLimit::change(
+                LimitKind::Deposit,
+                Some(&current),
+                requested,
+                now,
+                Duration::hours(24),
+            )
+            .unwrap()
+            .unwrap();
+
+            assert_eq!(changed.effective(now), weekly);
+            assert_eq!(changed.effective(now + Duration::hours(24)), requested);
+        }
+    }
+
+    #[test]
+    fn test_invalid_limits() {
+        let now = Utc::now();
+        let change =
+            |kind, requested| PlayerLimit::change(kind, None, requested, now, Duration::hours(24));
+
+        assert!(change(LimitKind::Deposit, daily(0)).is_err());
+        assert!(change(LimitKind::Stake, daily(100)).is_err());
+        assert!(change(
+            LimitKind::Loss,
+            Some(Limit {
+                amount: Sats::new(100),
+                period: None,
+            })
+        )
+        .is_err());
+        assert_eq!(change(LimitKind::Loss, None).ok(), Some(None));
+    }
+
+    #[test]
+    fn test_check_stake() {
+        let now = Utc::now();
+        let protection = PlayerProtection {
+            limits: vec![
+                limit(
+                    LimitKind::Stake,
+                    Some(Limit {
+                        amount: Sats::new(100),
+                        period: None,
+                    }),
+                ),
+                limit(LimitKind::Loss, daily(300)),
+            ],
+            lost: Msat::new(250_000),
+            ..Default::default()
+        };
+
+        assert!(protection.check_stake(Msat::new(50_000), now).is_ok());
+        assert!(protection.check_stake(Msat::new(51_000), now).is_err());
+        assert!(protection.check_stake(Msat::new(101_000), now).is_err());
+    }
+
+    #[test]
+    fn test_exclusion_blocks_deposits_and_stakes() {
+        let now = Utc::now();
+        let protection = PlayerProtection {
+            exclusion: Some(Exclusion {
+                kind: ExclusionKind::TimeOut,
+                until: now + Duration::days(1),
+            }),
+            ..Default::default()
+        };
+
+        assert!(protection.check_deposit(Msat::new(1000), now).is_err());
+        assert!(protection.check_stake(Msat::ZERO, now).is_err());
+        assert!(protection
+            .check_deposit(Msat::new(1000), now + Duration::days(1))
+            .is_ok());
+    }
+}
@@ -5,9 +5,14 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use shakmaty::{san::San, Chess, Color, Outcome, Position};
 use sqlx::prelude::FromRow;
+use std::fmt;
 use std::str::FromStr;
 
 use uuid::Uuid;
+
+use super::money::Sats;
+use super::rating::RatingCategory;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PlayerColor {
     White,
@@ -29,11 +34,13 @@ impl PlayerColor {
             None => PlayerColor::random(),
         }
     }
+}
 
-    pub fn to_string(self) -> String {
+impl fmt::Display for PlayerColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::White => String::from("white_player"),
-            Self::Black => String::from("black_player"),
+            Self::White => write!(f, "white_player"),
+            Self::Black => write!(f, "black_player"),
         }
     }
 }
@@ -60,9 +67,12 @@ pub struct Game {
     pub id: Uuid,
     pub white_player: Uuid,
     pub black_player: Uuid,
-    pub bet_value: i32,
+    pub bet_value: Sats,
     pub state: GameState,
     pub moves: Vec<String>,
+    pub stake_mode: StakeMode,
+    /// Unset for games created before ratings existed, which stay unrated.
+    pub category: Option<RatingCategory>,
 }
 
 fn invalid_move() -> Error {
@@ -145,8 +155,10 @@ impl GameState {
             _ => Err(Error::InternalServerError),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let result = match self {
             GameState::Waiting => "waiting",
             GameState::Running => "running",
@@ -155,7 +167,68 @@ impl GameState {
             GameState::BlackWin => "black_win",
         };
 
-        result.to_string()
+        write!(f, "{result}")
+    }
+}
+
+/// Where the stakes of a game are held: in the players' custodial balances, or
+/// locked in hold invoices on the players' own wallets.
+#[derive(Default, Serialize, Deserialize, Clone, JsonSchema, PartialEq, Debug, Copy)]
+pub enum StakeMode {
+    #[default]
+    Custodial,
+    Hold,
+}
+
+impl StakeMode {
+    pub fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "custodial" => Ok(StakeMode::Custodial),
+            "hold" => Ok(StakeMode::Hold),
+            _ => Err(Error::InternalServerError),
+        }
+    }
+}
+
+impl fmt::Display for StakeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = match self {
+            StakeMode::Custodial => "custodial",
+            StakeMode::Hold => "hold",
+        };
+
+        write!(f, "{result}")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema, PartialEq, Debug, Copy)]
+pub enum Termination {
+    Checkmate,
+    Draw,
+    Abandoned,
+    Aborted,
+}
+
+impl Termination {
+    pub fn from_move(new_game_state: GameState) -> Option<Self> {
+        match new_game_state {
+            GameState::WhiteWin | GameState::BlackWin => Some(Self::Checkmate),
+            GameState::Draw => Some(Self::Draw),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Draw => "draw",
+            Termination::Abandoned => "abandoned",
+            Termination::Aborted => "aborted",
+        };
+
+        write!(f, "{result}")
     }
 }
 
@@ -1,13 +1,148 @@
-use super::game::PlayerColor;
+use super::game::{PlayerColor, StakeMode};
+use super::money::Sats;
+use super::rating::RatingCategory;
 use crate::http::{Error, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
 
-#[derive(Debug, PartialEq)]
+/// Version of the structured game request schema.
+pub const GAME_REQUEST_VERSION: u8 = 1;
+
+const MIN_BASE_SECONDS: u32 = 15;
+const MAX_BASE_SECONDS: u32 = 21_600;
+const MAX_INCREMENT_SECONDS: u32 = 600;
+const MAX_RATING: u16 = 4_000;
+// The pot of both stakes has to fit the ledger in millisatoshis.
+const MAX_BET_SATS: i64 = i64::MAX / 2_000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    #[default]
+    Standard,
+}
+
+impl Variant {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Variant::Standard => "standard",
+        }
+    }
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "standard" => Ok(Variant::Standard),
+            _ => Err(invalid_game_request()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorPreference {
+    White,
+    Black,
+    #[default]
+    Random,
+}
+
+/// Ratings an opponent must have, inclusive on both ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatingRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl RatingRange {
+    pub fn contains(&self, rating: f64) -> bool {
+        f64::from(self.min) <= rating && rating <= f64::from(self.max)
+    }
+}
+
+/// Structured game request, version 1.
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+#[validate(schema(function = "validate_spec", skip_on_field_errors = false))]
+pub struct GameRequestSpec {
+    pub version: u8,
+    #[serde(default)]
+    pub color: ColorPreference,
+    #[validate(range(
+        min = 15,
+        max = 21600,
+        message = "Base time must be between 15 seconds and 6 hours"
+    ))]
+    pub base_seconds: u32,
+    #[serde(default)]
+    #[validate(range(max = 600, message = "Increment must be at most 10 minutes"))]
+    pub increment_seconds: u32,
+    #[serde(default)]
+    pub variant: Variant,
+    /// Casual games leave ratings untouched. Defaults to rated.
+    #[serde(default = "rated_by_default")]
+    pub rated: bool,
+    #[validate(range(max = 4000, message = "Ratings must be at most 4000"))]
+    pub rating_min: Option<u16>,
+    #[validate(range(max = 4000, message = "Ratings must be at most 4000"))]
+    pub rating_max: Option<u16>,
+    /// Username of the only player allowed to take the seek.
+    #[validate(length(min = 1, message = "Opponent username is empty"))]
+    pub opponent: Option<String>,
+    #[validate(range(min = 0, message = "Bet value cannot be negative"))]
+    pub bet_sats: i64,
+    #[serde(default)]
+    pub stake_mode: StakeMode,
+}
+
+impl GameRequestSpec {
+    /// Message of the validation error of the first field by name, if any,
+    /// so the same request always gets the same message.
+    pub fn validation_message(&self) -> Option<String> {
+        let errs = self.validate().err()?;
+        let (_, err) = errs
+            .field_errors()
+            .into_iter()
+            .min_by_key(|(field, _)| *field)?;
+
+        err[0].message.clone().map(String::from)
+    }
+}
+
+fn rated_by_default() -> bool {
+    true
+}
+
+fn validate_spec(spec: &GameRequestSpec) -> std::result::Result<(), ValidationError> {
+    let message = if spec.version != GAME_REQUEST_VERSION {
+        "Unsupported game request version"
+    } else if spec.bet_sats > MAX_BET_SATS {
+        "Bet value is too large"
+    } else if matches!((spec.rating_min, spec.rating_max), (Some(min), Some(max)) if min > max) {
+        "Minimum rating is above the maximum one"
+    } else if spec.stake_mode == StakeMode::Hold && spec.bet_sats == 0 {
+        "Hold stakes need a bet"
+    } else {
+        return Ok(());
+    };
+
+    Err(ValidationError::new("game_request").with_message(Cow::from(message)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct GameRequest {
+    /// Canonical form of the request, shared by every spelling of it.
     pub key: String,
     pub player_color: Option<PlayerColor>,
-    pub total_time: u8,
-    pub turn_time: u8,
-    pub bet_value: i32,
+    pub base_seconds: u32,
+    pub increment_seconds: u32,
+    pub variant: Variant,
+    pub rated: bool,
+    pub rating_range: Option<RatingRange>,
+    pub opponent: Option<Uuid>,
+    pub bet_value: Sats,
+    pub stake_mode: StakeMode,
 }
 
 fn invalid_game_request() -> Error {
@@ -16,10 +151,11 @@ fn invalid_game_request() -> Error {
     }
 }
 
-fn resolve_i32(input: Option<&str>) -> Result<i32> {
+fn resolve_sats(input: Option<&str>) -> Result<Sats> {
     input
         .ok_or(invalid_game_request())?
-        .parse::<i32>()
+        .parse::<i64>()
+        .map(Sats::new)
         .map_err(|_| invalid_game_request())
 }
 
@@ -30,6 +166,13 @@ fn resolve_u8(input: Option<&str>) -> Result<u8> {
         .map_err(|_| invalid_game_request())
 }
 
+fn resolve_u32(input: Option<&str>) -> Result<u32> {
+    input
+        .ok_or(invalid_game_request())?
+        .parse::<u32>()
+        .map_err(|_| invalid_game_request())
+}
+
 fn resolve_player_color(input: Option<&str>) -> Result<Option<PlayerColor>> {
     let input = input.ok_or(invalid_game_request())?;
 
@@ -41,35 +184,239 @@ fn resolve_player_color(input: Option<&str>) -> Result<Option<PlayerColor>> {
     }
 }
 
+fn resolve_stake_mode(input: Option<&str>) -> Result<StakeMode> {
+    match input {
+        None => Ok(StakeMode::Custodial),
+        Some("h") => Ok(StakeMode::Hold),
+        _ => Err(invalid_game_request()),
+    }
+}
+
+fn resolve_rating_range(input: Option<&str>) -> Result<Option<RatingRange>> {
+    match input.ok_or(invalid_game_request())? {
+        "*" => Ok(None),
+        range => {
+            let (min, max) = range.split_once('-').ok_or(invalid_game_request())?;
+
+            Ok(Some(RatingRange {
+                min: min.parse().map_err(|_| invalid_game_request())?,
+                max: max.parse().map_err(|_| invalid_game_request())?,
+            }))
+        }
+    }
+}
+
+fn resolve_opponent(input: Option<&str>) -> Result<Option<Uuid>> {
+    match input.ok_or(invalid_game_request())? {
+        "*" => Ok(None),
+        opponent => Uuid::parse_str(opponent)
+            .map(Some)
+            .map_err(|_| invalid_game_request()),
+    }
+}
+
 impl GameRequest {
+    /// Whether both requests are for the same time control, variant, rating
+    /// mode and stake.
+    pub fn same_terms(&self, other: &GameRequest) -> bool {
+        self.base_seconds == other.base_seconds
+            && self.increment_seconds == other.increment_seconds
+            && self.variant == other.variant
+            && self.rated == other.rated
+            && self.bet_value == other.bet_value
+            && self.stake_mode == other.stake_mode
+    }
+
+    pub fn category(&self) -> RatingCategory {
+        RatingCategory::from_time_control(self.base_seconds, self.increment_seconds)
+    }
+
+    /// Parses either a canonical key or a legacy `color-total-turn-bet` one,
+    /// where the total time is in minutes and the turn time in seconds. Both
+    /// take an `-h` suffix for games whose stakes are locked in hold invoices.
     pub fn from_str(key: &str) -> Result<Self> {
+        match key.strip_prefix("v1:") {
+            Some(canonical) => Self::from_canonical(canonical),
+            None => Self::from_legacy(key),
+        }
+    }
+
+    fn from_legacy(key: &str) -> Result<Self> {
         let mut result = key.split("-");
 
         let player_color = resolve_player_color(result.next())?;
         let total_time = resolve_u8(result.next())?;
         let turn_time = resolve_u8(result.next())?;
-        let bet_value = resolve_i32(result.next())?;
+        let bet_value = resolve_sats(result.next())?;
+        let stake_mode = resolve_stake_mode(result.next())?;
+
+        if total_time == 0 || result.next().is_some() {
+            return Err(invalid_game_request());
+        }
+
+        Self::build(
+            player_color,
+            u32::from(total_time) * 60,
+            u32::from(turn_time),
+            Variant::Standard,
+            true,
+            None,
+            None,
+            bet_value,
+            stake_mode,
+        )
+    }
 
-        if total_time <= 0 || bet_value < 0 {
+    /// `{color}:{base}+{increment}:{variant}:{rated|casual}:{range|*}:{opponent|*}:{bet}[-h]`
+    fn from_canonical(key: &str) -> Result<Self> {
+        let mut result = key.split(':');
+
+        let player_color = resolve_player_color(result.next())?;
+        let (base_seconds, increment_seconds) = result
+            .next()
+            .and_then(|time| time.split_once('+'))
+            .ok_or(invalid_game_request())?;
+        let base_seconds = resolve_u32(Some(base_seconds))?;
+        let increment_seconds = resolve_u32(Some(increment_seconds))?;
+        let variant = Variant::from_str(result.next().ok_or(invalid_game_request())?)?;
+        let rated = match result.next() {
+            Some("rated") => true,
+            Some("casual") => false,
+            _ => return Err(invalid_game_request()),
+        };
+        let rating_range = resolve_rating_range(result.next())?;
+        let opponent = resolve_opponent(result.next())?;
+        let mut stake = result.next().ok_or(invalid_game_request())?.split('-');
+        let bet_value = resolve_sats(stake.next())?;
+        let stake_mode = resolve_stake_mode(stake.next())?;
+
+        if result.next().is_some() || stake.next().is_some() {
             return Err(invalid_game_request());
         }
 
-        Ok(Self {
-            key: key.to_string(),
-            total_time,
-            turn_time,
+        Self::build(
+            player_color,
+            base_seconds,
+            increment_seconds,
+            variant,
+            rated,
+            rating_range,
+            opponent,
             bet_value,
+            stake_mode,
+        )
+    }
+
+    /// Builds the request of a validated spec, with `opponent` resolved from
+    /// the username it names.
+    pub fn from_spec(spec: &GameRequestSpec, opponent: Option<Uuid>) -> Result<Self> {
+        let player_color = match spec.color {
+            ColorPreference::White => Some(PlayerColor::White),
+            ColorPreference::Black => Some(PlayerColor::Black),
+            ColorPreference::Random => None,
+        };
+
+        let rating_range = match (spec.rating_min, spec.rating_max) {
+            (None, None) => None,
+            (min, max) => Some(RatingRange {
+                min: min.unwrap_or(0),
+                max: max.unwrap_or(MAX_RATING),
+            }),
+        };
+
+        Self::build(
             player_color,
-        })
+            spec.base_seconds,
+            spec.increment_seconds,
+            spec.variant,
+            spec.rated,
+            rating_range,
+            opponent,
+            Sats::new(spec.bet_sats),
+            spec.stake_mode,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        player_color: Option<PlayerColor>,
+        base_seconds: u32,
+        increment_seconds: u32,
+        variant: Variant,
+        rated: bool,
+        rating_range: Option<RatingRange>,
+        opponent: Option<Uuid>,
+        bet_value: Sats,
+        stake_mode: StakeMode,
+    ) -> Result<Self> {
+        let range_is_valid =
+            rating_range.is_none_or(|range| range.min <= range.max && range.max <= MAX_RATING);
+
+        if !(MIN_BASE_SECONDS..=MAX_BASE_SECONDS).contains(&base_seconds)
+            || increment_seconds > MAX_INCREMENT_SECONDS
+            || !range_is_valid
+            || bet_value < Sats::ZERO
+            || bet_value.as_i64() > MAX_BET_SATS
+            || (stake_mode == StakeMode::Hold && bet_value == Sats::ZERO)
+        {
+            return Err(invalid_game_request());
+        }
+
+        let mut request = Self {
+            key: String::new(),
+            player_color,
+            base_seconds,
+            increment_seconds,
+            variant,
+            rated,
+            rating_range,
+            opponent,
+            bet_value,
+            stake_mode,
+        };
+        request.key = request.canonical_key();
+
+        Ok(request)
+    }
+
+    fn canonical_key(&self) -> String {
+        let color = match self.player_color {
+            Some(PlayerColor::White) => "w",
+            Some(PlayerColor::Black) => "b",
+            None => "n",
+        };
+        let rated = if self.rated { "rated" } else { "casual" };
+        let range = self.rating_range.map_or(String::from("*"), |range| {
+            format!("{}-{}", range.min, range.max)
+        });
+        let opponent = self
+            .opponent
+            .map_or(String::from("*"), |opponent| opponent.to_string());
+        let hold = match self.stake_mode {
+            StakeMode::Custodial => "",
+            StakeMode::Hold => "-h",
+        };
+
+        format!(
+            "v1:{color}:{}+{}:{}:{rated}:{range}:{opponent}:{}{hold}",
+            self.base_seconds,
+            self.increment_seconds,
+            self.variant.as_str(),
+            self.bet_value.as_i64(),
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::models::game::PlayerColor;
+    use crate::models::game::{PlayerColor, StakeMode};
 
     use super::*;
 
+    fn spec(json: &str) -> GameRequestSpec {
+        serde_json::from_str(json).unwrap()
+    }
+
     #[test]
     fn test_request_key_parsing_1() {
         let input = "";
@@ -87,11 +434,16 @@ mod tests {
         assert_eq!(
             result,
             Some(GameRequest {
-                key: input.to_string(),
+                key: String::from("v1:w:600+0:standard:rated:*:*:0"),
                 player_color: Some(PlayerColor::White),
-                total_time: 10,
-                turn_time: 0,
-                bet_value: 0,
+                base_seconds: 600,
+                increment_seconds: 0,
+                variant: Variant::Standard,
+                rated: true,
+                rating_range: None,
+                opponent: None,
+                bet_value: Sats::ZERO,
+                stake_mode: StakeMode::Custodial,
             })
         )
     }
@@ -107,18 +459,99 @@ mod tests {
     #[test]
     fn test_request_key_parsing_4() {
         let input = "b-30-10-10000";
-        let result = GameRequest::from_str(input).ok();
+        let result = GameRequest::from_str(input).unwrap();
+
+        assert_eq!(result.player_color, Some(PlayerColor::Black));
+        assert_eq!(result.base_seconds, 1800);
+        assert_eq!(result.increment_seconds, 10);
+        assert_eq!(result.bet_value, Sats::new(10000));
+        assert_eq!(result.stake_mode, StakeMode::Custodial);
+    }
+
+    #[test]
+    fn test_request_key_parsing_hold() {
+        let result = GameRequest::from_str("n-10-0-100-h").unwrap();
+
+        assert_eq!(result.stake_mode, StakeMode::Hold);
+        assert_eq!(result.key, "v1:n:600+0:standard:rated:*:*:100-h");
+        assert!(GameRequest::from_str("n-10-0-0-h").is_err());
+        assert!(GameRequest::from_str("n-10-0-100-x").is_err());
+    }
+
+    #[test]
+    fn test_canonical_key_round_trip() {
+        let request = GameRequest::from_spec(
+            &spec(
+                r#"{ "version": 1, "color": "black", "base_seconds": 30, "increment_seconds": 300,
+                     "rated": false, "rating_min": 1200, "bet_sats": 50, "stake_mode": "Hold" }"#,
+            ),
+            Some(Uuid::nil()),
+        )
+        .unwrap();
 
-        assert!(result.is_some());
         assert_eq!(
-            result,
-            Some(GameRequest {
-                key: input.to_string(),
-                player_color: Some(PlayerColor::Black),
-                total_time: 30,
-                turn_time: 10,
-                bet_value: 10000,
-            })
+            request.key,
+            format!("v1:b:30+300:standard:casual:1200-4000:{}:50-h", Uuid::nil())
+        );
+        assert_eq!(GameRequest::from_str(&request.key).unwrap(), request);
+    }
+
+    #[test]
+    fn test_legacy_and_structured_requests_share_a_key() {
+        let structured = GameRequest::from_spec(
+            &spec(r#"{ "version": 1, "base_seconds": 600, "bet_sats": 10 }"#),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            structured.key,
+            GameRequest::from_str("n-10-0-10").unwrap().key
+        );
+    }
+
+    #[test]
+    fn test_spec_validation() {
+        assert!(
+            spec(r#"{ "version": 1, "base_seconds": 60, "bet_sats": 0 }"#)
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            spec(r#"{ "version": 2, "base_seconds": 60, "bet_sats": 0 }"#)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            spec(r#"{ "version": 1, "base_seconds": 5, "bet_sats": 0 }"#)
+                .validate()
+                .is_err()
+        );
+        assert!(spec(
+            r#"{ "version": 1, "base_seconds": 60, "rating_min": 1800, "rating_max": 1200, "bet_sats": 0 }"#
+        )
+        .validate()
+        .is_err());
+        assert!(spec(
+            r#"{ "version": 1, "base_seconds": 60, "bet_sats": 0, "stake_mode": "Hold" }"#
         )
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_validation_message_is_stable() {
+        let invalid = spec(
+            r#"{ "version": 1, "base_seconds": 5, "increment_seconds": 900, "rating_max": 5000, "opponent": "", "bet_sats": -1 }"#,
+        );
+
+        for _ in 0..10 {
+            assert_eq!(
+                invalid.validation_message(),
+                Some(String::from(
+                    "Base time must be between 15 seconds and 6 hours"
+                ))
+            );
+        }
     }
 }
@@ -0,0 +1,106 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use chrono::{DateTime, Utc};
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescriptionRef};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use super::money::Msat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Pending,
+    Paid,
+    Expired,
+}
+
+impl InvoiceStatus {
+    pub fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "pending" => Ok(Self::Pending),
+            "paid" => Ok(Self::Paid),
+            "expired" => Ok(Self::Expired),
+            _ => Err(Error::InternalServerError),
+        }
+    }
+}
+
+/// The fields of a BOLT11 payment request the wallet keeps track of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedInvoice {
+    pub payment_request: String,
+    pub payment_hash: String,
+    pub amount: Msat,
+    pub description_hash: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl DecodedInvoice {
+    /// Amounts keep their millisatoshi precision. Invoices without an amount
+    /// decode to 0.
+    pub fn decode(payment_request: &str) -> Result<Self> {
+        let Ok(invoice) = Bolt11Invoice::from_str(payment_request.trim()) else {
+            return bad_req!("Invalid invoice input");
+        };
+
+        let amount_msat = invoice.amount_milli_satoshis().unwrap_or(0);
+        let expires_at = invoice
+            .expires_at()
+            .and_then(|expires_at| DateTime::from_timestamp(expires_at.as_secs() as i64, 0));
+        let description_hash = match invoice.description() {
+            Bolt11InvoiceDescriptionRef::Hash(hash) => Some(hash.0.to_string()),
+            Bolt11InvoiceDescriptionRef::Direct(_) => None,
+        };
+
+        let (Ok(amount_msat), Some(expires_at)) = (i64::try_from(amount_msat), expires_at) else {
+            return bad_req!("Invalid invoice input");
+        };
+
+        Ok(Self {
+            payment_request: invoice.to_string(),
+            payment_hash: invoice.payment_hash().to_string(),
+            amount: Msat::new(amount_msat),
+            description_hash,
+            expires_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BOLT11 specification test vector.
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+    #[test]
+    fn test_decode_invoice() {
+        let invoice = DecodedInvoice::decode(INVOICE).unwrap();
+
+        assert_eq!(
+            invoice.payment_hash,
+            "0001020304050607080900010203040506070809000102030405060708090102"
+        );
+        assert_eq!(invoice.amount, Msat::new(250_000_000));
+        assert_eq!(invoice.description_hash, None);
+        assert_eq!(invoice.expires_at.timestamp(), 1496314658 + 60);
+    }
+
+    #[test]
+    fn test_decode_description_hash() {
+        // BOLT11 specification test vector with a hashed description.
+        let invoice = DecodedInvoice::decode("lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqs9qrsgq7ea976txfraylvgzuxs8kgcw23ezlrszfnh8r6qtfpr6cxga50aj6txm9rxrydzd06dfeawfk6swupvz4erwnyutnjq7x39ymw6j38gp7ynn44").unwrap();
+
+        assert_eq!(
+            invoice.description_hash.as_deref(),
+            Some("3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1")
+        );
+    }
+
+    #[test]
+    fn test_decode_invalid_invoice() {
+        assert!(DecodedInvoice::decode("lnbc1").is_err());
+    }
+}
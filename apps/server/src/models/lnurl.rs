@@ -0,0 +1,265 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use crate::models::{is_valid_username, Msat};
+use bech32::{Bech32, Hrp};
+use reqwest::Url;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const LNURL_HRP: Hrp = Hrp::parse_unchecked("lnurl");
+
+/// Encodes a URL as a bech32 LNURL (LUD-01). Uppercase keeps QR codes small.
+pub fn encode_lnurl(url: &str) -> Result<String> {
+    bech32::encode_upper::<Bech32>(LNURL_HRP, url.as_bytes())
+        .map_err(|_| Error::InternalServerError)
+}
+
+pub fn decode_lnurl(lnurl: &str) -> Result<String> {
+    let Ok((hrp, data)) = bech32::decode(lnurl) else {
+        return bad_req!("Invalid LNURL");
+    };
+
+    if hrp != LNURL_HRP {
+        return bad_req!("Invalid LNURL");
+    }
+
+    String::from_utf8(data).or(bad_req!("Invalid LNURL"))
+}
+
+/// Only HTTPS URLs, or plain HTTP to onion services, may be used to reach
+/// LNURL services (LUD-01).
+pub fn secure_url(url: &str) -> Result<Url> {
+    let Ok(url) = Url::parse(url) else {
+        return bad_req!("Invalid LNURL");
+    };
+
+    let onion = url.host_str().is_some_and(|host| host.ends_with(".onion"));
+
+    match url.scheme() {
+        "https" => Ok(url),
+        "http" if onion => Ok(url),
+        _ => bad_req!("LNURL services must be reached over HTTPS"),
+    }
+}
+
+/// Resolves a Lightning Address or a bech32 LNURL-pay string into the URL of
+/// its pay request.
+pub fn pay_request_url(destination: &str) -> Result<Url> {
+    let destination = destination.trim();
+    let destination = destination
+        .strip_prefix("lightning:")
+        .or_else(|| destination.strip_prefix("LIGHTNING:"))
+        .unwrap_or(destination);
+
+    let url = match destination.split_once('@') {
+        Some((username, domain)) if !username.is_empty() && !domain.is_empty() => {
+            let scheme = if domain.ends_with(".onion") {
+                "http"
+            } else {
+                "https"
+            };
+
+            format!("{scheme}://{domain}/.well-known/lnurlp/{username}")
+        }
+        Some(_) => return bad_req!("Invalid Lightning Address"),
+        None => decode_lnurl(destination)?,
+    };
+
+    secure_url(&url)
+}
+
+/// Joins `segments` to the public URL of the server, escaping each of them.
+pub fn public_url(base: &str, segments: &[&str]) -> Result<String> {
+    let mut url = Url::parse(base).map_err(|_| Error::InternalServerError)?;
+
+    url.path_segments_mut()
+        .map_err(|_| Error::InternalServerError)?
+        .pop_if_empty()
+        .extend(segments);
+
+    Ok(url.to_string())
+}
+
+/// Lightning Address (LUD-16) of `username` on the host of the public URL.
+pub fn lightning_address(base: &str, username: &str) -> Option<String> {
+    let url = Url::parse(base).ok()?;
+    let domain = url.host_str()?;
+
+    is_valid_username(username).then(|| format!("{}@{domain}", username.to_lowercase()))
+}
+
+/// Metadata of a pay request (LUD-06). Invoices for it commit to its hash.
+pub fn pay_metadata(description: &str, identifier: Option<&str>) -> String {
+    let mut metadata = vec![["text/plain", description]];
+
+    if let Some(identifier) = identifier {
+        metadata.push(["text/identifier", identifier]);
+    }
+
+    serde_json::json!(metadata).to_string()
+}
+
+pub fn description_hash(metadata: &str) -> String {
+    format!("{:x}", Sha256::digest(metadata.as_bytes()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum LnurlTag {
+    PayRequest,
+    WithdrawRequest,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PayRequest {
+    pub tag: LnurlTag,
+    pub callback: String,
+    pub min_sendable: Msat,
+    pub max_sendable: Msat,
+    pub metadata: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PayRequestInvoice {
+    pub pr: String,
+    pub routes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawRequest {
+    pub tag: LnurlTag,
+    pub callback: String,
+    pub k1: String,
+    pub default_description: String,
+    pub min_withdrawable: Msat,
+    pub max_withdrawable: Msat,
+}
+
+/// Status object LNURL wallets expect from callbacks and on errors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "UPPERCASE")]
+pub enum LnurlStatus {
+    Ok,
+    Error { reason: String },
+}
+
+impl From<Error> for LnurlStatus {
+    fn from(error: Error) -> Self {
+        Self::Error {
+            reason: error.to_string(),
+        }
+    }
+}
+
+/// LNURL endpoints answer errors with 200 and a status object instead of the
+/// usual error body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum LnurlResponse<T> {
+    Ok(T),
+    Status(LnurlStatus),
+}
+
+impl<T> From<Result<T>> for LnurlResponse<T> {
+    fn from(result: Result<T>) -> Self {
+        match result {
+            Ok(response) => Self::Ok(response),
+            Err(error) => Self::Status(error.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_lnurl() {
+        // LUD-01 example.
+        assert_eq!(
+            encode_lnurl("https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd340693afabe04be7b0ccd178df").unwrap(),
+            "LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS"
+        );
+    }
+
+    #[test]
+    fn test_decode_lnurl() {
+        let url = "https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd340693afabe04be7b0ccd178df";
+
+        assert_eq!(decode_lnurl(&encode_lnurl(url).unwrap()).unwrap(), url);
+        assert!(decode_lnurl("lnbc1").is_err());
+    }
+
+    #[test]
+    fn test_pay_request_url() {
+        assert_eq!(
+            pay_request_url("lightning:alice@chesu.com")
+                .unwrap()
+                .as_str(),
+            "https://chesu.com/.well-known/lnurlp/alice"
+        );
+        assert_eq!(
+            pay_request_url(&encode_lnurl("https://chesu.com/lnurl/pay/alice").unwrap())
+                .unwrap()
+                .as_str(),
+            "https://chesu.com/lnurl/pay/alice"
+        );
+    }
+
+    #[test]
+    fn test_pay_request_url_requires_https() {
+        assert!(pay_request_url(&encode_lnurl("http://chesu.com/lnurl").unwrap()).is_err());
+        assert!(pay_request_url("@chesu.com").is_err());
+    }
+
+    #[test]
+    fn test_public_url() {
+        assert_eq!(
+            public_url("https://chesu.com/", &["lnurl", "pay", "jo ana"]).unwrap(),
+            "https://chesu.com/lnurl/pay/jo%20ana"
+        );
+    }
+
+    #[test]
+    fn test_lightning_address() {
+        assert_eq!(
+            lightning_address("https://chesu.com", "Alice").as_deref(),
+            Some("alice@chesu.com")
+        );
+        assert_eq!(lightning_address("https://chesu.com", "al ice"), None);
+    }
+
+    #[test]
+    fn test_pay_metadata_with_identifier() {
+        assert_eq!(
+            pay_metadata("Deposit", Some("alice@chesu.com")),
+            r#"[["text/plain","Deposit"],["text/identifier","alice@chesu.com"]]"#
+        );
+    }
+
+    #[test]
+    fn test_pay_metadata() {
+        let metadata = pay_metadata("Deposit to alice", None);
+
+        assert_eq!(metadata, r#"[["text/plain","Deposit to alice"]]"#);
+        assert_eq!(
+            description_hash(&metadata),
+            "84f63ab7d980013460e5da25de4b4886461ebebcd82f62432b83572d158741f4"
+        );
+    }
+
+    #[test]
+    fn test_lnurl_error_status() {
+        let response = LnurlResponse::<PayRequestInvoice>::from(Err(Error::BadRequest {
+            message: String::from("Amount is out of range"),
+        }));
+
+        assert_eq!(
+            serde_json::to_value(response).unwrap(),
+            serde_json::json!({ "status": "ERROR", "reason": "Amount is out of range" })
+        );
+    }
+}
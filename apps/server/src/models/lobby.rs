@@ -0,0 +1,95 @@
+use super::event::Event;
+use crate::states::lobby;
+use mockall::automock;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Channels of the players connected to the lobby socket, by player id.
+pub type LobbyChannels = Arc<Mutex<HashMap<Uuid, broadcast::Sender<String>>>>;
+
+#[automock]
+pub trait LobbyTrait: Send + Sync {
+    /// Subscribes a new lobby socket of the player to the channel relayed to
+    /// all of them.
+    fn join(&self, player_id: Uuid) -> (broadcast::Sender<String>, broadcast::Receiver<String>);
+    /// Drops the channel of the player once none of their lobby sockets is
+    /// open.
+    fn leave(&self, player_id: Uuid);
+    /// Sends the event to the lobby sockets of the player, if any is open.
+    fn notify(&self, player_id: Uuid, event: Event);
+}
+
+#[derive(Debug)]
+pub struct Lobby {
+    channels: LobbyChannels,
+}
+
+impl Lobby {
+    pub fn new() -> Self {
+        Self {
+            channels: lobby::get(),
+        }
+    }
+}
+
+impl LobbyTrait for Lobby {
+    fn join(&self, player_id: Uuid) -> (broadcast::Sender<String>, broadcast::Receiver<String>) {
+        let mut channels = self.channels.lock().unwrap();
+        let tx = channels
+            .entry(player_id)
+            .or_insert_with(|| broadcast::channel(100).0);
+
+        (tx.clone(), tx.subscribe())
+    }
+
+    fn leave(&self, player_id: Uuid) {
+        let mut channels = self.channels.lock().unwrap();
+
+        if channels
+            .get(&player_id)
+            .is_some_and(|tx| tx.receiver_count() == 0)
+        {
+            channels.remove(&player_id);
+        }
+    }
+
+    fn notify(&self, player_id: Uuid, event: Event) {
+        let mut channels = self.channels.lock().unwrap();
+
+        if let Some(tx) = channels.get(&player_id) {
+            if tx.send(event.json()).is_err() {
+                channels.remove(&player_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_is_dropped_after_last_socket() {
+        let lobby = Lobby {
+            channels: Default::default(),
+        };
+        let player_id = Uuid::new_v4();
+
+        let (_, first) = lobby.join(player_id);
+        let (_, second) = lobby.join(player_id);
+
+        drop(first);
+        lobby.leave(player_id);
+
+        assert!(lobby.channels.lock().unwrap().contains_key(&player_id));
+
+        drop(second);
+        lobby.leave(player_id);
+
+        assert!(!lobby.channels.lock().unwrap().contains_key(&player_id));
+    }
+}
@@ -0,0 +1,272 @@
+use super::game::{PlayerColor, StakeMode};
+use super::game_request::{GameRequest, RatingRange, Variant};
+use super::money::Sats;
+use crate::Env;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Rating difference a seek accepts as soon as it is created.
+const BASE_RATING_WINDOW: f64 = 100.0;
+/// How much the window widens for every second a seek waits.
+const RATING_WINDOW_GROWTH: f64 = 10.0;
+const MAX_RATING_WINDOW: f64 = 800.0;
+const DEFAULT_MAX_CONCURRENT_GAMES: usize = 1;
+const DEFAULT_SEEK_TTL_MINUTES: i64 = 10;
+const DEFAULT_INVITE_TTL_MINUTES: i64 = 1_440;
+const DEFAULT_CHALLENGE_TTL_MINUTES: i64 = 1_440;
+
+/// A player waiting in the matchmaking pool for an opponent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seek {
+    pub key: String,
+    pub player_id: Uuid,
+    pub player_color: Option<PlayerColor>,
+    pub base_seconds: u32,
+    pub increment_seconds: u32,
+    pub variant: Variant,
+    pub rated: bool,
+    pub rating_range: Option<RatingRange>,
+    pub opponent: Option<Uuid>,
+    pub bet_value: Sats,
+    pub stake_mode: StakeMode,
+    /// Rating of the player in the category of the time control.
+    pub rating: f64,
+    /// Set for private rooms, which are only joined through their invite
+    /// link and never paired by matchmaking.
+    pub invite_token: Option<String>,
+    /// Set once the stake of the seek is taken. Until then it can be neither
+    /// paired nor canceled.
+    pub staked: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Seek {
+    pub fn new(player_id: Uuid, game_request: &GameRequest, rating: f64) -> Self {
+        Self {
+            key: game_request.key.clone(),
+            player_id,
+            player_color: game_request.player_color,
+            base_seconds: game_request.base_seconds,
+            increment_seconds: game_request.increment_seconds,
+            variant: game_request.variant,
+            rated: game_request.rated,
+            rating_range: game_request.rating_range,
+            opponent: game_request.opponent,
+            bet_value: game_request.bet_value,
+            stake_mode: game_request.stake_mode,
+            rating,
+            invite_token: None,
+            staked: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn rating_window(&self, now: DateTime<Utc>) -> f64 {
+        let waited = (now - self.created_at).num_milliseconds().max(0) as f64 / 1000.0;
+
+        (BASE_RATING_WINDOW + RATING_WINDOW_GROWTH * waited).min(MAX_RATING_WINDOW)
+    }
+
+    /// Whether `other` can take this waiting seek. Unless it asks for a rating
+    /// range, the window of the waiting seek decides it, so long waits open
+    /// it to more players. A seek asking for an opponent only pairs with them.
+    pub fn accepts(&self, other: &Seek, now: DateTime<Utc>) -> bool {
+        let colors_match = !matches!(
+            (self.player_color, other.player_color),
+            (Some(PlayerColor::White), Some(PlayerColor::White))
+                | (Some(PlayerColor::Black), Some(PlayerColor::Black))
+        );
+
+        let rating_matches = match self.rating_range {
+            Some(range) => range.contains(other.rating),
+            None => (self.rating - other.rating).abs() <= self.rating_window(now),
+        } && other
+            .rating_range
+            .is_none_or(|range| range.contains(self.rating));
+
+        let opponents_match = self.opponent.is_none_or(|id| id == other.player_id)
+            && other.opponent.is_none_or(|id| id == self.player_id);
+
+        colors_match
+            && self.player_id != other.player_id
+            && self.base_seconds == other.base_seconds
+            && self.increment_seconds == other.increment_seconds
+            && self.variant == other.variant
+            && self.rated == other.rated
+            && self.bet_value == other.bet_value
+            && self.stake_mode == other.stake_mode
+            && rating_matches
+            && opponents_match
+    }
+}
+
+/// Room id of the waiting seek `seek` should be paired with: the closest
+/// rating first, then the longest waiting.
+pub fn find_match(pool: &HashMap<Uuid, Seek>, seek: &Seek, now: DateTime<Utc>) -> Option<Uuid> {
+    pool.iter()
+        .filter(|(_, waiting)| {
+            waiting.staked && waiting.invite_token.is_none() && waiting.accepts(seek, now)
+        })
+        .min_by(|(_, a), (_, b)| {
+            let distance = |waiting: &Seek| (waiting.rating - seek.rating).abs();
+
+            distance(a)
+                .total_cmp(&distance(b))
+                .then(a.created_at.cmp(&b.created_at))
+        })
+        .map(|(room_id, _)| *room_id)
+}
+
+/// Games a user can play at the same time.
+pub fn max_concurrent_games() -> usize {
+    match Env::get().max_concurrent_games.trim() {
+        "" => DEFAULT_MAX_CONCURRENT_GAMES,
+        games => games
+            .parse()
+            .ok()
+            .filter(|games| *games > 0)
+            .expect("MAX_CONCURRENT_GAMES is invalid"),
+    }
+}
+
+fn parse_ttl(minutes: &str, default_minutes: i64, name: &str) -> Duration {
+    match minutes.trim() {
+        "" => Duration::minutes(default_minutes),
+        minutes => minutes
+            .parse()
+            .ok()
+            .filter(|minutes| *minutes > 0)
+            .and_then(Duration::try_minutes)
+            .unwrap_or_else(|| panic!("{name} is invalid")),
+    }
+}
+
+/// How long a seek waits for an opponent before it expires.
+pub fn seek_ttl() -> Duration {
+    parse_ttl(
+        &Env::get().seek_ttl_minutes,
+        DEFAULT_SEEK_TTL_MINUTES,
+        "SEEK_TTL_MINUTES",
+    )
+}
+
+/// How long the invite link of a private game stays open. Friends often
+/// open it much later than a matchmaking seek would wait, so it has its own.
+pub fn invite_ttl() -> Duration {
+    parse_ttl(
+        &Env::get().invite_ttl_minutes,
+        DEFAULT_INVITE_TTL_MINUTES,
+        "INVITE_TTL_MINUTES",
+    )
+}
+
+/// How long a challenge waits for the challenged user to answer.
+pub fn challenge_ttl() -> Duration {
+    parse_ttl(
+        &Env::get().challenge_ttl_minutes,
+        DEFAULT_CHALLENGE_TTL_MINUTES,
+        "CHALLENGE_TTL_MINUTES",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn seek(key: &str, rating: f64) -> Seek {
+        Seek::new(Uuid::new_v4(), &GameRequest::from_str(key).unwrap(), rating)
+    }
+
+    #[test]
+    fn test_compatible_colors_and_time_controls() {
+        let now = Utc::now();
+        let white = seek("w-10-0-0", 1500.0);
+
+        assert!(white.accepts(&seek("b-10-0-0", 1500.0), now));
+        assert!(white.accepts(&seek("n-10-0-0", 1500.0), now));
+        assert!(!white.accepts(&seek("w-10-0-0", 1500.0), now));
+        assert!(!white.accepts(&seek("b-10-5-0", 1500.0), now));
+        assert!(!white.accepts(&seek("b-10-0-10", 1500.0), now));
+        assert!(!white.accepts(
+            &Seek {
+                player_color: Some(PlayerColor::Black),
+                ..white.clone()
+            },
+            now
+        ));
+    }
+
+    #[test]
+    fn test_rating_window_widens_while_waiting() {
+        let now = Utc::now();
+        let waiting = seek("n-10-0-0", 1200.0);
+        let stronger = seek("n-10-0-0", 1450.0);
+
+        assert!(waiting.accepts(&seek("n-10-0-0", 1250.0), now));
+        assert!(!waiting.accepts(&stronger, now));
+        assert!(waiting.accepts(&stronger, now + Duration::seconds(30)));
+        assert!(!waiting.accepts(&seek("n-10-0-0", 2100.0), now + Duration::hours(1)));
+    }
+
+    fn staked(key: &str, rating: f64) -> Seek {
+        Seek {
+            staked: true,
+            ..seek(key, rating)
+        }
+    }
+
+    #[test]
+    fn test_find_closest_rating() {
+        let now = Utc::now();
+        let pool = HashMap::from([
+            (Uuid::from_u128(1), staked("n-10-0-0", 1400.0)),
+            (Uuid::from_u128(2), staked("n-10-0-0", 1480.0)),
+            (Uuid::from_u128(3), staked("w-10-0-0", 1500.0)),
+            (Uuid::from_u128(4), seek("n-10-0-0", 1500.0)),
+        ]);
+
+        assert_eq!(
+            find_match(&pool, &seek("w-10-0-0", 1500.0), now),
+            Some(Uuid::from_u128(2))
+        );
+        assert_eq!(find_match(&pool, &seek("w-3-0-0", 1500.0), now), None);
+
+        let private = HashMap::from([(
+            Uuid::from_u128(1),
+            Seek {
+                invite_token: Some(String::from("token")),
+                ..staked("n-10-0-0", 1500.0)
+            },
+        )]);
+
+        assert_eq!(find_match(&private, &seek("n-10-0-0", 1500.0), now), None);
+    }
+
+    #[test]
+    fn test_rating_ranges_and_opponents() {
+        let now = Utc::now();
+        let ranged = Seek {
+            rating_range: Some(RatingRange {
+                min: 1800,
+                max: 2000,
+            }),
+            ..seek("n-10-0-0", 1500.0)
+        };
+
+        assert!(ranged.accepts(&seek("n-10-0-0", 1900.0), now));
+        assert!(!ranged.accepts(&seek("n-10-0-0", 1550.0), now));
+        assert!(!seek("n-10-0-0", 1900.0).accepts(&ranged, now));
+
+        let friend = seek("n-10-0-0", 1500.0);
+        let challenge = Seek {
+            opponent: Some(friend.player_id),
+            ..seek("n-10-0-0", 1500.0)
+        };
+
+        assert!(challenge.accepts(&friend, now));
+        assert!(friend.accepts(&challenge, now));
+        assert!(!challenge.accepts(&seek("n-10-0-0", 1500.0), now));
+    }
+}
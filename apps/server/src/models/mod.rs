@@ -17,3 +17,33 @@ pub use game::*;
 
 mod rooms_manager;
 pub use rooms_manager::*;
+
+mod money;
+pub use money::*;
+
+mod rake;
+pub use rake::*;
+
+mod withdrawal_limits;
+pub use withdrawal_limits::*;
+
+mod gambling_limits;
+pub use gambling_limits::*;
+
+mod invoice;
+pub use invoice::*;
+
+mod lnurl;
+pub use lnurl::*;
+
+mod reconciliation;
+pub use reconciliation::*;
+
+mod rating;
+pub use rating::*;
+
+mod matchmaking;
+pub use matchmaking::*;
+
+mod lobby;
+pub use lobby::*;
@@ -0,0 +1,186 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const MSAT_PER_SAT: i64 = 1000;
+
+/// An amount in millisatoshis, the unit every balance and transfer of the
+/// ledger is kept in. Arithmetic is checked, so an overflow is an error
+/// instead of a wrapped balance.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct Msat(i64);
+
+/// An amount in whole sats, the unit users enter amounts in and the Lightning
+/// backend creates invoices with.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    sqlx::Type,
+)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct Sats(i64);
+
+impl Msat {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn new(msat: i64) -> Self {
+        Self(msat)
+    }
+
+    pub const fn as_i64(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Self)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        self.0.checked_neg().map(Self)
+    }
+
+    /// `basis_points` of the amount, rounded down to the millisatoshi.
+    pub fn basis_points(self, basis_points: i64) -> Option<Self> {
+        let amount = i128::from(self.0) * i128::from(basis_points) / 10_000;
+
+        i64::try_from(amount).ok().map(Self)
+    }
+
+    /// Rounds down, for amounts that must not exceed what is available.
+    pub fn floor_sats(self) -> Sats {
+        Sats(self.0.div_euclid(MSAT_PER_SAT))
+    }
+
+    /// Rounds up, for lower bounds expressed in sats.
+    pub fn ceil_sats(self) -> Sats {
+        let sats = self.0.div_euclid(MSAT_PER_SAT);
+
+        Sats(if self.0.rem_euclid(MSAT_PER_SAT) == 0 {
+            sats
+        } else {
+            sats + 1
+        })
+    }
+
+    /// Converts without rounding, for the Lightning backends that only take
+    /// whole sats. Fails if the amount has a fraction of a sat.
+    pub fn to_sats(self) -> Result<Sats> {
+        if self.0 % MSAT_PER_SAT != 0 {
+            return bad_req!("Amount must be a whole number of sats");
+        }
+
+        Ok(Sats(self.0 / MSAT_PER_SAT))
+    }
+}
+
+impl Sats {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn new(sats: i64) -> Self {
+        Self(sats)
+    }
+
+    pub const fn as_i64(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn to_msat(self) -> Result<Msat> {
+        match self.0.checked_mul(MSAT_PER_SAT) {
+            Some(msat) => Ok(Msat(msat)),
+            None => bad_req!("Amount is out of range"),
+        }
+    }
+}
+
+impl fmt::Display for Msat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for Sats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(
+            Msat::new(1500).checked_add(Msat::new(500)),
+            Some(Msat::new(2000))
+        );
+        assert_eq!(Msat::new(i64::MAX).checked_add(Msat::new(1)), None);
+        assert_eq!(Msat::new(i64::MIN).checked_sub(Msat::new(1)), None);
+        assert_eq!(Msat::new(i64::MIN).checked_neg(), None);
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(Msat::new(1999).floor_sats(), Sats::new(1));
+        assert_eq!(Msat::new(1001).ceil_sats(), Sats::new(2));
+        assert_eq!(Msat::new(2000).ceil_sats(), Sats::new(2));
+        assert_eq!(Msat::new(-1500).floor_sats(), Sats::new(-2));
+        assert_eq!(Msat::new(-1500).ceil_sats(), Sats::new(-1));
+    }
+
+    #[test]
+    fn test_exact_sats() {
+        assert_eq!(Msat::new(2000).to_sats().ok(), Some(Sats::new(2)));
+        assert!(Msat::new(2001).to_sats().is_err());
+        assert_eq!(Sats::new(2).to_msat().ok(), Some(Msat::new(2000)));
+        assert!(Sats::new(i64::MAX).to_msat().is_err());
+    }
+
+    #[test]
+    fn test_basis_points() {
+        assert_eq!(Msat::new(30_000).basis_points(500), Some(Msat::new(1500)));
+        assert_eq!(Msat::new(3).basis_points(500), Some(Msat::new(0)));
+        assert_eq!(Msat::new(i64::MAX).basis_points(20_000), None);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::Env;
+
+use super::money::{Msat, Sats};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RakeTier {
+    pub min_stake: Msat,
+    pub basis_points: i64,
+}
+
+/// House fee taken from the pot of decisive games. Draws are always refunded
+/// in full.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Rake {
+    pub tiers: Vec<RakeTier>,
+    pub min_fee: Msat,
+}
+
+impl Rake {
+    pub fn from_env() -> Self {
+        let env = Env::get();
+
+        Self::parse(&env.rake_tiers, &env.rake_min_fee)
+            .expect("RAKE_TIERS or RAKE_MIN_FEE is invalid")
+    }
+
+    /// Parses tiers written as `min_stake:basis_points` pairs, e.g.
+    /// `0:500,100000:300` takes 5% of the pot and 3% from stakes of 100000 sats.
+    /// Stakes and the minimum fee are given in sats.
+    pub fn parse(tiers: &str, min_fee: &str) -> Option<Self> {
+        let mut tiers = tiers
+            .split(',')
+            .filter(|tier| !tier.trim().is_empty())
+            .map(|tier| {
+                let (min_stake, basis_points) = tier.trim().split_once(':')?;
+
+                Some(RakeTier {
+                    min_stake: parse_sats(min_stake)?,
+                    basis_points: basis_points.parse().ok()?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        if tiers
+            .iter()
+            .any(|tier| tier.min_stake < Msat::ZERO || !(0..=10_000).contains(&tier.basis_points))
+        {
+            return None;
+        }
+
+        tiers.sort_by_key(|tier| tier.min_stake);
+
+        let min_fee = match min_fee.trim() {
+            "" => Msat::ZERO,
+            min_fee => parse_sats(min_fee).filter(|min_fee| *min_fee >= Msat::ZERO)?,
+        };
+
+        Some(Self { tiers, min_fee })
+    }
+
+    /// Fee for a decisive game where both players staked `stake`. The
+    /// percentage is rounded down to the millisatoshi, and the fee never
+    /// exceeds the loser's stake so the winner always gets at least their own
+    /// stake back.
+    pub fn fee(&self, stake: Msat) -> Msat {
+        if !stake.is_positive() {
+            return Msat::ZERO;
+        }
+
+        let basis_points = self
+            .tiers
+            .iter()
+            .rev()
+            .find(|tier| stake >= tier.min_stake)
+            .map(|tier| tier.basis_points)
+            .unwrap_or(0);
+
+        // A percentage of the pot too large to fit is capped at the stake
+        // below anyway.
+        let fee = stake.basis_points(2 * basis_points).unwrap_or(stake);
+
+        fee.max(self.min_fee).min(stake)
+    }
+}
+
+fn parse_sats(input: &str) -> Option<Msat> {
+    Sats::new(input.trim().parse().ok()?).to_msat().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sats(sats: i64) -> Msat {
+        Sats::new(sats).to_msat().unwrap()
+    }
+
+    #[test]
+    fn test_parse_rake() {
+        let rake = Rake::parse("100000:300, 0:500", "2");
+
+        assert_eq!(
+            rake,
+            Some(Rake {
+                tiers: vec![
+                    RakeTier {
+                        min_stake: Msat::ZERO,
+                        basis_points: 500,
+                    },
+                    RakeTier {
+                        min_stake: sats(100000),
+                        basis_points: 300,
+                    },
+                ],
+                min_fee: sats(2),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_rake() {
+        assert_eq!(Rake::parse("", ""), Some(Rake::default()));
+    }
+
+    #[test]
+    fn test_parse_invalid_rake() {
+        assert_eq!(Rake::parse("0-500", ""), None);
+        assert_eq!(Rake::parse("0:10001", ""), None);
+        assert_eq!(Rake::parse("0:500", "-1"), None);
+    }
+
+    #[test]
+    fn test_fee_by_tier() {
+        let rake = Rake::parse("0:500,100000:300", "").unwrap();
+
+        assert_eq!(rake.fee(sats(1000)), sats(100));
+        assert_eq!(rake.fee(sats(100000)), sats(6000));
+    }
+
+    #[test]
+    fn test_fee_rounds_down() {
+        let rake = Rake::parse("0:500", "").unwrap();
+
+        assert_eq!(rake.fee(sats(15)), Msat::new(1500));
+        assert_eq!(rake.fee(Msat::new(15)), Msat::new(1));
+        assert_eq!(rake.fee(Msat::new(9)), Msat::ZERO);
+    }
+
+    #[test]
+    fn test_fee_minimum() {
+        let rake = Rake::parse("0:500", "3").unwrap();
+
+        assert_eq!(rake.fee(sats(9)), sats(3));
+        assert_eq!(rake.fee(sats(2)), sats(2));
+        assert_eq!(rake.fee(Msat::ZERO), Msat::ZERO);
+    }
+
+    #[test]
+    fn test_fee_without_rake() {
+        assert_eq!(Rake::default().fee(sats(1000)), Msat::ZERO);
+    }
+}
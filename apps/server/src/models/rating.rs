@@ -0,0 +1,220 @@
+use crate::http::{Error, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Converts between the Glicko-1 and Glicko-2 scales.
+const SCALE: f64 = 173.7178;
+/// Constrains how much the volatility can change in a single game.
+const TAU: f64 = 0.5;
+const CONVERGENCE_TOLERANCE: f64 = 0.000001;
+
+/// Time controls are grouped by the estimated length of a game, counting 40
+/// moves of increment on top of the base time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RatingCategory {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+}
+
+impl RatingCategory {
+    /// Both the base time and the increment are in seconds.
+    pub fn from_time_control(base_seconds: u32, increment_seconds: u32) -> Self {
+        let estimated_seconds = base_seconds + increment_seconds * 40;
+
+        match estimated_seconds {
+            0..180 => Self::Bullet,
+            180..480 => Self::Blitz,
+            480..1500 => Self::Rapid,
+            _ => Self::Classical,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Bullet => "bullet",
+            Self::Blitz => "blitz",
+            Self::Rapid => "rapid",
+            Self::Classical => "classical",
+        }
+    }
+
+    pub fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "bullet" => Ok(Self::Bullet),
+            "blitz" => Ok(Self::Blitz),
+            "rapid" => Ok(Self::Rapid),
+            "classical" => Ok(Self::Classical),
+            _ => Err(Error::InternalServerError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self {
+            rating: 1500.0,
+            deviation: 350.0,
+            volatility: 0.06,
+        }
+    }
+}
+
+impl Rating {
+    /// Applies a rating period with the given `(opponent, score)` results,
+    /// scores being 1 for a win, 0.5 for a draw and 0 for a loss. Every game
+    /// is rated as its own period.
+    pub fn update(self, results: &[(Rating, f64)]) -> Self {
+        let mu = (self.rating - 1500.0) / SCALE;
+        let phi = self.deviation / SCALE;
+        let sigma = self.volatility;
+
+        let results: Vec<(f64, f64, f64)> = results
+            .iter()
+            .map(|(opponent, score)| {
+                let g = g(opponent.deviation / SCALE);
+                let opponent_mu = (opponent.rating - 1500.0) / SCALE;
+                let expected = 1.0 / (1.0 + (-g * (mu - opponent_mu)).exp());
+
+                (g, expected, *score)
+            })
+            .collect();
+
+        let v = 1.0
+            / results
+                .iter()
+                .map(|(g, expected, _)| g.powi(2) * expected * (1.0 - expected))
+                .sum::<f64>();
+        let improvement: f64 = results
+            .iter()
+            .map(|(g, expected, score)| g * (score - expected))
+            .sum();
+        let delta = v * improvement;
+
+        let sigma = new_volatility(phi, sigma, v, delta);
+        let phi_star = (phi.powi(2) + sigma.powi(2)).sqrt();
+        let phi = 1.0 / (1.0 / phi_star.powi(2) + 1.0 / v).sqrt();
+        let mu = mu + phi.powi(2) * improvement;
+
+        Self {
+            rating: mu * SCALE + 1500.0,
+            deviation: phi * SCALE,
+            volatility: sigma,
+        }
+    }
+}
+
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi.powi(2) / PI.powi(2)).sqrt()
+}
+
+/// Step 5 of the Glicko-2 algorithm, solved with the Illinois method.
+fn new_volatility(phi: f64, sigma: f64, v: f64, delta: f64) -> f64 {
+    let a = sigma.powi(2).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+
+        ex * (delta.powi(2) - phi.powi(2) - v - ex) / (2.0 * (phi.powi(2) + v + ex).powi(2))
+            - (x - a) / TAU.powi(2)
+    };
+
+    let mut lower = a;
+    let mut upper = if delta.powi(2) > phi.powi(2) + v {
+        (delta.powi(2) - phi.powi(2) - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+
+    let mut f_lower = f(lower);
+    let mut f_upper = f(upper);
+
+    while (upper - lower).abs() > CONVERGENCE_TOLERANCE {
+        let c = lower + (lower - upper) * f_lower / (f_upper - f_lower);
+        let f_c = f(c);
+
+        if f_c * f_upper <= 0.0 {
+            lower = upper;
+            f_lower = f_upper;
+        } else {
+            f_lower /= 2.0;
+        }
+
+        upper = c;
+        f_upper = f_c;
+    }
+
+    (lower / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_categories() {
+        assert_eq!(
+            RatingCategory::from_time_control(60, 0),
+            RatingCategory::Bullet
+        );
+        assert_eq!(
+            RatingCategory::from_time_control(120, 1),
+            RatingCategory::Bullet
+        );
+        assert_eq!(
+            RatingCategory::from_time_control(180, 0),
+            RatingCategory::Blitz
+        );
+        assert_eq!(
+            RatingCategory::from_time_control(600, 0),
+            RatingCategory::Rapid
+        );
+        assert_eq!(
+            RatingCategory::from_time_control(1500, 10),
+            RatingCategory::Classical
+        );
+    }
+
+    /// The worked example of Glickman's "Example of the Glicko-2 system".
+    #[test]
+    fn test_update_matches_reference_example() {
+        let updated = rating(1500.0, 200.0).update(&[
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ]);
+
+        assert!((updated.rating - 1464.06).abs() < 0.01);
+        assert!((updated.deviation - 151.52).abs() < 0.01);
+        assert!((updated.volatility - 0.05999).abs() < 0.00001);
+    }
+
+    #[test]
+    fn test_draw_between_equals_only_narrows_deviation() {
+        let updated = Rating::default().update(&[(Rating::default(), 0.5)]);
+
+        assert!((updated.rating - 1500.0).abs() < 0.000001);
+        assert!(updated.deviation < 350.0);
+    }
+}
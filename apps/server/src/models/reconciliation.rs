@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::money::{Msat, Sats};
+
+/// Sum of account balances by kind.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LedgerTotals {
+    pub users: Msat,
+    pub escrow: Msat,
+    pub platform: Msat,
+    /// Goes negative as money comes in, so the node should hold its opposite.
+    pub lightning: Msat,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Discrepancy {
+    /// The node balance differs from what the ledger says it holds, counting
+    /// withdrawals that are still in flight. The node only reports whole
+    /// sats, so the ledger side is rounded down.
+    BalanceMismatch { expected: Sats, actual: Sats },
+    /// A settled incoming payment that was never credited.
+    UncreditedPayment { payment_hash: String, amount: Sats },
+    /// A settled outgoing payment that was never debited.
+    UndebitedPayment { payment_hash: String, amount: Sats },
+    /// A failed outgoing payment that is still debited. Payments with an
+    /// unknown outcome are not refunded right away, so they end up here if
+    /// they fail.
+    FailedPayment { payment_hash: String, amount: Sats },
+    /// Account balances do not sum to zero.
+    UnbalancedLedger { total: Msat },
+    /// An account balance differs from the `last_balance` of its latest entry.
+    BalanceDrift {
+        account_id: Uuid,
+        balance: Msat,
+        last_balance: Msat,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ReconciliationReport {
+    pub checked_at: DateTime<Utc>,
+    pub ledger: LedgerTotals,
+    pub node_balance: Sats,
+    pub in_flight_withdrawals: Msat,
+    pub discrepancies: Vec<Discrepancy>,
+}
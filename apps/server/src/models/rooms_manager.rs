@@ -1,6 +1,8 @@
 use super::event::Event;
 use super::game::PlayerColor;
+use super::matchmaking::{find_match, Seek};
 use crate::{http::Result, states::rooms_manager, Error};
+use chrono::{DateTime, Utc};
 use mockall::automock;
 use std::{
     collections::HashMap,
@@ -10,8 +12,12 @@ use tokio::sync::broadcast;
 use uuid::Uuid;
 
 pub enum PairedGame {
+    /// The player waits in a new room, whose seek is paired once staked.
     NewGame(Uuid),
-    ExistingGame(Uuid),
+    /// The player took the free seat of the waiting seek.
+    ExistingGame(Uuid, Seek),
+    /// The player already waits in this room with another seek.
+    Seeking(Uuid),
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +25,10 @@ pub struct Room {
     pub request_key: String,
     pub white_player: Option<Uuid>,
     pub black_player: Option<Uuid>,
+    /// Player who offered a rematch once the game was over.
+    pub rematch_offer: Option<Uuid>,
+    /// Set once the game is settled. The room stays open for a rematch.
+    pub finished: bool,
     pub tx: broadcast::Sender<String>,
 }
 
@@ -28,6 +38,8 @@ impl Room {
             request_key,
             white_player: None,
             black_player: None,
+            rematch_offer: None,
+            finished: false,
             tx: broadcast::channel(100).0,
         }
     }
@@ -82,34 +94,73 @@ pub trait RoomsManagerTrait: Send + Sync {
     fn get_room_tx(&self, room_id: Uuid) -> Result<broadcast::Sender<String>>;
 
     fn get_room(&self, room_id: Uuid) -> Result<Room>;
-    fn create_room(&self, room_id: Uuid, request_key: &str);
-    fn add_player(
+    /// Seats the player in the room of a compatible seek from the matchmaking
+    /// pool, or in a new room whose seek waits in the pool for an opponent.
+    fn pair_new_player(&self, seek: Seek) -> Result<PairedGame>;
+    /// Seats the player in a new room whose seek carries an invite token, so
+    /// it only waits for the player who opens the invite link.
+    fn open_private_room(&self, seek: Seek) -> Result<PairedGame>;
+    /// The private room of the invite token and the seek waiting in it.
+    fn find_invite(&self, invite_token: &str) -> Option<(Uuid, Seek)>;
+    /// Seats the player in the free seat of the private room of the invite
+    /// token. Returns the room id and the seek that waited in it.
+    fn join_private_room(&self, invite_token: &str, player_id: Uuid) -> Result<(Uuid, Seek)>;
+    /// Marks the seek of the room as staked, so it can be paired.
+    fn stake_seek(&self, room_id: Uuid);
+    /// Closes the room of a seek whose stake could not be taken.
+    fn abandon_seek(&self, room_id: Uuid);
+    /// Undoes a pairing whose game could not be started: the player who
+    /// took the seat leaves and `waiting` goes back in the pool.
+    fn unpair(&self, room_id: Uuid, waiting: Seek);
+    /// Adds a room whose seats were assigned outside of the matchmaking pool.
+    fn open_room(&self, room_id: Uuid, room: Room);
+    /// Marks the game of the room as settled, so it no longer counts as one
+    /// of its players' games.
+    fn finish_room(&self, room_id: Uuid);
+    fn offer_rematch(&self, room_id: Uuid, player_id: Uuid) -> Result<()>;
+    /// Withdraws the rematch offer of `offered_by`. Returns false if they had
+    /// not offered one.
+    fn take_rematch_offer(&self, room_id: Uuid, offered_by: Uuid) -> bool;
+    /// Removes the staked seek waiting in the room from the pool. Returns None
+    /// if it was already paired or taken.
+    fn remove_seek(&self, room_id: Uuid) -> Option<Seek>;
+    /// Puts a taken seek back in the pool, for when closing it failed.
+    fn restore_seek(&self, room_id: Uuid, seek: Seek);
+    /// Removes the staked seek of the player from the pool, so it can no
+    /// longer be paired. Returns it with the room it waited in.
+    fn take_seek(&self, player_id: Uuid) -> Option<(Uuid, Seek)>;
+    /// Removes from the pool the staked seeks created before `seeks_before`,
+    /// and the private room seeks created before `invites_before`.
+    fn take_expired_seeks(
         &self,
-        room_id: Uuid,
-        player_id: Uuid,
-        color_preference: Option<PlayerColor>,
-    ) -> Result<PlayerColor>;
-    fn pair_new_player(&self, room_key: &str) -> PairedGame;
-    fn remove_request(&self, request_key: &str);
+        seeks_before: DateTime<Utc>,
+        invites_before: DateTime<Utc>,
+    ) -> Vec<(Uuid, Seek)>;
+    /// The open seek of the player and the room it waits in.
+    fn find_seek(&self, player_id: Uuid) -> Option<(Uuid, Seek)>;
+    /// Unfinished rooms where the player has an opponent.
+    fn find_games(&self, player_id: Uuid) -> Vec<(Uuid, Room)>;
+    fn count_games(&self, player_id: Uuid) -> usize;
     fn remove_room(&self, room_id: Uuid);
 }
 
 pub type GameRooms = Arc<Mutex<HashMap<Uuid, Room>>>;
-pub type Requests = Arc<Mutex<HashMap<String, Uuid>>>;
+/// Seeks waiting for an opponent, by the id of the room they wait in.
+pub type MatchmakingPool = Arc<Mutex<HashMap<Uuid, Seek>>>;
 
 #[derive(Debug)]
 pub struct RoomsManager {
     game_rooms: GameRooms,
-    requests: Requests,
+    matchmaking_pool: MatchmakingPool,
 }
 
 impl RoomsManager {
     pub fn new() -> Self {
-        let (game_rooms, requests) = rooms_manager::get();
+        let (game_rooms, matchmaking_pool) = rooms_manager::get();
 
         Self {
             game_rooms,
-            requests,
+            matchmaking_pool,
         }
     }
 }
@@ -140,19 +191,159 @@ impl RoomsManagerTrait for RoomsManager {
             .clone())
     }
 
-    fn create_room(&self, room_id: Uuid, request_key: &str) {
-        self.game_rooms
+    fn pair_new_player(&self, seek: Seek) -> Result<PairedGame> {
+        // Both locks are held until the player is seated, so a paired room
+        // always exists and still has the opponent's seat free.
+        let mut pool = self.matchmaking_pool.lock().unwrap();
+        let mut game_rooms = self.game_rooms.lock().unwrap();
+
+        if let Some((room_id, _)) = pool
+            .iter()
+            .find(|(_, waiting)| waiting.player_id == seek.player_id)
+        {
+            return Ok(PairedGame::Seeking(*room_id));
+        }
+
+        let Some(room_id) = find_match(&pool, &seek, Utc::now()) else {
+            let room_id = Uuid::new_v4();
+            let mut room = Room::new(seek.key.clone());
+
+            room.add_player(seek.player_id, seek.player_color)?;
+            game_rooms.insert(room_id, room);
+            pool.insert(room_id, seek);
+
+            return Ok(PairedGame::NewGame(room_id));
+        };
+
+        let room = game_rooms.get_mut(&room_id).ok_or(Error::NotFound {
+            message: String::from("Room not found!"),
+        })?;
+
+        // A player without a colour preference was seated as white, so they
+        // move over if the opponent asked for white.
+        if let (Some(waiting), Some(PlayerColor::White)) = (pool.get(&room_id), seek.player_color) {
+            if waiting.player_color.is_none() && room.white_player == Some(waiting.player_id) {
+                room.white_player = None;
+                room.black_player = Some(waiting.player_id);
+            }
+        }
+
+        room.add_player(seek.player_id, seek.player_color)?;
+
+        let waiting = pool.remove(&room_id).ok_or(Error::InternalServerError)?;
+
+        Ok(PairedGame::ExistingGame(room_id, waiting))
+    }
+
+    fn open_private_room(&self, seek: Seek) -> Result<PairedGame> {
+        let mut pool = self.matchmaking_pool.lock().unwrap();
+        let mut game_rooms = self.game_rooms.lock().unwrap();
+
+        if let Some((room_id, _)) = pool
+            .iter()
+            .find(|(_, waiting)| waiting.player_id == seek.player_id)
+        {
+            return Ok(PairedGame::Seeking(*room_id));
+        }
+
+        let room_id = Uuid::new_v4();
+        let mut room = Room::new(seek.key.clone());
+
+        room.add_player(seek.player_id, seek.player_color)?;
+        game_rooms.insert(room_id, room);
+        pool.insert(room_id, seek);
+
+        Ok(PairedGame::NewGame(room_id))
+    }
+
+    fn find_invite(&self, invite_token: &str) -> Option<(Uuid, Seek)> {
+        self.matchmaking_pool
             .lock()
             .unwrap()
-            .insert(room_id, Room::new(request_key.to_string()));
+            .iter()
+            .find(|(_, seek)| seek.staked && seek.invite_token.as_deref() == Some(invite_token))
+            .map(|(room_id, seek)| (*room_id, seek.clone()))
     }
 
-    fn add_player(
-        &self,
-        room_id: Uuid,
-        player_id: Uuid,
-        color_preference: Option<PlayerColor>,
-    ) -> Result<PlayerColor> {
+    fn join_private_room(&self, invite_token: &str, player_id: Uuid) -> Result<(Uuid, Seek)> {
+        let mut pool = self.matchmaking_pool.lock().unwrap();
+        let mut game_rooms = self.game_rooms.lock().unwrap();
+
+        let (room_id, seek) = pool
+            .iter()
+            .find(|(_, seek)| seek.staked && seek.invite_token.as_deref() == Some(invite_token))
+            .map(|(room_id, seek)| (*room_id, seek))
+            .ok_or(Error::NotFound {
+                message: String::from("Invite not found!"),
+            })?;
+
+        if seek.player_id == player_id {
+            return Err(Error::BadRequest {
+                message: String::from("You cannot join your own game!"),
+            });
+        }
+
+        if seek.opponent.is_some_and(|opponent| opponent != player_id) {
+            return Err(Error::NotFound {
+                message: String::from("Invite not found!"),
+            });
+        }
+
+        let room = game_rooms.get_mut(&room_id).ok_or(Error::NotFound {
+            message: String::from("Room not found!"),
+        })?;
+
+        room.add_player(player_id, None)?;
+
+        let waiting = pool.remove(&room_id).ok_or(Error::InternalServerError)?;
+
+        Ok((room_id, waiting))
+    }
+
+    fn stake_seek(&self, room_id: Uuid) {
+        if let Some(seek) = self.matchmaking_pool.lock().unwrap().get_mut(&room_id) {
+            seek.staked = true;
+        }
+    }
+
+    fn abandon_seek(&self, room_id: Uuid) {
+        let mut pool = self.matchmaking_pool.lock().unwrap();
+        let mut game_rooms = self.game_rooms.lock().unwrap();
+
+        pool.remove(&room_id);
+        game_rooms.remove(&room_id);
+    }
+
+    fn unpair(&self, room_id: Uuid, waiting: Seek) {
+        let mut pool = self.matchmaking_pool.lock().unwrap();
+        let mut game_rooms = self.game_rooms.lock().unwrap();
+
+        let Some(room) = game_rooms.get_mut(&room_id) else {
+            return;
+        };
+
+        room.white_player = None;
+        room.black_player = None;
+
+        if room
+            .add_player(waiting.player_id, waiting.player_color)
+            .is_ok()
+        {
+            pool.insert(room_id, waiting);
+        }
+    }
+
+    fn open_room(&self, room_id: Uuid, room: Room) {
+        self.game_rooms.lock().unwrap().insert(room_id, room);
+    }
+
+    fn finish_room(&self, room_id: Uuid) {
+        if let Some(room) = self.game_rooms.lock().unwrap().get_mut(&room_id) {
+            room.finished = true;
+        }
+    }
+
+    fn offer_rematch(&self, room_id: Uuid, player_id: Uuid) -> Result<()> {
         self.game_rooms
             .lock()
             .unwrap()
@@ -160,25 +351,89 @@ impl RoomsManagerTrait for RoomsManager {
             .ok_or(Error::NotFound {
                 message: String::from("Room not found!"),
             })?
-            .add_player(player_id, color_preference)
+            .rematch_offer = Some(player_id);
+
+        Ok(())
     }
 
-    fn pair_new_player(&self, key: &str) -> PairedGame {
-        let mut requests = self.requests.lock().unwrap();
+    fn take_rematch_offer(&self, room_id: Uuid, offered_by: Uuid) -> bool {
+        self.game_rooms
+            .lock()
+            .unwrap()
+            .get_mut(&room_id)
+            .filter(|room| room.rematch_offer == Some(offered_by))
+            .map(|room| room.rematch_offer.take())
+            .is_some()
+    }
 
-        match requests.remove(key) {
-            Some(room_id) => PairedGame::ExistingGame(room_id),
-            None => {
-                let room_id = Uuid::new_v4();
-                requests.insert(key.to_string(), room_id);
+    fn remove_seek(&self, room_id: Uuid) -> Option<Seek> {
+        let mut pool = self.matchmaking_pool.lock().unwrap();
 
-                PairedGame::NewGame(room_id)
-            }
-        }
+        pool.get(&room_id)?.staked.then(|| pool.remove(&room_id))?
+    }
+
+    fn restore_seek(&self, room_id: Uuid, seek: Seek) {
+        self.matchmaking_pool.lock().unwrap().insert(room_id, seek);
+    }
+
+    fn take_seek(&self, player_id: Uuid) -> Option<(Uuid, Seek)> {
+        let mut pool = self.matchmaking_pool.lock().unwrap();
+
+        let room_id = *pool
+            .iter()
+            .find(|(_, seek)| seek.staked && seek.player_id == player_id)?
+            .0;
+
+        pool.remove(&room_id).map(|seek| (room_id, seek))
     }
 
-    fn remove_request(&self, request_key: &str) {
-        self.requests.lock().unwrap().remove(request_key);
+    fn take_expired_seeks(
+        &self,
+        seeks_before: DateTime<Utc>,
+        invites_before: DateTime<Utc>,
+    ) -> Vec<(Uuid, Seek)> {
+        let mut pool = self.matchmaking_pool.lock().unwrap();
+
+        let expired: Vec<Uuid> = pool
+            .iter()
+            .filter(|(_, seek)| {
+                let created_before = match seek.invite_token {
+                    Some(_) => invites_before,
+                    None => seeks_before,
+                };
+
+                seek.staked && seek.created_at < created_before
+            })
+            .map(|(room_id, _)| *room_id)
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|room_id| pool.remove(&room_id).map(|seek| (room_id, seek)))
+            .collect()
+    }
+
+    fn find_seek(&self, player_id: Uuid) -> Option<(Uuid, Seek)> {
+        self.matchmaking_pool
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, seek)| seek.player_id == player_id)
+            .map(|(room_id, seek)| (*room_id, seek.clone()))
+    }
+
+    fn find_games(&self, player_id: Uuid) -> Vec<(Uuid, Room)> {
+        self.game_rooms
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, room)| room.is_full() && !room.finished && room.is_playing(player_id))
+            .map(|(room_id, room)| (*room_id, room.clone()))
+            .collect()
+    }
+
+    fn count_games(&self, player_id: Uuid) -> usize {
+        self.find_games(player_id).len()
     }
 
     fn remove_room(&self, room_id: Uuid) {
@@ -189,13 +444,25 @@ impl RoomsManagerTrait for RoomsManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::GameRequest;
 
     impl RoomsManager {
         pub fn new_empty() -> Self {
             Self {
                 game_rooms: Arc::new(Mutex::new(HashMap::new())),
-                requests: Arc::new(Mutex::new(HashMap::new())),
+                matchmaking_pool: Arc::new(Mutex::new(HashMap::new())),
+            }
+        }
+
+        /// Pairs the seek, taking its stake at once if it has to wait.
+        fn pair(&self, seek: Seek) -> Result<PairedGame> {
+            let paired_game = self.pair_new_player(seek)?;
+
+            if let PairedGame::NewGame(room_id) = paired_game {
+                self.stake_seek(room_id);
             }
+
+            Ok(paired_game)
         }
     }
 
@@ -203,30 +470,29 @@ mod tests {
     fn test_add_player_rooms_manager() {
         let rooms_manager = RoomsManager::new_empty();
 
-        let room_id = Uuid::new_v4();
-
-        let player1 = Uuid::new_v4();
-        let player2 = Uuid::new_v4();
-
-        rooms_manager.create_room(room_id, "123");
+        let player1 = seek("n-10-0-0");
+        let player2 = seek("n-10-0-0");
+        let (player1_id, player2_id) = (player1.player_id, player2.player_id);
 
-        let player1_color = rooms_manager.add_player(room_id, player1, None);
+        let PairedGame::NewGame(room_id) = rooms_manager.pair(player1).unwrap() else {
+            panic!()
+        };
 
         let room = rooms_manager.get_room(room_id).unwrap();
 
-        assert!(room.white_player.is_some());
+        assert_eq!(Some(player1_id), room.white_player);
         assert!(room.black_player.is_none());
-        assert!(player1_color.ok() == Some(PlayerColor::White));
-        assert_eq!(Some(player1), room.white_player);
 
-        let player2_color = rooms_manager.add_player(room_id, player2, None);
+        let PairedGame::ExistingGame(paired_room_id, _) = rooms_manager.pair(player2).unwrap()
+        else {
+            panic!()
+        };
 
         let room = rooms_manager.get_room(room_id).unwrap();
 
-        assert!(room.white_player.is_some());
-        assert!(room.black_player.is_some());
-        assert!(player2_color.ok() == Some(PlayerColor::Black));
-        assert_eq!(Some(player2), room.black_player);
+        assert_eq!(paired_room_id, room_id);
+        assert_eq!(Some(player1_id), room.white_player);
+        assert_eq!(Some(player2_id), room.black_player);
     }
 
     #[test]
@@ -323,12 +589,16 @@ mod tests {
         assert!(room.black_player.is_some());
     }
 
+    fn seek(key: &str) -> Seek {
+        Seek::new(Uuid::new_v4(), &GameRequest::from_str(key).unwrap(), 1500.0)
+    }
+
     #[test]
     fn test_pairing_new_room() {
         let rooms_manager = RoomsManager::new_empty();
-        let result = rooms_manager.pair_new_player("w-10-0-0");
+        let result = rooms_manager.pair(seek("w-10-0-0")).unwrap();
 
-        if let PairedGame::ExistingGame(_) = result {
+        if let PairedGame::ExistingGame(..) = result {
             panic!()
         }
     }
@@ -336,10 +606,10 @@ mod tests {
     #[test]
     fn test_pairing_2_not_equal_rooms() {
         let rooms_manager = RoomsManager::new_empty();
-        rooms_manager.pair_new_player("w-10-0-1");
-        let result = rooms_manager.pair_new_player("w-10-0-0");
+        rooms_manager.pair(seek("w-10-0-1")).unwrap();
+        let result = rooms_manager.pair(seek("w-10-0-0")).unwrap();
 
-        if let PairedGame::ExistingGame(_) = result {
+        if let PairedGame::ExistingGame(..) = result {
             panic!()
         }
     }
@@ -347,25 +617,260 @@ mod tests {
     #[test]
     fn test_pairing_existing_room() {
         let rooms_manager = RoomsManager::new_empty();
-        let player = Uuid::new_v4();
+        let waiting = seek("b-10-0-0");
+        let player = waiting.player_id;
 
-        if let PairedGame::NewGame(room_id) = rooms_manager.pair_new_player("w-10-0-0") {
-            rooms_manager.create_room(room_id, "w-10-0-0");
-            rooms_manager
-                .add_player(room_id, player, Some(PlayerColor::Black))
-                .unwrap();
-        }
+        rooms_manager.pair(waiting).unwrap();
 
-        let result = rooms_manager.pair_new_player("w-10-0-0");
+        let result = rooms_manager.pair(seek("w-10-0-0")).unwrap();
 
         match result {
-            PairedGame::ExistingGame(room_id) => {
+            PairedGame::ExistingGame(room_id, _) => {
+                let room = rooms_manager.get_room(room_id).unwrap();
+
+                assert_eq!(room.request_key, "v1:b:600+0:standard:rated:*:*:0");
+                assert_eq!(room.black_player, Some(player));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_pairing_same_player_twice() {
+        let rooms_manager = RoomsManager::new_empty();
+        let waiting = seek("n-10-0-0");
+
+        let PairedGame::NewGame(room_id) = rooms_manager.pair(waiting.clone()).unwrap() else {
+            panic!()
+        };
+
+        match rooms_manager.pair(waiting.clone()).unwrap() {
+            PairedGame::Seeking(seeking_room_id) => assert_eq!(seeking_room_id, room_id),
+            _ => panic!(),
+        }
+        assert_eq!(
+            rooms_manager.find_seek(waiting.player_id),
+            Some((
+                room_id,
+                Seek {
+                    staked: true,
+                    ..waiting
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_unstaked_seek_is_not_paired() {
+        let rooms_manager = RoomsManager::new_empty();
+        let waiting = seek("n-10-0-0");
+        let player = waiting.player_id;
+
+        let PairedGame::NewGame(room_id) = rooms_manager.pair_new_player(waiting).unwrap() else {
+            panic!()
+        };
+
+        assert!(matches!(
+            rooms_manager.pair(seek("n-10-0-0")).unwrap(),
+            PairedGame::NewGame(other) if other != room_id
+        ));
+        assert_eq!(rooms_manager.take_seek(player), None);
+        assert_eq!(rooms_manager.remove_seek(room_id), None);
+
+        rooms_manager.abandon_seek(room_id);
+
+        assert!(rooms_manager.get_room(room_id).is_err());
+        assert_eq!(rooms_manager.find_seek(player), None);
+    }
+
+    #[test]
+    fn test_unpair_reopens_the_seek() {
+        let rooms_manager = RoomsManager::new_empty();
+        let waiting = seek("n-10-0-0");
+        let player = waiting.player_id;
+
+        rooms_manager.pair(waiting).unwrap();
+
+        let PairedGame::ExistingGame(room_id, waiting) =
+            rooms_manager.pair(seek("w-10-0-0")).unwrap()
+        else {
+            panic!()
+        };
+
+        rooms_manager.unpair(room_id, waiting);
+
+        let room = rooms_manager.get_room(room_id).unwrap();
+
+        assert_eq!(room.white_player, Some(player));
+        assert_eq!(room.black_player, None);
+        assert_eq!(
+            rooms_manager.take_seek(player).map(|(id, _)| id),
+            Some(room_id)
+        );
+    }
+
+    #[test]
+    fn test_taken_seek_is_not_paired() {
+        let rooms_manager = RoomsManager::new_empty();
+        let waiting = seek("n-10-0-0");
+        let player = waiting.player_id;
+
+        let PairedGame::NewGame(room_id) = rooms_manager.pair(waiting).unwrap() else {
+            panic!()
+        };
+
+        assert_eq!(
+            rooms_manager.take_seek(player).map(|(id, _)| id),
+            Some(room_id)
+        );
+        assert_eq!(rooms_manager.take_seek(player), None);
+
+        if let PairedGame::ExistingGame(..) = rooms_manager.pair(seek("n-10-0-0")).unwrap() {
+            panic!()
+        }
+    }
+
+    #[test]
+    fn test_pairing_moves_flexible_player_to_black() {
+        let rooms_manager = RoomsManager::new_empty();
+        let waiting = seek("n-10-0-0");
+        let player = waiting.player_id;
+
+        let PairedGame::NewGame(room_id) = rooms_manager.pair(waiting).unwrap() else {
+            panic!()
+        };
+
+        let white = seek("w-10-0-0");
+        let white_player = white.player_id;
+
+        match rooms_manager.pair(white).unwrap() {
+            PairedGame::ExistingGame(paired_room_id, _) => {
                 let room = rooms_manager.get_room(room_id).unwrap();
 
-                assert_eq!(room.request_key, "w-10-0-0");
+                assert_eq!(paired_room_id, room_id);
+                assert_eq!(room.white_player, Some(white_player));
                 assert_eq!(room.black_player, Some(player));
             }
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn test_concurrent_pairing_seats_everyone() {
+        let rooms_manager = RoomsManager::new_empty();
+
+        let paired: Vec<PairedGame> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..50)
+                .map(|_| scope.spawn(|| rooms_manager.pair(seek("n-10-0-0")).unwrap()))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        for game in paired {
+            if let PairedGame::ExistingGame(room_id, _) = game {
+                assert!(rooms_manager.get_room(room_id).unwrap().is_full());
+            }
+        }
+        assert!(rooms_manager.matchmaking_pool.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_finished_rooms_are_not_counted() {
+        let rooms_manager = RoomsManager::new_empty();
+        let waiting = seek("n-10-0-0");
+        let player = waiting.player_id;
+
+        let PairedGame::NewGame(room_id) = rooms_manager.pair(waiting).unwrap() else {
+            panic!()
+        };
+
+        assert_eq!(rooms_manager.count_games(player), 0);
+
+        rooms_manager.pair(seek("n-10-0-0")).unwrap();
+
+        assert_eq!(rooms_manager.count_games(player), 1);
+
+        rooms_manager.finish_room(room_id);
+
+        assert_eq!(rooms_manager.count_games(player), 0);
+        assert!(rooms_manager.get_room(room_id).is_ok());
+    }
+
+    #[test]
+    fn test_private_room_only_joins_through_invite() {
+        let rooms_manager = RoomsManager::new_empty();
+        let host = Seek {
+            invite_token: Some(String::from("invite")),
+            ..seek("n-10-0-0")
+        };
+        let host_id = host.player_id;
+
+        let PairedGame::NewGame(room_id) = rooms_manager.open_private_room(host).unwrap() else {
+            panic!();
+        };
+
+        rooms_manager.stake_seek(room_id);
+
+        assert!(matches!(
+            rooms_manager.pair(seek("n-10-0-0")).unwrap(),
+            PairedGame::NewGame(other) if other != room_id
+        ));
+        assert!(rooms_manager.join_private_room("invite", host_id).is_err());
+        assert!(rooms_manager
+            .join_private_room("wrong", Uuid::new_v4())
+            .is_err());
+
+        let guest = Uuid::new_v4();
+
+        assert_eq!(
+            rooms_manager.join_private_room("invite", guest).unwrap().0,
+            room_id
+        );
+        assert!(rooms_manager.get_room(room_id).unwrap().is_playing(guest));
+        assert!(rooms_manager.find_invite("invite").is_none());
+    }
+
+    #[test]
+    fn test_invites_expire_on_their_own_ttl() {
+        let rooms_manager = RoomsManager::new_empty();
+        let created_at = Utc::now() - chrono::Duration::minutes(30);
+
+        let PairedGame::NewGame(seek_room) = rooms_manager
+            .pair(Seek {
+                created_at,
+                ..seek("n-10-0-0")
+            })
+            .unwrap()
+        else {
+            panic!()
+        };
+        let PairedGame::NewGame(invite_room) = rooms_manager
+            .open_private_room(Seek {
+                invite_token: Some(String::from("invite")),
+                created_at,
+                ..seek("n-10-0-0")
+            })
+            .unwrap()
+        else {
+            panic!()
+        };
+
+        rooms_manager.stake_seek(invite_room);
+
+        let now = Utc::now();
+        let expired = rooms_manager.take_expired_seeks(
+            now - chrono::Duration::minutes(10),
+            now - chrono::Duration::days(1),
+        );
+
+        assert_eq!(
+            expired.into_iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            vec![seek_room]
+        );
+        assert!(rooms_manager.find_invite("invite").is_some());
+    }
 }
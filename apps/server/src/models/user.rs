@@ -2,11 +2,22 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::money::Msat;
+
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct User {
     pub id: Uuid,
     pub email: String,
     pub username: String,
     pub hashed_password: String,
-    pub balance: i32,
+    pub balance: Msat,
+}
+
+/// Usernames double as the local part of the user's Lightning Address
+/// (LUD-16), so they are limited to the characters it allows.
+pub fn is_valid_username(username: &str) -> bool {
+    (1..=64).contains(&username.len())
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
 }
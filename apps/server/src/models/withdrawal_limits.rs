@@ -0,0 +1,214 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use crate::Env;
+use chrono::{DateTime, Duration, Utc};
+
+use super::money::{Msat, Sats};
+
+/// Caps on what users can withdraw. Limits left unset are unlimited.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WithdrawalLimits {
+    pub per_withdrawal: Option<Msat>,
+    pub daily: Option<Msat>,
+    pub weekly: Option<Msat>,
+    /// Time after a deposit during which withdrawals are refused.
+    pub cooldown: Duration,
+    /// Withdrawals above this wait for an admin to approve them.
+    pub review_threshold: Option<Msat>,
+}
+
+/// Recent activity of a user the limits are checked against.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WithdrawalActivity {
+    pub withdrawn_last_day: Msat,
+    pub withdrawn_last_week: Msat,
+    pub last_deposit_at: Option<DateTime<Utc>>,
+}
+
+impl WithdrawalLimits {
+    pub fn from_env() -> Self {
+        let env = Env::get();
+
+        Self::parse(
+            &env.withdrawal_max,
+            &env.withdrawal_daily_limit,
+            &env.withdrawal_weekly_limit,
+            &env.withdrawal_cooldown_minutes,
+            &env.withdrawal_review_threshold,
+        )
+        .expect("WITHDRAWAL_* limits are invalid")
+    }
+
+    /// Amounts are given in sats and the cooldown in minutes. Empty values
+    /// leave the limit unset.
+    pub fn parse(
+        per_withdrawal: &str,
+        daily: &str,
+        weekly: &str,
+        cooldown_minutes: &str,
+        review_threshold: &str,
+    ) -> Option<Self> {
+        let cooldown = match cooldown_minutes.trim() {
+            "" => Duration::zero(),
+            minutes => Duration::try_minutes(minutes.parse().ok().filter(|m| *m >= 0)?)?,
+        };
+
+        Some(Self {
+            per_withdrawal: parse_limit(per_withdrawal)?,
+            daily: parse_limit(daily)?,
+            weekly: parse_limit(weekly)?,
+            cooldown,
+            review_threshold: parse_limit(review_threshold)?,
+        })
+    }
+
+    pub fn check(
+        &self,
+        amount: Msat,
+        activity: &WithdrawalActivity,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let paused_until = activity.last_deposit_at.map(|at| at + self.cooldown);
+
+        if let Some(until) = paused_until.filter(|until| *until > now) {
+            return bad_req!(format!(
+                "Withdrawals are paused until {} after a recent deposit",
+                until.to_rfc3339()
+            ));
+        }
+
+        if let Some(max) = self.per_withdrawal.filter(|max| amount > *max) {
+            return bad_req!(format!(
+                "A withdrawal can be at most {} sats",
+                max.floor_sats()
+            ));
+        }
+
+        for (limit, withdrawn, period) in [
+            (self.daily, activity.withdrawn_last_day, "daily"),
+            (self.weekly, activity.withdrawn_last_week, "weekly"),
+        ] {
+            let Some(limit) = limit else {
+                continue;
+            };
+
+            if withdrawn
+                .checked_add(amount)
+                .is_none_or(|total| total > limit)
+            {
+                return bad_req!(format!(
+                    "This withdrawal exceeds your {period} limit of {} sats",
+                    limit.floor_sats()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn needs_review(&self, amount: Msat) -> bool {
+        self.review_threshold
+            .is_some_and(|threshold| amount > threshold)
+    }
+}
+
+fn parse_limit(input: &str) -> Option<Option<Msat>> {
+    match input.trim() {
+        "" => Some(None),
+        sats => {
+            let sats = Sats::new(sats.parse().ok()?);
+
+            if sats < Sats::ZERO {
+                return None;
+            }
+
+            sats.to_msat().ok().map(Some)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sats(sats: i64) -> Msat {
+        Sats::new(sats).to_msat().unwrap()
+    }
+
+    fn limits() -> WithdrawalLimits {
+        WithdrawalLimits::parse("1000", "2000", "5000", "60", "500").unwrap()
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(
+            limits(),
+            WithdrawalLimits {
+                per_withdrawal: Some(sats(1000)),
+                daily: Some(sats(2000)),
+                weekly: Some(sats(5000)),
+                cooldown: Duration::minutes(60),
+                review_threshold: Some(sats(500)),
+            }
+        );
+        assert_eq!(
+            WithdrawalLimits::parse("", "", "", "", ""),
+            Some(WithdrawalLimits::default())
+        );
+        assert_eq!(WithdrawalLimits::parse("-1", "", "", "", ""), None);
+        assert_eq!(WithdrawalLimits::parse("", "", "", "soon", ""), None);
+    }
+
+    #[test]
+    fn test_check_amount_limits() {
+        let now = Utc::now();
+        let activity = WithdrawalActivity {
+            withdrawn_last_day: sats(1500),
+            withdrawn_last_week: sats(3500),
+            ..Default::default()
+        };
+
+        assert!(limits().check(sats(500), &activity, now).is_ok());
+        assert!(limits()
+            .check(sats(1001), &Default::default(), now)
+            .is_err());
+        assert!(limits().check(sats(501), &activity, now).is_err());
+        assert!(limits()
+            .check(
+                sats(1000),
+                &WithdrawalActivity {
+                    withdrawn_last_week: sats(4500),
+                    ..Default::default()
+                },
+                now
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_cooldown() {
+        let now = Utc::now();
+
+        let after_deposit = WithdrawalActivity {
+            last_deposit_at: Some(now - Duration::minutes(30)),
+            ..Default::default()
+        };
+        let settled = WithdrawalActivity {
+            last_deposit_at: Some(now - Duration::minutes(61)),
+            ..Default::default()
+        };
+
+        assert!(limits().check(sats(1), &after_deposit, now).is_err());
+        assert!(limits().check(sats(1), &settled, now).is_ok());
+        assert!(WithdrawalLimits::default()
+            .check(sats(1), &after_deposit, now)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_needs_review() {
+        assert!(!limits().needs_review(sats(500)));
+        assert!(limits().needs_review(sats(501)));
+        assert!(!WithdrawalLimits::default().needs_review(sats(1_000_000)));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::http::{Error, Result};
+use crate::models::Game;
+use crate::states::db;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, Pool, Postgres};
+use uuid::Uuid;
+
+use super::game_repository::insert_game;
+use super::wallet_repository::{post_transfer, SaveTransfer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChallengeStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Canceled,
+    Expired,
+}
+
+impl ChallengeStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Accepted => "accepted",
+            Self::Declined => "declined",
+            Self::Canceled => "canceled",
+            Self::Expired => "expired",
+        }
+    }
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "pending" => Ok(Self::Pending),
+            "accepted" => Ok(Self::Accepted),
+            "declined" => Ok(Self::Declined),
+            "canceled" => Ok(Self::Canceled),
+            "expired" => Ok(Self::Expired),
+            _ => Err(Error::InternalServerError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Challenge {
+    pub id: Uuid,
+    pub challenger_id: Uuid,
+    pub challenger: String,
+    pub challenged_id: Uuid,
+    pub challenged: String,
+    /// Canonical key of the game request, naming the challenged user as the
+    /// opponent.
+    pub request_key: String,
+    pub status: ChallengeStatus,
+    pub game_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// A challenge still pending at this time can no longer be accepted.
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct ChallengeRecord {
+    id: Uuid,
+    challenger_id: Uuid,
+    challenger: String,
+    challenged_id: Uuid,
+    challenged: String,
+    request_key: String,
+    status: String,
+    game_id: Option<Uuid>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+impl ChallengeRecord {
+    fn into_challenge(self) -> Result<Challenge> {
+        Ok(Challenge {
+            id: self.id,
+            challenger_id: self.challenger_id,
+            challenger: self.challenger,
+            challenged_id: self.challenged_id,
+            challenged: self.challenged,
+            request_key: self.request_key,
+            status: ChallengeStatus::from_str(&self.status)?,
+            game_id: self.game_id,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+        })
+    }
+}
+
+const SELECT_CHALLENGES: &str = r#"
+    SELECT c.id, c.challenger_id, challenger.username AS challenger, c.challenged_id,
+        challenged.username AS challenged, c.request_key, c.status, c.game_id, c.created_at,
+        c.expires_at
+    FROM challenges c
+    JOIN users challenger ON challenger.id = c.challenger_id
+    JOIN users challenged ON challenged.id = c.challenged_id
+"#;
+
+#[automock]
+pub trait ChallengeRepositoryTrait {
+    /// Returns None if the challenger already has a pending challenge for the
+    /// same user that has not expired.
+    async fn save_challenge(
+        &self,
+        challenger_id: Uuid,
+        challenged_id: Uuid,
+        request_key: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Uuid>>;
+    async fn get_challenge(&self, id: Uuid) -> Result<Challenge>;
+    /// Pending challenges sent or received by the user that have not
+    /// expired, newest first.
+    async fn list_pending_challenges(&self, user_id: Uuid) -> Result<Vec<Challenge>>;
+    /// Moves a pending challenge to `status`. Returns false if it was no
+    /// longer pending or has expired.
+    async fn close_challenge(&self, id: Uuid, status: ChallengeStatus) -> Result<bool>;
+    /// Accepts a pending challenge, creating its game and posting the stake
+    /// transfers in the same transaction. Returns false if it was no longer
+    /// pending or has expired.
+    async fn accept_challenge(
+        &self,
+        id: Uuid,
+        game: Game,
+        transfers: Vec<SaveTransfer>,
+    ) -> Result<bool>;
+}
+
+pub struct ChallengeRepository {
+    db: Pool<Postgres>,
+}
+
+impl ChallengeRepository {
+    pub fn new() -> Self {
+        Self { db: db::get() }
+    }
+}
+
+impl ChallengeRepositoryTrait for ChallengeRepository {
+    async fn save_challenge(
+        &self,
+        challenger_id: Uuid,
+        challenged_id: Uuid,
+        request_key: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Uuid>> {
+        let mut tx = self.db.begin().await?;
+
+        // An expired challenge would otherwise block a new one for the same
+        // user.
+        sqlx::query(
+            r#"
+                UPDATE challenges SET status = 'expired'
+                WHERE challenger_id = $1 AND challenged_id = $2 AND status = 'pending' AND expires_at <= now()
+            "#,
+        )
+        .bind(challenger_id)
+        .bind(challenged_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let id = sqlx::query_scalar(
+            r#"
+                INSERT INTO challenges (challenger_id, challenged_id, request_key, expires_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT DO NOTHING
+                RETURNING id
+            "#,
+        )
+        .bind(challenger_id)
+        .bind(challenged_id)
+        .bind(request_key)
+        .bind(expires_at)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    async fn get_challenge(&self, id: Uuid) -> Result<Challenge> {
+        sqlx::query_as::<_, ChallengeRecord>(&format!("{SELECT_CHALLENGES} WHERE c.id = $1"))
+            .bind(id)
+            .fetch_one(&self.db)
+            .await?
+            .into_challenge()
+    }
+
+    async fn list_pending_challenges(&self, user_id: Uuid) -> Result<Vec<Challenge>> {
+        sqlx::query_as::<_, ChallengeRecord>(&format!(
+            "{SELECT_CHALLENGES} WHERE c.status = 'pending' AND c.expires_at > now() AND (c.challenger_id = $1 OR c.challenged_id = $1) ORDER BY c.created_at DESC"
+        ))
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(ChallengeRecord::into_challenge)
+        .collect()
+    }
+
+    async fn close_challenge(&self, id: Uuid, status: ChallengeStatus) -> Result<bool> {
+        let result = sqlx::query(
+            r#" UPDATE challenges SET status = $1 WHERE id = $2 AND status = 'pending' AND expires_at > now() "#,
+        )
+        .bind(status.as_str())
+        .bind(id)
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn accept_challenge(
+        &self,
+        id: Uuid,
+        game: Game,
+        transfers: Vec<SaveTransfer>,
+    ) -> Result<bool> {
+        let mut tx = self.db.begin().await?;
+        let game_id = game.id;
+
+        insert_game(&mut tx, game).await?;
+
+        let result = sqlx::query(
+            r#" UPDATE challenges SET status = 'accepted', game_id = $1 WHERE id = $2 AND status = 'pending' AND expires_at > now() "#,
+        )
+        .bind(game_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        for transfer in transfers {
+            post_transfer(&mut tx, transfer).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,254 @@
+use crate::http::{Error, Result};
+use crate::models::{
+    Exclusion, ExclusionKind, Limit, LimitKind, LimitPeriod, Msat, PendingLimit, PlayerLimit,
+    PlayerProtection, Sats,
+};
+use crate::states::db;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use sqlx::{prelude::FromRow, Pool, Postgres};
+use uuid::Uuid;
+
+#[derive(FromRow)]
+struct LimitRecord {
+    kind: String,
+    amount: Sats,
+    period: Option<String>,
+    pending_amount: Option<Sats>,
+    pending_period: Option<String>,
+    pending_effective_at: Option<DateTime<Utc>>,
+}
+
+fn parse_period(period: Option<String>) -> Result<Option<LimitPeriod>> {
+    period.as_deref().map(LimitPeriod::from_str).transpose()
+}
+
+impl LimitRecord {
+    fn into_player_limit(self) -> Result<PlayerLimit> {
+        let pending = match self.pending_effective_at {
+            Some(effective_at) => Some(PendingLimit {
+                limit: match self.pending_amount {
+                    Some(amount) => Some(Limit {
+                        amount,
+                        period: parse_period(self.pending_period)?,
+                    }),
+                    None => None,
+                },
+                effective_at,
+            }),
+            None => None,
+        };
+
+        Ok(PlayerLimit {
+            kind: LimitKind::from_str(&self.kind)?,
+            limit: Limit {
+                amount: self.amount,
+                period: parse_period(self.period)?,
+            },
+            pending,
+        })
+    }
+}
+
+#[derive(FromRow)]
+struct ExclusionRecord {
+    exclusion_kind: Option<String>,
+    excluded_until: Option<DateTime<Utc>>,
+}
+
+impl ExclusionRecord {
+    fn into_exclusion(self) -> Result<Option<Exclusion>> {
+        match (self.exclusion_kind, self.excluded_until) {
+            (Some(kind), Some(until)) => Ok(Some(Exclusion {
+                kind: ExclusionKind::from_str(&kind)?,
+                until,
+            })),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[automock]
+pub trait GamblingLimitRepositoryTrait {
+    async fn get_protection(&self, user_id: Uuid) -> Result<PlayerProtection>;
+    /// Replaces the limit of `kind`, removing it if `limit` is `None`.
+    async fn save_limit(
+        &self,
+        user_id: Uuid,
+        kind: LimitKind,
+        limit: Option<PlayerLimit>,
+    ) -> Result<()>;
+    /// Extends the exclusion of the user, never shortening it. Returns the
+    /// exclusion in place afterwards.
+    async fn exclude(&self, user_id: Uuid, exclusion: Exclusion) -> Result<Exclusion>;
+}
+
+pub struct GamblingLimitRepository {
+    db: Pool<Postgres>,
+}
+
+impl GamblingLimitRepository {
+    pub fn new() -> Self {
+        Self { db: db::get() }
+    }
+
+    /// Deposits paid or still payable since `since`, so unpaid invoices
+    /// cannot be stacked past the limit.
+    async fn deposited_since(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<Msat> {
+        Ok(sqlx::query_scalar(
+            r#"
+                SELECT COALESCE(SUM(amount), 0)::bigint
+                FROM invoices
+                WHERE user_id = $1 AND created_at >= $2
+                  AND (status = 'paid' OR (status = 'pending' AND expires_at > now()))
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(&self.db)
+        .await?)
+    }
+
+    /// Net game losses since `since`: custodial stakes, fees, payouts and
+    /// refunds from the ledger, plus hold stakes settled to an opponent.
+    async fn lost_since(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<Msat> {
+        Ok(sqlx::query_scalar(
+            r#"
+                SELECT GREATEST(
+                    COALESCE((
+                        SELECT -SUM(entries.amount)
+                        FROM entries
+                        JOIN transfers ON transfers.id = entries.transfer_id
+                        JOIN accounts ON accounts.id = entries.account_id
+                        WHERE accounts.user_id = $1
+                          AND transfers.game_id IS NOT NULL
+                          AND entries.created_at >= $2
+                    ), 0)
+                    + COALESCE((
+                        SELECT SUM(amount)
+                        FROM stake_holds
+                        WHERE user_id = $1
+                          AND resolution = 'settle'
+                          AND payout_user_id <> $1
+                          AND COALESCE(updated_at, created_at) >= $2
+                    ), 0),
+                    0
+                )::bigint
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(&self.db)
+        .await?)
+    }
+}
+
+impl GamblingLimitRepositoryTrait for GamblingLimitRepository {
+    async fn get_protection(&self, user_id: Uuid) -> Result<PlayerProtection> {
+        let limits = sqlx::query_as::<_, LimitRecord>(
+            r#"
+                SELECT kind, amount, period, pending_amount, pending_period, pending_effective_at
+                FROM gambling_limits
+                WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(LimitRecord::into_player_limit)
+        .collect::<Result<Vec<_>>>()?;
+
+        let exclusion = sqlx::query_as::<_, ExclusionRecord>(
+            r#" SELECT exclusion_kind, excluded_until FROM users WHERE id = $1 "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.db)
+        .await?
+        .into_exclusion()?;
+
+        let mut protection = PlayerProtection {
+            limits,
+            exclusion,
+            ..Default::default()
+        };
+
+        let now = Utc::now();
+
+        if let Some(period) = protection
+            .limit(LimitKind::Deposit, now)
+            .and_then(|limit| limit.period)
+        {
+            protection.deposited = self
+                .deposited_since(user_id, now - period.duration())
+                .await?;
+        }
+
+        if let Some(period) = protection
+            .limit(LimitKind::Loss, now)
+            .and_then(|limit| limit.period)
+        {
+            protection.lost = self.lost_since(user_id, now - period.duration()).await?;
+        }
+
+        Ok(protection)
+    }
+
+    async fn save_limit(
+        &self,
+        user_id: Uuid,
+        kind: LimitKind,
+        limit: Option<PlayerLimit>,
+    ) -> Result<()> {
+        let Some(limit) = limit else {
+            sqlx::query(r#" DELETE FROM gambling_limits WHERE user_id = $1 AND kind = $2 "#)
+                .bind(user_id)
+                .bind(kind.as_str())
+                .execute(&self.db)
+                .await?;
+
+            return Ok(());
+        };
+
+        let pending_limit = limit.pending.and_then(|pending| pending.limit);
+
+        sqlx::query(
+            r#"
+                INSERT INTO gambling_limits (user_id, kind, amount, period, pending_amount, pending_period, pending_effective_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (user_id, kind) DO UPDATE
+                SET amount = $3, period = $4, pending_amount = $5, pending_period = $6, pending_effective_at = $7
+            "#,
+        )
+        .bind(user_id)
+        .bind(kind.as_str())
+        .bind(limit.limit.amount)
+        .bind(limit.limit.period.map(LimitPeriod::as_str))
+        .bind(pending_limit.map(|pending| pending.amount))
+        .bind(pending_limit.and_then(|pending| pending.period).map(LimitPeriod::as_str))
+        .bind(limit.pending.map(|pending| pending.effective_at))
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn exclude(&self, user_id: Uuid, exclusion: Exclusion) -> Result<Exclusion> {
+        sqlx::query_as::<_, ExclusionRecord>(
+            r#"
+                UPDATE users
+                SET exclusion_kind = CASE WHEN excluded_until IS NULL OR excluded_until < $3 THEN $2 ELSE exclusion_kind END,
+                    excluded_until = GREATEST(excluded_until, $3)
+                WHERE id = $1
+                RETURNING exclusion_kind, excluded_until
+            "#,
+        )
+        .bind(user_id)
+        .bind(exclusion.kind.as_str())
+        .bind(exclusion.until)
+        .fetch_one(&self.db)
+        .await?
+        .into_exclusion()?
+        .ok_or(Error::InternalServerError)
+    }
+}
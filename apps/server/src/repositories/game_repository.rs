@@ -1,25 +1,36 @@
-use crate::http::Result;
-use crate::models::{Game, GameState, Player};
+use crate::http::{Error, Result};
+use crate::internal_error;
+use crate::models::{
+    Game, GameResultInfo, GameState, Msat, Player, RatingCategory, Sats, StakeMode, Termination,
+};
 use crate::states::db;
 use mockall::automock;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
-use sqlx::{Pool, Postgres};
+use sqlx::{PgConnection, Pool, Postgres};
 use uuid::Uuid;
 
+use super::rating_repository::post_rating_update;
+use super::stake_hold_repository::{
+    post_hold_assignment, post_hold_resolution, HoldResolution, ResolveHold,
+};
+use super::wallet_repository::{post_transfer, SaveTransfer, TransferKind};
+
 #[derive(FromRow)]
 struct GameRecord {
     id: Uuid,
     white_player: Uuid,
     black_player: Uuid,
-    bet_value: i32,
+    bet_value: Sats,
     state: String,
     moves: Vec<String>,
+    stake_mode: String,
+    rating_category: Option<String>,
 }
 
 impl GameRecord {
-    fn to_game(self) -> Result<Game> {
+    fn into_game(self) -> Result<Game> {
         Ok(Game {
             id: self.id,
             white_player: self.white_player,
@@ -27,6 +38,12 @@ impl GameRecord {
             bet_value: self.bet_value,
             state: GameState::from_str(&self.state)?,
             moves: self.moves,
+            stake_mode: StakeMode::from_str(&self.stake_mode)?,
+            category: self
+                .rating_category
+                .as_deref()
+                .map(RatingCategory::from_str)
+                .transpose()?,
         })
     }
 }
@@ -36,18 +53,58 @@ pub struct GameWithPlayers {
     pub id: Uuid,
     pub white_player: Player,
     pub black_player: Player,
-    pub bet_value: i32,
+    pub bet_value: Sats,
     pub state: GameState,
     pub moves: Vec<String>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settlement {
+    pub game_id: Uuid,
+    pub state: GameState,
+    pub termination: Termination,
+    pub last_move: Option<String>,
+    pub transfers: Vec<SaveTransfer>,
+    /// Only set for games staked with hold invoices.
+    pub holds: Vec<ResolveHold>,
+}
+
+impl Settlement {
+    pub fn result(&self) -> Result<GameResultInfo> {
+        Ok(GameResultInfo {
+            game_id: self.game_id,
+            state: self.state,
+            termination: self.termination,
+            fee: self
+                .transfers
+                .iter()
+                .filter(|transfer| transfer.kind == TransferKind::Fee)
+                .map(|transfer| transfer.amount)
+                .chain(self.holds.iter().map(|hold| match hold.resolution {
+                    HoldResolution::Settle { fee, .. } => fee,
+                    HoldResolution::Cancel => Msat::ZERO,
+                }))
+                .try_fold(Msat::ZERO, Msat::checked_add)
+                .ok_or(internal_error!())?,
+        })
+    }
+}
+
 #[automock]
 pub trait GameRepositoryTrait {
     async fn get_player(&self, user_id: Uuid) -> Result<Player>;
     async fn get_game_with_players(&self, game_id: Uuid) -> Result<GameWithPlayers>;
     async fn get_game(&self, game_id: Uuid) -> Result<Game>;
-    async fn save_game(&self, game: Game) -> Result<()>;
+    /// Saves the game, posts the stake transfers and assigns the stake holds
+    /// of the `stake_holds` payment hashes to it in the same transaction.
+    async fn start_game(
+        &self,
+        game: Game,
+        transfers: Vec<SaveTransfer>,
+        stake_holds: Vec<String>,
+    ) -> Result<()>;
     async fn update_state(&self, game_id: Uuid, new_state: GameState) -> Result<()>;
+    async fn settle_game(&self, settlement: Settlement) -> Result<bool>;
     async fn record_move(&self, game_id: Uuid, move_played: String) -> Result<()>;
 }
 
@@ -61,6 +118,23 @@ impl GameRepository {
     }
 }
 
+pub(super) async fn insert_game(conn: &mut PgConnection, game: Game) -> Result<()> {
+    sqlx::query(
+        r#" INSERT INTO games (id, white_player, black_player, bet_value, moves, stake_mode, rating_category) VALUES ($1, $2, $3, $4, $5, $6, $7); "#,
+    )
+    .bind(game.id)
+    .bind(game.white_player)
+    .bind(game.black_player)
+    .bind(game.bet_value)
+    .bind(&game.moves)
+    .bind(game.stake_mode.to_string())
+    .bind(game.category.map(RatingCategory::as_str))
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
 impl GameRepositoryTrait for GameRepository {
     async fn get_player(&self, user_id: Uuid) -> Result<Player> {
         Ok(
@@ -73,11 +147,11 @@ impl GameRepositoryTrait for GameRepository {
 
     async fn get_game_with_players(&self, game_id: Uuid) -> Result<GameWithPlayers> {
         let game = sqlx::query_as::<_, GameRecord>(
-            r#" SELECT id, white_player, black_player, bet_value, moves, state FROM games WHERE id = $1 "#,
+            r#" SELECT id, white_player, black_player, bet_value, moves, state, stake_mode, rating_category FROM games WHERE id = $1 "#,
         )
         .bind(game_id)
         .fetch_one(&self.db)
-        .await?.to_game()?;
+        .await?.into_game()?;
 
         Ok(GameWithPlayers {
             id: game.id,
@@ -91,34 +165,41 @@ impl GameRepositoryTrait for GameRepository {
 
     async fn get_game(&self, game_id: Uuid) -> Result<Game> {
         let game = sqlx::query_as::<_, GameRecord>(
-            r#" SELECT id, white_player, black_player, bet_value, moves, state FROM games WHERE id = $1 "#,
+            r#" SELECT id, white_player, black_player, bet_value, moves, state, stake_mode, rating_category FROM games WHERE id = $1 "#,
         )
         .bind(game_id)
         .fetch_one(&self.db)
-        .await?.to_game()?;
+        .await?.into_game()?;
 
         Ok(game)
     }
 
-    async fn save_game(&self, game: Game) -> Result<()> {
-        let result = sqlx::query(
-            r#" INSERT INTO games (id, white_player, black_player, bet_value, moves) VALUES ($1, $2, $3, $4, $5); "#,
-        )
-        .bind(game.id)
-        .bind(game.white_player)
-        .bind(game.black_player)
-        .bind(game.bet_value)
-        .bind(&game.moves)
-        .execute(&self.db)
-        .await;
+    async fn start_game(
+        &self,
+        game: Game,
+        transfers: Vec<SaveTransfer>,
+        stake_holds: Vec<String>,
+    ) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+        let game_id = game.id;
+
+        insert_game(&mut tx, game).await?;
 
-        result?;
+        for transfer in transfers {
+            post_transfer(&mut tx, transfer).await?;
+        }
+
+        for payment_hash in stake_holds {
+            post_hold_assignment(&mut tx, payment_hash, game_id).await?;
+        }
+
+        tx.commit().await?;
 
         Ok(())
     }
 
     async fn update_state(&self, game_id: Uuid, new_state: GameState) -> Result<()> {
-        sqlx::query(&format!("UPDATE games SET state = $1 WHERE id = $2;",))
+        sqlx::query(r#" UPDATE games SET state = $1 WHERE id = $2 "#)
             .bind(new_state.to_string())
             .bind(game_id)
             .execute(&self.db)
@@ -127,6 +208,53 @@ impl GameRepositoryTrait for GameRepository {
         Ok(())
     }
 
+    async fn settle_game(&self, settlement: Settlement) -> Result<bool> {
+        let mut tx = self.db.begin().await?;
+
+        let settled = sqlx::query(
+            r#"
+                UPDATE games
+                SET state = $1,
+                    termination = $2,
+                    moves = CASE WHEN $3::text IS NULL THEN moves ELSE array_append(moves, $3) END,
+                    settled_at = now()
+                WHERE id = $4 AND settled_at IS NULL
+            "#,
+        )
+        .bind(settlement.state.to_string())
+        .bind(settlement.termination.to_string())
+        .bind(settlement.last_move)
+        .bind(settlement.game_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // The game was already settled by an earlier attempt, so paying again
+        // would credit the players twice.
+        if settled == 0 {
+            return Ok(false);
+        }
+
+        for transfer in settlement.transfers {
+            if transfer.amount.is_positive() {
+                post_transfer(&mut tx, transfer).await?;
+            }
+        }
+
+        for hold in settlement.holds {
+            post_hold_resolution(&mut tx, settlement.game_id, hold).await?;
+        }
+
+        // Aborted games never started, so they leave the ratings alone.
+        if settlement.termination != Termination::Aborted {
+            post_rating_update(&mut tx, settlement.game_id, settlement.state).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
     async fn record_move(&self, game_id: Uuid, move_played: String) -> Result<()> {
         sqlx::query(r#" UPDATE games SET moves = array_append(moves, $1) WHERE id = $2 "#)
             .bind(move_played)
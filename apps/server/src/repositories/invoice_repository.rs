@@ -0,0 +1,145 @@
+use crate::http::Result;
+use crate::models::{DecodedInvoice, InvoiceStatus, Msat};
+use crate::states::db;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, Pool, Postgres};
+use uuid::Uuid;
+
+use super::wallet_repository::{post_transfer, SaveTransfer};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Invoice {
+    pub user_id: Uuid,
+    pub payment_hash: String,
+    pub payment_request: String,
+    pub amount: Msat,
+    pub status: InvoiceStatus,
+    pub expires_at: DateTime<Utc>,
+    pub paid_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct InvoiceRecord {
+    user_id: Uuid,
+    payment_hash: String,
+    payment_request: String,
+    amount: Msat,
+    status: String,
+    expires_at: DateTime<Utc>,
+    paid_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl InvoiceRecord {
+    fn into_invoice(self) -> Result<Invoice> {
+        Ok(Invoice {
+            user_id: self.user_id,
+            payment_hash: self.payment_hash,
+            payment_request: self.payment_request,
+            amount: self.amount,
+            status: InvoiceStatus::from_str(&self.status)?,
+            expires_at: self.expires_at,
+            paid_at: self.paid_at,
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[automock]
+pub trait InvoiceRepositoryTrait {
+    async fn save_invoice(&self, user_id: Uuid, invoice: DecodedInvoice) -> Result<()>;
+    async fn get_invoice(&self, payment_hash: String) -> Result<Invoice>;
+    async fn settle_invoice(&self, payment_hash: String) -> Result<bool>;
+}
+
+pub struct InvoiceRepository {
+    db: Pool<Postgres>,
+}
+
+impl InvoiceRepository {
+    pub fn new() -> Self {
+        Self { db: db::get() }
+    }
+}
+
+impl InvoiceRepositoryTrait for InvoiceRepository {
+    async fn save_invoice(&self, user_id: Uuid, invoice: DecodedInvoice) -> Result<()> {
+        sqlx::query(
+            r#" INSERT INTO invoices (user_id, payment_hash, payment_request, amount, expires_at) VALUES ($1, $2, $3, $4, $5) "#,
+        )
+        .bind(user_id)
+        .bind(invoice.payment_hash)
+        .bind(invoice.payment_request)
+        .bind(invoice.amount)
+        .bind(invoice.expires_at)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_invoice(&self, payment_hash: String) -> Result<Invoice> {
+        sqlx::query(
+            r#" UPDATE invoices SET status = 'expired' WHERE payment_hash = $1 AND status = 'pending' AND expires_at <= now() "#,
+        )
+        .bind(&payment_hash)
+        .execute(&self.db)
+        .await?;
+
+        sqlx::query_as::<_, InvoiceRecord>(
+            r#"
+                SELECT user_id, payment_hash, payment_request, amount, status, expires_at, paid_at, created_at
+                FROM invoices
+                WHERE payment_hash = $1
+            "#,
+        )
+        .bind(payment_hash)
+        .fetch_one(&self.db)
+        .await?
+        .into_invoice()
+    }
+
+    /// Marks the invoice as paid and credits its owner in the same transaction.
+    /// Returns false without crediting anything if it was already paid.
+    async fn settle_invoice(&self, payment_hash: String) -> Result<bool> {
+        let mut tx = self.db.begin().await?;
+
+        let invoice = sqlx::query_as::<_, InvoiceRecord>(
+            r#"
+                SELECT user_id, payment_hash, payment_request, amount, status, expires_at, paid_at, created_at
+                FROM invoices
+                WHERE payment_hash = $1
+                FOR UPDATE
+            "#,
+        )
+        .bind(&payment_hash)
+        .fetch_one(&mut *tx)
+        .await?
+        .into_invoice()?;
+
+        if invoice.status == InvoiceStatus::Paid {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#" UPDATE invoices SET status = 'paid', paid_at = now() WHERE payment_hash = $1 "#,
+        )
+        .bind(&payment_hash)
+        .execute(&mut *tx)
+        .await?;
+
+        post_transfer(
+            &mut tx,
+            SaveTransfer::deposit(invoice.user_id, invoice.amount, invoice.payment_request),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+}
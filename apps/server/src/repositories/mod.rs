@@ -6,3 +6,27 @@ pub use user_repository::*;
 
 mod wallet_repository;
 pub use wallet_repository::*;
+
+mod invoice_repository;
+pub use invoice_repository::*;
+
+mod withdraw_link_repository;
+pub use withdraw_link_repository::*;
+
+mod stake_hold_repository;
+pub use stake_hold_repository::*;
+
+mod reconciliation_repository;
+pub use reconciliation_repository::*;
+
+mod withdrawal_repository;
+pub use withdrawal_repository::*;
+
+mod gambling_limit_repository;
+pub use gambling_limit_repository::*;
+
+mod rating_repository;
+pub use rating_repository::*;
+
+mod challenge_repository;
+pub use challenge_repository::*;
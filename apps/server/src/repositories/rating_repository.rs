@@ -0,0 +1,271 @@
+use crate::http::Result;
+use crate::models::{GameState, Rating, RatingCategory};
+use crate::states::db;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgConnection, Pool, Postgres};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CategoryRating {
+    pub category: RatingCategory,
+    #[serde(flatten)]
+    pub rating: Rating,
+    pub games: i32,
+}
+
+/// A player's rating right after one of their rated games.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RatingChange {
+    pub game_id: Uuid,
+    pub category: RatingCategory,
+    #[serde(flatten)]
+    pub rating: Rating,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct RatingRecord {
+    user_id: Uuid,
+    category: String,
+    rating: f64,
+    deviation: f64,
+    volatility: f64,
+    games: i32,
+}
+
+impl RatingRecord {
+    fn rating(&self) -> Rating {
+        Rating {
+            rating: self.rating,
+            deviation: self.deviation,
+            volatility: self.volatility,
+        }
+    }
+
+    fn into_category_rating(self) -> Result<CategoryRating> {
+        Ok(CategoryRating {
+            category: RatingCategory::from_str(&self.category)?,
+            rating: self.rating(),
+            games: self.games,
+        })
+    }
+}
+
+#[derive(FromRow)]
+struct RatingChangeRecord {
+    game_id: Uuid,
+    category: String,
+    rating: f64,
+    deviation: f64,
+    volatility: f64,
+    created_at: DateTime<Utc>,
+}
+
+impl RatingChangeRecord {
+    fn into_rating_change(self) -> Result<RatingChange> {
+        Ok(RatingChange {
+            game_id: self.game_id,
+            category: RatingCategory::from_str(&self.category)?,
+            rating: Rating {
+                rating: self.rating,
+                deviation: self.deviation,
+                volatility: self.volatility,
+            },
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[automock]
+pub trait RatingRepositoryTrait {
+    /// Rating of the user in `category`, the default one if they have never
+    /// played a rated game there.
+    async fn get_rating(&self, user_id: Uuid, category: RatingCategory) -> Result<Rating>;
+    /// Ratings of the categories the user has played rated games in.
+    async fn get_ratings(&self, user_id: Uuid) -> Result<Vec<CategoryRating>>;
+    /// Latest rating changes first.
+    async fn get_rating_history(&self, user_id: Uuid, limit: i64) -> Result<Vec<RatingChange>>;
+}
+
+pub struct RatingRepository {
+    db: Pool<Postgres>,
+}
+
+impl RatingRepository {
+    pub fn new() -> Self {
+        Self { db: db::get() }
+    }
+}
+
+#[derive(FromRow)]
+struct RatedGame {
+    white_player: Uuid,
+    black_player: Uuid,
+    rating_category: Option<String>,
+}
+
+/// Rates a game that just ended in `state`. Both players' ratings are locked
+/// until the surrounding transaction ends, so concurrent games of a player
+/// are rated one after the other.
+pub(super) async fn post_rating_update(
+    conn: &mut PgConnection,
+    game_id: Uuid,
+    state: GameState,
+) -> Result<()> {
+    let white_score = match state {
+        GameState::WhiteWin => 1.0,
+        GameState::BlackWin => 0.0,
+        GameState::Draw => 0.5,
+        GameState::Waiting | GameState::Running => return Ok(()),
+    };
+
+    let game = sqlx::query_as::<_, RatedGame>(
+        r#" SELECT white_player, black_player, rating_category FROM games WHERE id = $1 "#,
+    )
+    .bind(game_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let Some(category) = game.rating_category else {
+        return Ok(());
+    };
+
+    let default = Rating::default();
+
+    sqlx::query(
+        r#"
+            INSERT INTO ratings (user_id, category, rating, deviation, volatility)
+            SELECT user_id, $2, $3, $4, $5 FROM unnest($1::uuid[]) AS user_id
+            ON CONFLICT (user_id, category) DO NOTHING
+        "#,
+    )
+    .bind(vec![game.white_player, game.black_player])
+    .bind(&category)
+    .bind(default.rating)
+    .bind(default.deviation)
+    .bind(default.volatility)
+    .execute(&mut *conn)
+    .await?;
+
+    let ratings = sqlx::query_as::<_, RatingRecord>(
+        r#"
+            SELECT user_id, category, rating, deviation, volatility, games
+            FROM ratings
+            WHERE user_id IN ($1, $2) AND category = $3
+            ORDER BY user_id
+            FOR UPDATE
+        "#,
+    )
+    .bind(game.white_player)
+    .bind(game.black_player)
+    .bind(&category)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let rating_of = |user_id: Uuid| {
+        ratings
+            .iter()
+            .find(|record| record.user_id == user_id)
+            .map(RatingRecord::rating)
+            .unwrap_or_default()
+    };
+    let white = rating_of(game.white_player);
+    let black = rating_of(game.black_player);
+
+    for (user_id, rating) in [
+        (game.white_player, white.update(&[(black, white_score)])),
+        (
+            game.black_player,
+            black.update(&[(white, 1.0 - white_score)]),
+        ),
+    ] {
+        sqlx::query(
+            r#"
+                UPDATE ratings
+                SET rating = $3, deviation = $4, volatility = $5, games = games + 1
+                WHERE user_id = $1 AND category = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(&category)
+        .bind(rating.rating)
+        .bind(rating.deviation)
+        .bind(rating.volatility)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+                INSERT INTO rating_history (user_id, game_id, category, rating, deviation, volatility)
+                VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(user_id)
+        .bind(game_id)
+        .bind(&category)
+        .bind(rating.rating)
+        .bind(rating.deviation)
+        .bind(rating.volatility)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+impl RatingRepositoryTrait for RatingRepository {
+    async fn get_rating(&self, user_id: Uuid, category: RatingCategory) -> Result<Rating> {
+        Ok(sqlx::query_as::<_, RatingRecord>(
+            r#"
+                SELECT user_id, category, rating, deviation, volatility, games
+                FROM ratings
+                WHERE user_id = $1 AND category = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(category.as_str())
+        .fetch_optional(&self.db)
+        .await?
+        .map(|record| record.rating())
+        .unwrap_or_default())
+    }
+
+    async fn get_ratings(&self, user_id: Uuid) -> Result<Vec<CategoryRating>> {
+        sqlx::query_as::<_, RatingRecord>(
+            r#"
+                SELECT user_id, category, rating, deviation, volatility, games
+                FROM ratings
+                WHERE user_id = $1
+                ORDER BY category
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(RatingRecord::into_category_rating)
+        .collect()
+    }
+
+    async fn get_rating_history(&self, user_id: Uuid, limit: i64) -> Result<Vec<RatingChange>> {
+        sqlx::query_as::<_, RatingChangeRecord>(
+            r#"
+                SELECT game_id, category, rating, deviation, volatility, created_at
+                FROM rating_history
+                WHERE user_id = $1
+                ORDER BY created_at DESC
+                LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(RatingChangeRecord::into_rating_change)
+        .collect()
+    }
+}
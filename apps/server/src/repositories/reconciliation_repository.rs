@@ -0,0 +1,114 @@
+use crate::http::Result;
+use crate::models::{LedgerTotals, Msat};
+use crate::states::db;
+use mockall::automock;
+use sqlx::{prelude::FromRow, Pool, Postgres};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct AccountDrift {
+    pub account_id: Uuid,
+    pub balance: Msat,
+    pub last_balance: Msat,
+}
+
+#[derive(FromRow)]
+struct KindTotal {
+    kind: String,
+    balance: Msat,
+}
+
+#[automock]
+pub trait ReconciliationRepositoryTrait {
+    async fn get_ledger_totals(&self) -> Result<LedgerTotals>;
+    async fn list_account_drifts(&self) -> Result<Vec<AccountDrift>>;
+    /// Payment hashes of incoming payments the ledger accounts for.
+    async fn list_credited_payments(&self) -> Result<Vec<String>>;
+    /// Invoices of outgoing payments the ledger accounts for.
+    async fn list_debited_invoices(&self) -> Result<Vec<String>>;
+}
+
+pub struct ReconciliationRepository {
+    db: Pool<Postgres>,
+}
+
+impl ReconciliationRepository {
+    pub fn new() -> Self {
+        Self { db: db::get() }
+    }
+}
+
+impl ReconciliationRepositoryTrait for ReconciliationRepository {
+    async fn get_ledger_totals(&self) -> Result<LedgerTotals> {
+        let totals = sqlx::query_as::<_, KindTotal>(
+            r#" SELECT kind, sum(balance)::bigint AS balance FROM accounts GROUP BY kind "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let total_of = |kind: &str| {
+            totals
+                .iter()
+                .find(|total| total.kind == kind)
+                .map(|total| total.balance)
+                .unwrap_or_default()
+        };
+
+        Ok(LedgerTotals {
+            users: total_of("user"),
+            escrow: total_of("escrow"),
+            platform: total_of("platform"),
+            lightning: total_of("lightning"),
+        })
+    }
+
+    async fn list_account_drifts(&self) -> Result<Vec<AccountDrift>> {
+        Ok(sqlx::query_as::<_, AccountDrift>(
+            r#"
+                SELECT accounts.id AS account_id, accounts.balance, coalesce(latest.last_balance, 0) AS last_balance
+                FROM accounts
+                LEFT JOIN LATERAL (
+                    SELECT last_balance
+                    FROM entries
+                    WHERE entries.account_id = accounts.id
+                    ORDER BY created_at DESC, id DESC
+                    LIMIT 1
+                ) latest ON true
+                WHERE accounts.balance <> coalesce(latest.last_balance, 0)
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?)
+    }
+
+    async fn list_credited_payments(&self) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            r#"
+                SELECT payment_hash FROM invoices WHERE status = 'paid'
+                UNION ALL
+                SELECT payment_hash FROM stake_holds WHERE status = 'settled'
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?)
+    }
+
+    async fn list_debited_invoices(&self) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            r#"
+                SELECT withdrawal.invoice
+                FROM transfers withdrawal
+                WHERE withdrawal.type = 'withdrawal'
+                  AND withdrawal.invoice IS NOT NULL
+                  AND NOT EXISTS (
+                      SELECT 1 FROM transfers refund
+                      WHERE refund.type = 'refund' AND refund.invoice = withdrawal.invoice
+                  )
+                UNION ALL
+                SELECT payout_invoice FROM stake_holds WHERE payout_invoice IS NOT NULL
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?)
+    }
+}
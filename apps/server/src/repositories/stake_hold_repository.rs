@@ -0,0 +1,417 @@
+use crate::http::{Error, Result};
+use crate::models::Msat;
+use crate::states::db;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use sqlx::{prelude::FromRow, PgConnection, Pool, Postgres};
+use uuid::Uuid;
+
+use super::wallet_repository::{post_transfer, SaveTransfer};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HoldStatus {
+    /// Waiting for the player to pay the hold invoice.
+    Pending,
+    /// Paid and locked for a game.
+    Accepted,
+    Settled,
+    Canceled,
+}
+
+impl HoldStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Accepted => "accepted",
+            Self::Settled => "settled",
+            Self::Canceled => "canceled",
+        }
+    }
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "pending" => Ok(Self::Pending),
+            "accepted" => Ok(Self::Accepted),
+            "settled" => Ok(Self::Settled),
+            "canceled" => Ok(Self::Canceled),
+            _ => Err(Error::InternalServerError),
+        }
+    }
+}
+
+/// What happens to a player's hold once their game is over.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoldResolution {
+    Cancel,
+    /// The loser's hold is settled and `payout_amount` goes to the winner.
+    Settle {
+        payout_user_id: Uuid,
+        payout_amount: Msat,
+        fee: Msat,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveHold {
+    pub user_id: Uuid,
+    pub resolution: HoldResolution,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveStakeHold {
+    pub user_id: Uuid,
+    pub payment_hash: String,
+    pub preimage: String,
+    pub payment_request: String,
+    pub amount: Msat,
+    pub payout_destination: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakeHold {
+    pub user_id: Uuid,
+    pub game_id: Option<Uuid>,
+    pub payment_hash: String,
+    pub preimage: String,
+    pub amount: Msat,
+    pub status: HoldStatus,
+    /// Holds without a resolution are only handed out to be cancelled after
+    /// they were left unused.
+    pub resolution: Option<HoldResolution>,
+}
+
+/// Winnings of a settled hold that still have to reach the winner.
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct HoldPayout {
+    pub payment_hash: String,
+    pub game_id: Uuid,
+    pub user_id: Uuid,
+    pub amount: Msat,
+    pub fee: Msat,
+    pub destination: Option<String>,
+    /// Invoice of a payment to the destination that was started but not
+    /// completed.
+    pub payout_invoice: Option<String>,
+}
+
+#[derive(FromRow)]
+struct StakeHoldRecord {
+    user_id: Uuid,
+    game_id: Option<Uuid>,
+    payment_hash: String,
+    preimage: String,
+    amount: Msat,
+    status: String,
+    resolution: Option<String>,
+    payout_user_id: Option<Uuid>,
+    payout_amount: Option<Msat>,
+    fee: Option<Msat>,
+}
+
+impl StakeHoldRecord {
+    fn into_stake_hold(self) -> Result<StakeHold> {
+        let resolution = match (
+            self.resolution.as_deref(),
+            self.payout_user_id,
+            self.payout_amount,
+            self.fee,
+        ) {
+            (None, ..) => None,
+            (Some("cancel"), ..) => Some(HoldResolution::Cancel),
+            (Some("settle"), Some(payout_user_id), Some(payout_amount), Some(fee)) => {
+                Some(HoldResolution::Settle {
+                    payout_user_id,
+                    payout_amount,
+                    fee,
+                })
+            }
+            _ => return Err(Error::InternalServerError),
+        };
+
+        Ok(StakeHold {
+            user_id: self.user_id,
+            game_id: self.game_id,
+            payment_hash: self.payment_hash,
+            preimage: self.preimage,
+            amount: self.amount,
+            status: HoldStatus::from_str(&self.status)?,
+            resolution,
+        })
+    }
+}
+
+#[automock]
+pub trait StakeHoldRepositoryTrait {
+    async fn save_stake_hold(&self, hold: SaveStakeHold) -> Result<()>;
+    async fn get_stake_hold(&self, payment_hash: String) -> Result<StakeHold>;
+    /// Locks a paid hold for pairing. Returns false if it was already used.
+    async fn claim_stake_hold(&self, user_id: Uuid, payment_hash: String) -> Result<bool>;
+    /// Frees a claimed hold that no game took, so it can be used again.
+    async fn release_stake_hold(&self, payment_hash: String) -> Result<()>;
+    async fn assign_stake_hold(&self, payment_hash: String, game_id: Uuid) -> Result<()>;
+    async fn resolve_stake_hold(&self, game_id: Uuid, hold: ResolveHold) -> Result<()>;
+    /// Holds with a resolution, and unused holds created before `stale_before`.
+    async fn list_unresolved_holds(&self, stale_before: DateTime<Utc>) -> Result<Vec<StakeHold>>;
+    async fn update_hold_status(&self, payment_hash: String, status: HoldStatus) -> Result<()>;
+    async fn list_hold_payouts(&self) -> Result<Vec<HoldPayout>>;
+    /// Records the invoice about to be paid to the winner, so the payout is
+    /// never paid twice. Returns false if a payment was already started.
+    async fn start_hold_payout(&self, payment_hash: String, payout_invoice: String)
+        -> Result<bool>;
+    /// Marks the payout as done and posts its transfers in the same
+    /// transaction. Returns false if it was already done.
+    async fn complete_hold_payout(
+        &self,
+        payment_hash: String,
+        payout_invoice: Option<String>,
+        transfers: Vec<SaveTransfer>,
+    ) -> Result<bool>;
+}
+
+pub struct StakeHoldRepository {
+    db: Pool<Postgres>,
+}
+
+impl StakeHoldRepository {
+    pub fn new() -> Self {
+        Self { db: db::get() }
+    }
+}
+
+pub(super) async fn post_hold_assignment(
+    conn: &mut PgConnection,
+    payment_hash: String,
+    game_id: Uuid,
+) -> Result<()> {
+    sqlx::query(r#" UPDATE stake_holds SET game_id = $1 WHERE payment_hash = $2 "#)
+        .bind(game_id)
+        .bind(payment_hash)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+pub(super) async fn post_hold_resolution(
+    conn: &mut PgConnection,
+    game_id: Uuid,
+    ResolveHold {
+        user_id,
+        resolution,
+    }: ResolveHold,
+) -> Result<()> {
+    let (resolution, payout_user_id, payout_amount, fee) = match resolution {
+        HoldResolution::Cancel => ("cancel", None, None, None),
+        HoldResolution::Settle {
+            payout_user_id,
+            payout_amount,
+            fee,
+        } => (
+            "settle",
+            Some(payout_user_id),
+            Some(payout_amount),
+            Some(fee),
+        ),
+    };
+
+    sqlx::query(
+        r#"
+            UPDATE stake_holds
+            SET resolution = $1, payout_user_id = $2, payout_amount = $3, fee = $4
+            WHERE game_id = $5 AND user_id = $6 AND resolution IS NULL
+        "#,
+    )
+    .bind(resolution)
+    .bind(payout_user_id)
+    .bind(payout_amount)
+    .bind(fee)
+    .bind(game_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+impl StakeHoldRepositoryTrait for StakeHoldRepository {
+    async fn save_stake_hold(&self, hold: SaveStakeHold) -> Result<()> {
+        sqlx::query(
+            r#"
+                INSERT INTO stake_holds (user_id, payment_hash, preimage, payment_request, amount, payout_destination)
+                VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(hold.user_id)
+        .bind(hold.payment_hash)
+        .bind(hold.preimage)
+        .bind(hold.payment_request)
+        .bind(hold.amount)
+        .bind(hold.payout_destination)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_stake_hold(&self, payment_hash: String) -> Result<StakeHold> {
+        sqlx::query_as::<_, StakeHoldRecord>(
+            r#"
+                SELECT user_id, game_id, payment_hash, preimage, amount, status, resolution,
+                       payout_user_id, payout_amount, fee
+                FROM stake_holds
+                WHERE payment_hash = $1
+            "#,
+        )
+        .bind(payment_hash)
+        .fetch_one(&self.db)
+        .await?
+        .into_stake_hold()
+    }
+
+    async fn claim_stake_hold(&self, user_id: Uuid, payment_hash: String) -> Result<bool> {
+        let claimed = sqlx::query(
+            r#"
+                UPDATE stake_holds
+                SET status = 'accepted'
+                WHERE payment_hash = $1 AND user_id = $2 AND status = 'pending' AND resolution IS NULL
+            "#,
+        )
+        .bind(payment_hash)
+        .bind(user_id)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        Ok(claimed > 0)
+    }
+
+    async fn release_stake_hold(&self, payment_hash: String) -> Result<()> {
+        sqlx::query(
+            r#"
+                UPDATE stake_holds
+                SET status = 'pending'
+                WHERE payment_hash = $1 AND status = 'accepted' AND game_id IS NULL AND resolution IS NULL
+            "#,
+        )
+        .bind(payment_hash)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn assign_stake_hold(&self, payment_hash: String, game_id: Uuid) -> Result<()> {
+        let mut conn = self.db.acquire().await?;
+
+        post_hold_assignment(&mut conn, payment_hash, game_id).await
+    }
+
+    async fn resolve_stake_hold(&self, game_id: Uuid, hold: ResolveHold) -> Result<()> {
+        let mut conn = self.db.acquire().await?;
+
+        post_hold_resolution(&mut conn, game_id, hold).await
+    }
+
+    async fn list_unresolved_holds(&self, stale_before: DateTime<Utc>) -> Result<Vec<StakeHold>> {
+        sqlx::query_as::<_, StakeHoldRecord>(
+            r#"
+                SELECT user_id, game_id, payment_hash, preimage, amount, status, resolution,
+                       payout_user_id, payout_amount, fee
+                FROM stake_holds
+                WHERE status IN ('pending', 'accepted')
+                  AND (resolution IS NOT NULL OR (game_id IS NULL AND created_at < $1))
+                ORDER BY created_at
+            "#,
+        )
+        .bind(stale_before)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(StakeHoldRecord::into_stake_hold)
+        .collect()
+    }
+
+    async fn update_hold_status(&self, payment_hash: String, status: HoldStatus) -> Result<()> {
+        sqlx::query(r#" UPDATE stake_holds SET status = $1 WHERE payment_hash = $2 "#)
+            .bind(status.as_str())
+            .bind(payment_hash)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn list_hold_payouts(&self) -> Result<Vec<HoldPayout>> {
+        Ok(sqlx::query_as::<_, HoldPayout>(
+            r#"
+                SELECT loser.payment_hash, loser.game_id, loser.payout_user_id AS user_id,
+                       loser.payout_amount AS amount, loser.fee, winner.payout_destination AS destination,
+                       loser.payout_invoice
+                FROM stake_holds loser
+                LEFT JOIN stake_holds winner
+                  ON winner.game_id = loser.game_id AND winner.user_id = loser.payout_user_id
+                WHERE loser.status = 'settled' AND loser.resolution = 'settle' AND loser.paid_out_at IS NULL
+                ORDER BY loser.created_at
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?)
+    }
+
+    async fn start_hold_payout(
+        &self,
+        payment_hash: String,
+        payout_invoice: String,
+    ) -> Result<bool> {
+        let started = sqlx::query(
+            r#"
+                UPDATE stake_holds
+                SET payout_invoice = $1
+                WHERE payment_hash = $2 AND paid_out_at IS NULL AND payout_invoice IS NULL
+            "#,
+        )
+        .bind(payout_invoice)
+        .bind(payment_hash)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        Ok(started > 0)
+    }
+
+    async fn complete_hold_payout(
+        &self,
+        payment_hash: String,
+        payout_invoice: Option<String>,
+        transfers: Vec<SaveTransfer>,
+    ) -> Result<bool> {
+        let mut tx = self.db.begin().await?;
+
+        let completed = sqlx::query(
+            r#"
+                UPDATE stake_holds
+                SET paid_out_at = now(), payout_invoice = $1
+                WHERE payment_hash = $2 AND paid_out_at IS NULL
+            "#,
+        )
+        .bind(payout_invoice)
+        .bind(payment_hash)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if completed == 0 {
+            return Ok(false);
+        }
+
+        for transfer in transfers {
+            if transfer.amount.is_positive() {
+                post_transfer(&mut tx, transfer).await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+}
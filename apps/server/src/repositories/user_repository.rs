@@ -1,11 +1,12 @@
 use crate::states::db;
 
 use crate::http::{Error, Result};
+use mockall::automock;
 use sqlx::prelude::FromRow;
 use sqlx::{Pool, Postgres};
 use uuid::Uuid;
 
-use crate::models::User;
+use crate::models::{Msat, User};
 
 pub struct SaveUser {
     pub username: String,
@@ -28,13 +29,16 @@ struct ReturnedUser {
 
 #[derive(FromRow)]
 struct ReturnedLastBalance {
-    last_balance: i32,
+    last_balance: Msat,
 }
 
+#[automock]
 pub trait UserRepositoryTrait {
     async fn find_by_email(&self, email: String) -> Result<User>;
+    async fn find_by_username(&self, username: String) -> Result<User>;
     async fn find_by_id(&self, id: Uuid) -> Result<User>;
     async fn save(&self, user: SaveUser) -> Result<Uuid>;
+    async fn is_admin(&self, id: Uuid) -> Result<bool>;
 }
 
 pub struct UserRepository {
@@ -61,10 +65,43 @@ impl UserRepositoryTrait for UserRepository {
         .fetch_one(&self.db)
         .await?;
 
-        let ReturnedLastBalance { last_balance: balance } = sqlx::query_as::<_, ReturnedLastBalance>(
-            r#" SELECT last_balance FROM transactions WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1 "#,
+        let ReturnedLastBalance {
+            last_balance: balance,
+        } = sqlx::query_as::<_, ReturnedLastBalance>(
+            r#" SELECT balance AS last_balance FROM accounts WHERE user_id = $1 "#,
         )
-            .bind(id)
+        .bind(id)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(User {
+            id,
+            username,
+            email,
+            hashed_password,
+            balance,
+        })
+    }
+
+    async fn find_by_username(&self, username: String) -> Result<User> {
+        let ReturnedUser {
+            id,
+            username,
+            email,
+            password: hashed_password,
+        } = sqlx::query_as::<_, ReturnedUser>(
+            r#" SELECT id, username, email, password FROM users WHERE username = $1 "#,
+        )
+        .bind(username)
+        .fetch_one(&self.db)
+        .await?;
+
+        let ReturnedLastBalance {
+            last_balance: balance,
+        } = sqlx::query_as::<_, ReturnedLastBalance>(
+            r#" SELECT balance AS last_balance FROM accounts WHERE user_id = $1 "#,
+        )
+        .bind(id)
         .fetch_one(&self.db)
         .await?;
 
@@ -90,8 +127,10 @@ impl UserRepositoryTrait for UserRepository {
         .fetch_one(&self.db)
         .await?;
 
-        let ReturnedLastBalance { last_balance: balance } = sqlx::query_as::<_, ReturnedLastBalance>(
-            r#" SELECT last_balance FROM transactions WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1 "#,
+        let ReturnedLastBalance {
+            last_balance: balance,
+        } = sqlx::query_as::<_, ReturnedLastBalance>(
+            r#" SELECT balance AS last_balance FROM accounts WHERE user_id = $1 "#,
         )
         .bind(id)
         .fetch_one(&self.db)
@@ -107,13 +146,15 @@ impl UserRepositoryTrait for UserRepository {
     }
 
     async fn save(&self, user: SaveUser) -> Result<Uuid> {
+        let mut tx = self.db.begin().await?;
+
         let ReturnedId { id } = sqlx::query_as::<_, ReturnedId>(
             r#" INSERT INTO users (username, email, password) VALUES ($1, $2, $3) RETURNING id "#,
         )
         .bind(user.username)
         .bind(user.email)
         .bind(user.password_hash)
-        .fetch_one(&self.db)
+        .fetch_one(&mut *tx)
         .await
         .map_err(|e| {
             if let sqlx::Error::Database(db_err) = &e {
@@ -124,13 +165,25 @@ impl UserRepositoryTrait for UserRepository {
                 }
             }
 
-            return Error::InternalServerError;
+            Error::InternalServerError
         })?;
 
-        sqlx::query(r#" INSERT INTO transactions (user_id, type, amount, last_balance) VALUES ( $1, 'input', 0, 0); "#)
-        .bind(id)
-        .execute(&self.db).await?;
+        sqlx::query(r#" INSERT INTO accounts (user_id) VALUES ($1) "#)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
 
         Ok(id)
     }
+
+    async fn is_admin(&self, id: Uuid) -> Result<bool> {
+        Ok(
+            sqlx::query_scalar::<_, bool>(r#" SELECT is_admin FROM users WHERE id = $1 "#)
+                .bind(id)
+                .fetch_one(&self.db)
+                .await?,
+        )
+    }
 }
@@ -1,25 +1,239 @@
-use crate::http::Result;
+use crate::http::{Error, Result};
+use crate::models::Msat;
 use crate::states::db;
+use crate::{bad_req, internal_error};
+use chrono::{DateTime, Utc};
 use mockall::automock;
-use sqlx::{prelude::FromRow, Pool, Postgres};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgConnection, Pool, Postgres};
 use uuid::Uuid;
 
-pub struct SaveIncoming {
-    pub user_id: Uuid,
-    pub amount: i32,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Account {
+    User(Uuid),
+    Escrow(Uuid),
+    Platform,
+    Lightning,
+}
+
+impl Account {
+    fn debit(self, balance: Msat, amount: Msat) -> Result<Msat> {
+        let new_balance = balance.checked_sub(amount).ok_or(internal_error!())?;
+
+        match self {
+            Self::Lightning => Ok(new_balance),
+            _ if new_balance >= Msat::ZERO => Ok(new_balance),
+            Self::User(_) => bad_req!("You don't have money enough! Deposit more sats."),
+            _ => Err(internal_error!()),
+        }
+    }
+
+    fn credit(self, balance: Msat, amount: Msat) -> Result<Msat> {
+        balance.checked_add(amount).ok_or(internal_error!())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferKind {
+    Deposit,
+    Withdrawal,
+    Stake,
+    Payout,
+    Refund,
+    Fee,
+}
+
+impl TransferKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Deposit => "deposit",
+            Self::Withdrawal => "withdrawal",
+            Self::Stake => "stake",
+            Self::Payout => "payout",
+            Self::Refund => "refund",
+            Self::Fee => "fee",
+        }
+    }
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "deposit" => Ok(Self::Deposit),
+            "withdrawal" => Ok(Self::Withdrawal),
+            "stake" => Ok(Self::Stake),
+            "payout" => Ok(Self::Payout),
+            "refund" => Ok(Self::Refund),
+            "fee" => Ok(Self::Fee),
+            _ => Err(internal_error!()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveTransfer {
+    pub kind: TransferKind,
+    pub from: Account,
+    pub to: Account,
+    pub amount: Msat,
+    pub game_id: Option<Uuid>,
     pub invoice: Option<String>,
 }
 
-pub struct SaveOutgoing {
+impl SaveTransfer {
+    pub fn deposit(user_id: Uuid, amount: Msat, invoice: String) -> Self {
+        Self {
+            kind: TransferKind::Deposit,
+            from: Account::Lightning,
+            to: Account::User(user_id),
+            amount,
+            game_id: None,
+            invoice: Some(invoice),
+        }
+    }
+
+    pub fn withdrawal(user_id: Uuid, amount: Msat, invoice: String) -> Self {
+        Self {
+            kind: TransferKind::Withdrawal,
+            from: Account::User(user_id),
+            to: Account::Lightning,
+            amount,
+            game_id: None,
+            invoice: Some(invoice),
+        }
+    }
+
+    /// Returns a withdrawal that could not be paid or was rejected.
+    pub fn withdrawal_refund(user_id: Uuid, amount: Msat, invoice: String) -> Self {
+        Self {
+            kind: TransferKind::Refund,
+            from: Account::Lightning,
+            to: Account::User(user_id),
+            amount,
+            game_id: None,
+            invoice: Some(invoice),
+        }
+    }
+
+    pub fn stake(user_id: Uuid, game_id: Uuid, amount: Msat) -> Self {
+        Self {
+            kind: TransferKind::Stake,
+            from: Account::User(user_id),
+            to: Account::Escrow(game_id),
+            amount,
+            game_id: Some(game_id),
+            invoice: None,
+        }
+    }
+
+    pub fn payout(game_id: Uuid, user_id: Uuid, amount: Msat) -> Self {
+        Self {
+            kind: TransferKind::Payout,
+            from: Account::Escrow(game_id),
+            to: Account::User(user_id),
+            amount,
+            game_id: Some(game_id),
+            invoice: None,
+        }
+    }
+
+    pub fn fee(game_id: Uuid, user_id: Uuid, amount: Msat) -> Self {
+        Self {
+            kind: TransferKind::Fee,
+            from: Account::User(user_id),
+            to: Account::Platform,
+            amount,
+            game_id: Some(game_id),
+            invoice: None,
+        }
+    }
+
+    /// Winnings of a hold-staked game credited to the winner's balance.
+    pub fn hold_payout(game_id: Uuid, user_id: Uuid, amount: Msat) -> Self {
+        Self {
+            kind: TransferKind::Payout,
+            from: Account::Lightning,
+            to: Account::User(user_id),
+            amount,
+            game_id: Some(game_id),
+            invoice: None,
+        }
+    }
+
+    /// Fee kept by the node from a settled stake hold.
+    pub fn hold_fee(game_id: Uuid, amount: Msat) -> Self {
+        Self {
+            kind: TransferKind::Fee,
+            from: Account::Lightning,
+            to: Account::Platform,
+            amount,
+            game_id: Some(game_id),
+            invoice: None,
+        }
+    }
+
+    pub fn refund(game_id: Uuid, user_id: Uuid, amount: Msat) -> Self {
+        Self {
+            kind: TransferKind::Refund,
+            from: Account::Escrow(game_id),
+            to: Account::User(user_id),
+            amount,
+            game_id: Some(game_id),
+            invoice: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionFilter {
     pub user_id: Uuid,
-    pub amount: i32,
+    pub kind: Option<TransferKind>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub before: Option<(DateTime<Utc>, Uuid)>,
+    pub limit: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Transaction {
+    pub id: Uuid,
+    pub transfer_id: Uuid,
+    #[serde(rename = "type")]
+    pub kind: TransferKind,
+    #[serde(rename = "amount_msat")]
+    pub amount: Msat,
+    #[serde(rename = "balance_msat")]
+    pub balance: Msat,
+    pub game_id: Option<Uuid>,
+    pub invoice: Option<String>,
+    pub created_at: DateTime<Utc>,
 }
 
-struct SaveTransaction {
-    user_id: Uuid,
-    amount: i32,
-    direction: String,
+#[derive(FromRow)]
+struct TransactionRecord {
+    id: Uuid,
+    transfer_id: Uuid,
+    kind: String,
+    amount: Msat,
+    last_balance: Msat,
+    game_id: Option<Uuid>,
     invoice: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl TransactionRecord {
+    fn into_transaction(self) -> Result<Transaction> {
+        Ok(Transaction {
+            id: self.id,
+            transfer_id: self.transfer_id,
+            kind: TransferKind::from_str(&self.kind)?,
+            amount: self.amount,
+            balance: self.last_balance,
+            game_id: self.game_id,
+            invoice: self.invoice,
+            created_at: self.created_at,
+        })
+    }
 }
 
 #[derive(FromRow)]
@@ -28,16 +242,17 @@ struct ReturnedId {
 }
 
 #[derive(FromRow)]
-struct ReturnedInvoice {
-    invoice: Option<String>,
+struct LockedAccount {
+    id: Uuid,
+    balance: Msat,
 }
 
 #[automock]
 pub trait WalletRepositoryTrait {
-    async fn save_incoming(&self, info: SaveIncoming) -> Result<Uuid>;
-    async fn save_outgoing(&self, info: SaveOutgoing) -> Result<Uuid>;
-    async fn get_balance(&self, user_id: Uuid) -> Result<i32>;
+    async fn save_transfer(&self, transfer: SaveTransfer) -> Result<Uuid>;
+    async fn get_balance(&self, user_id: Uuid) -> Result<Msat>;
     async fn get_invoice(&self, user_id: Uuid) -> Result<String>;
+    async fn list_transactions(&self, filter: TransactionFilter) -> Result<Vec<Transaction>>;
 }
 
 pub struct WalletRepository {
@@ -50,86 +265,222 @@ impl WalletRepository {
     }
 }
 
-async fn save_transaction(db: &Pool<Postgres>, info: SaveTransaction) -> sqlx::Result<Uuid> {
-    let signal = match info.direction.as_str() {
-        "input" => "+",
-        _ => "-",
+async fn get_account_id(conn: &mut PgConnection, account: Account) -> Result<Uuid> {
+    let query = match account {
+        Account::User(user_id) => {
+            sqlx::query_as::<_, ReturnedId>(r#" SELECT id FROM accounts WHERE user_id = $1 "#)
+                .bind(user_id)
+        }
+        Account::Escrow(game_id) => {
+            sqlx::query(r#" INSERT INTO accounts (kind, game_id) VALUES ('escrow', $1) ON CONFLICT (game_id) DO NOTHING "#)
+                .bind(game_id)
+                .execute(&mut *conn)
+                .await?;
+
+            sqlx::query_as::<_, ReturnedId>(r#" SELECT id FROM accounts WHERE game_id = $1 "#)
+                .bind(game_id)
+        }
+        Account::Platform => {
+            sqlx::query_as::<_, ReturnedId>(r#" SELECT id FROM accounts WHERE kind = 'platform' "#)
+        }
+        Account::Lightning => {
+            sqlx::query_as::<_, ReturnedId>(r#" SELECT id FROM accounts WHERE kind = 'lightning' "#)
+        }
     };
 
-    let ReturnedId { id } = sqlx::query_as::<_, ReturnedId>(&format!(
-        "
-            WITH last_transaction AS (
-                SELECT last_balance AS last_balance
-                FROM transactions
-                WHERE user_id = $1
-                ORDER BY created_at DESC
-                LIMIT 1
-            )
-            INSERT INTO transactions (user_id, type, amount, last_balance, invoice)
-            VALUES ( $1, $2, $3, (SELECT last_balance FROM last_transaction) {signal} $3, $4) 
-            RETURNING id;
-        ",
-    ))
-    .bind(info.user_id)
-    .bind(info.direction)
-    .bind(info.amount)
-    .bind(info.invoice.unwrap_or_default())
-    .fetch_one(db)
+    Ok(query.fetch_one(&mut *conn).await?.id)
+}
+
+pub(super) async fn post_transfer(conn: &mut PgConnection, transfer: SaveTransfer) -> Result<Uuid> {
+    if transfer.amount < Msat::ZERO || transfer.from == transfer.to {
+        return Err(internal_error!());
+    }
+
+    let from_id = get_account_id(conn, transfer.from).await?;
+    let to_id = get_account_id(conn, transfer.to).await?;
+
+    // Accounts are always locked in id order so concurrent transfers between
+    // the same pair of accounts cannot deadlock.
+    let locked = sqlx::query_as::<_, LockedAccount>(
+        r#" SELECT id, balance FROM accounts WHERE id = ANY($1) ORDER BY id FOR UPDATE "#,
+    )
+    .bind(vec![from_id, to_id])
+    .fetch_all(&mut *conn)
     .await?;
 
+    let balance_of = |id: Uuid| {
+        locked
+            .iter()
+            .find(|account| account.id == id)
+            .map(|account| account.balance)
+            .ok_or(internal_error!())
+    };
+
+    let from_balance = transfer.from.debit(balance_of(from_id)?, transfer.amount)?;
+    let to_balance = transfer.to.credit(balance_of(to_id)?, transfer.amount)?;
+
+    let ReturnedId { id } = sqlx::query_as::<_, ReturnedId>(
+        r#" INSERT INTO transfers (type, game_id, invoice, amount) VALUES ($1, $2, $3, $4) RETURNING id "#,
+    )
+    .bind(transfer.kind.as_str())
+    .bind(transfer.game_id)
+    .bind(transfer.invoice)
+    .bind(transfer.amount)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let debited = transfer.amount.checked_neg().ok_or(internal_error!())?;
+
+    for (account_id, amount, last_balance) in [
+        (from_id, debited, from_balance),
+        (to_id, transfer.amount, to_balance),
+    ] {
+        sqlx::query(r#" UPDATE accounts SET balance = $1 WHERE id = $2 "#)
+            .bind(last_balance)
+            .bind(account_id)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            r#" INSERT INTO entries (transfer_id, account_id, amount, last_balance) VALUES ($1, $2, $3, $4) "#,
+        )
+        .bind(id)
+        .bind(account_id)
+        .bind(amount)
+        .bind(last_balance)
+        .execute(&mut *conn)
+        .await?;
+    }
+
     Ok(id)
 }
 
 impl WalletRepositoryTrait for WalletRepository {
-    async fn save_incoming(&self, info: SaveIncoming) -> Result<Uuid> {
-        let SaveIncoming {
-            user_id,
-            amount,
-            invoice,
-        } = info;
-
-        Ok(save_transaction(
-            &self.db,
-            SaveTransaction {
-                user_id,
-                direction: String::from("input"),
-                amount,
-                invoice,
-            },
-        )
-        .await?)
-    }
+    async fn save_transfer(&self, transfer: SaveTransfer) -> Result<Uuid> {
+        let mut tx = self.db.begin().await?;
 
-    async fn save_outgoing(&self, info: SaveOutgoing) -> Result<Uuid> {
-        let SaveOutgoing { user_id, amount } = info;
+        let id = post_transfer(&mut tx, transfer).await?;
 
-        Ok(save_transaction(
-            &self.db,
-            SaveTransaction {
-                user_id,
-                direction: String::from("output"),
-                amount,
-                invoice: None,
-            },
-        )
-        .await?)
+        tx.commit().await?;
+
+        Ok(id)
     }
 
-    async fn get_balance(&self, user_id: Uuid) -> Result<i32> {
-        Ok(sqlx::query_scalar(
-            r#" SELECT last_balance FROM transactions WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1 "#
+    async fn get_balance(&self, user_id: Uuid) -> Result<Msat> {
+        Ok(
+            sqlx::query_scalar(r#" SELECT balance FROM accounts WHERE user_id = $1 "#)
+                .bind(user_id)
+                .fetch_one(&self.db)
+                .await?,
         )
-        .bind(user_id)
-        .fetch_one(&self.db)
-        .await?)
     }
 
     async fn get_invoice(&self, user_id: Uuid) -> Result<String> {
-        Ok(sqlx::query_as::<_, ReturnedInvoice>(
-            r#" SELECT invoice FROM transactions WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1 "#
+        let invoice: Option<Option<String>> = sqlx::query_scalar(
+            r#"
+                SELECT transfers.invoice
+                FROM entries
+                JOIN transfers ON transfers.id = entries.transfer_id
+                JOIN accounts ON accounts.id = entries.account_id
+                WHERE accounts.user_id = $1
+                ORDER BY entries.created_at DESC
+                LIMIT 1
+            "#,
         )
         .bind(user_id)
-        .fetch_one(&self.db)
-        .await?.invoice.unwrap_or_default())
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(invoice.flatten().unwrap_or_default())
+    }
+
+    async fn list_transactions(&self, filter: TransactionFilter) -> Result<Vec<Transaction>> {
+        let (before_created_at, before_id) = filter.before.unzip();
+
+        sqlx::query_as::<_, TransactionRecord>(
+            r#"
+                SELECT entries.id, entries.transfer_id, transfers.type AS kind, entries.amount,
+                       entries.last_balance, transfers.game_id, transfers.invoice, entries.created_at
+                FROM entries
+                JOIN transfers ON transfers.id = entries.transfer_id
+                JOIN accounts ON accounts.id = entries.account_id
+                WHERE accounts.user_id = $1
+                  AND ($2::text IS NULL OR transfers.type = $2)
+                  AND ($3::timestamptz IS NULL OR entries.created_at >= $3)
+                  AND ($4::timestamptz IS NULL OR entries.created_at < $4)
+                  AND ($5::timestamptz IS NULL OR (entries.created_at, entries.id) < ($5, $6))
+                ORDER BY entries.created_at DESC, entries.id DESC
+                LIMIT $7
+            "#,
+        )
+        .bind(filter.user_id)
+        .bind(filter.kind.map(TransferKind::as_str))
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(before_created_at)
+        .bind(before_id)
+        .bind(filter.limit)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(TransactionRecord::into_transaction)
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credit() {
+        assert_eq!(
+            Account::User(Uuid::new_v4())
+                .credit(Msat::new(100), Msat::new(50))
+                .ok(),
+            Some(Msat::new(150))
+        );
+    }
+
+    #[test]
+    fn test_debit_user() {
+        assert_eq!(
+            Account::User(Uuid::new_v4())
+                .debit(Msat::new(100), Msat::new(100))
+                .ok(),
+            Some(Msat::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_debit_user_insufficient_balance() {
+        assert!(Account::User(Uuid::new_v4())
+            .debit(Msat::new(100), Msat::new(101))
+            .is_err());
+    }
+
+    #[test]
+    fn test_debit_escrow_insufficient_balance() {
+        assert!(Account::Escrow(Uuid::new_v4())
+            .debit(Msat::ZERO, Msat::new(1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_debit_lightning_goes_negative() {
+        assert_eq!(
+            Account::Lightning.debit(Msat::ZERO, Msat::new(100)).ok(),
+            Some(Msat::new(-100))
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert!(Account::Platform
+            .credit(Msat::new(i64::MAX), Msat::new(1))
+            .is_err());
+        assert!(Account::Lightning
+            .debit(Msat::new(i64::MIN), Msat::new(1))
+            .is_err());
     }
 }
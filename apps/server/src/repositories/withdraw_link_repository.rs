@@ -0,0 +1,97 @@
+use crate::http::Result;
+use crate::models::Msat;
+use crate::states::db;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use sqlx::{prelude::FromRow, Pool, Postgres};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveWithdrawLink {
+    pub user_id: Uuid,
+    pub k1: String,
+    pub amount: Msat,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct WithdrawLink {
+    pub user_id: Uuid,
+    pub k1: String,
+    pub amount: Msat,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl WithdrawLink {
+    pub fn is_usable(&self) -> bool {
+        self.used_at.is_none() && self.expires_at > Utc::now()
+    }
+}
+
+#[automock]
+pub trait WithdrawLinkRepositoryTrait {
+    async fn save_withdraw_link(&self, link: SaveWithdrawLink) -> Result<()>;
+    async fn get_withdraw_link(&self, k1: String) -> Result<WithdrawLink>;
+    async fn claim_withdraw_link(&self, k1: String) -> Result<Option<WithdrawLink>>;
+    async fn release_withdraw_link(&self, k1: String) -> Result<()>;
+}
+
+pub struct WithdrawLinkRepository {
+    db: Pool<Postgres>,
+}
+
+impl WithdrawLinkRepository {
+    pub fn new() -> Self {
+        Self { db: db::get() }
+    }
+}
+
+impl WithdrawLinkRepositoryTrait for WithdrawLinkRepository {
+    async fn save_withdraw_link(&self, link: SaveWithdrawLink) -> Result<()> {
+        sqlx::query(
+            r#" INSERT INTO withdraw_links (user_id, k1, amount, expires_at) VALUES ($1, $2, $3, $4) "#,
+        )
+        .bind(link.user_id)
+        .bind(link.k1)
+        .bind(link.amount)
+        .bind(link.expires_at)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_withdraw_link(&self, k1: String) -> Result<WithdrawLink> {
+        Ok(sqlx::query_as::<_, WithdrawLink>(
+            r#" SELECT user_id, k1, amount, expires_at, used_at FROM withdraw_links WHERE k1 = $1 "#,
+        )
+        .bind(k1)
+        .fetch_one(&self.db)
+        .await?)
+    }
+
+    /// Marks the link as used. Returns None if it was already used or has
+    /// expired, so concurrent callbacks cannot both withdraw.
+    async fn claim_withdraw_link(&self, k1: String) -> Result<Option<WithdrawLink>> {
+        Ok(sqlx::query_as::<_, WithdrawLink>(
+            r#"
+                UPDATE withdraw_links SET used_at = now()
+                WHERE k1 = $1 AND used_at IS NULL AND expires_at > now()
+                RETURNING user_id, k1, amount, expires_at, used_at
+            "#,
+        )
+        .bind(k1)
+        .fetch_optional(&self.db)
+        .await?)
+    }
+
+    async fn release_withdraw_link(&self, k1: String) -> Result<()> {
+        sqlx::query(r#" UPDATE withdraw_links SET used_at = NULL WHERE k1 = $1 "#)
+            .bind(k1)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::http::Result;
+use crate::models::{Msat, WithdrawalActivity, WithdrawalLimits};
+use crate::states::db;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, PgConnection, Pool, Postgres};
+use uuid::Uuid;
+
+use super::wallet_repository::{post_transfer, SaveTransfer};
+
+/// A withdrawal above the review threshold, already debited from the user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, FromRow)]
+pub struct WithdrawalReview {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    #[serde(rename = "amount_msat")]
+    pub amount: Msat,
+    pub invoice: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow)]
+struct WithdrawnRecord {
+    withdrawn_last_day: Msat,
+    withdrawn_last_week: Msat,
+}
+
+async fn get_activity(conn: &mut PgConnection, user_id: Uuid) -> Result<WithdrawalActivity> {
+    // Refunds carrying an invoice give back failed or rejected
+    // withdrawals, so they don't count towards the limits.
+    let WithdrawnRecord {
+        withdrawn_last_day,
+        withdrawn_last_week,
+    } = sqlx::query_as::<_, WithdrawnRecord>(
+        r#"
+            SELECT GREATEST(COALESCE(SUM(-entries.amount) FILTER (WHERE entries.created_at >= now() - interval '1 day'), 0), 0)::bigint AS withdrawn_last_day,
+                   GREATEST(COALESCE(SUM(-entries.amount), 0), 0)::bigint AS withdrawn_last_week
+            FROM entries
+            JOIN transfers ON transfers.id = entries.transfer_id
+            JOIN accounts ON accounts.id = entries.account_id
+            WHERE accounts.user_id = $1
+              AND entries.created_at >= now() - interval '7 days'
+              AND (transfers.type = 'withdrawal' OR (transfers.type = 'refund' AND transfers.invoice IS NOT NULL))
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let last_deposit_at = sqlx::query_scalar(
+        r#"
+            SELECT max(entries.created_at)
+            FROM entries
+            JOIN transfers ON transfers.id = entries.transfer_id
+            JOIN accounts ON accounts.id = entries.account_id
+            WHERE accounts.user_id = $1 AND transfers.type = 'deposit'
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(WithdrawalActivity {
+        withdrawn_last_day,
+        withdrawn_last_week,
+        last_deposit_at,
+    })
+}
+
+#[automock]
+pub trait WithdrawalRepositoryTrait {
+    /// Checks `limits` against the user's recent withdrawals and debits the
+    /// withdrawal in one transaction, queueing it for review if it needs one.
+    /// Returns the id of the review.
+    async fn debit_withdrawal(
+        &self,
+        user_id: Uuid,
+        amount: Msat,
+        invoice: String,
+        limits: WithdrawalLimits,
+    ) -> Result<Option<Uuid>>;
+    async fn list_pending_reviews(&self) -> Result<Vec<WithdrawalReview>>;
+    /// Marks a pending review approved. Returns `None` if it was already
+    /// reviewed.
+    async fn approve_review(&self, id: Uuid, admin_id: Uuid) -> Result<Option<WithdrawalReview>>;
+    /// Marks a pending review rejected and refunds the user.
+    async fn reject_review(&self, id: Uuid, admin_id: Uuid) -> Result<Option<WithdrawalReview>>;
+}
+
+pub struct WithdrawalRepository {
+    db: Pool<Postgres>,
+}
+
+impl WithdrawalRepository {
+    pub fn new() -> Self {
+        Self { db: db::get() }
+    }
+}
+
+impl WithdrawalRepositoryTrait for WithdrawalRepository {
+    async fn debit_withdrawal(
+        &self,
+        user_id: Uuid,
+        amount: Msat,
+        invoice: String,
+        limits: WithdrawalLimits,
+    ) -> Result<Option<Uuid>> {
+        let mut tx = self.db.begin().await?;
+
+        // Concurrent withdrawals of the user wait here, so each one is checked
+        // against the withdrawals debited before it.
+        sqlx::query(r#" SELECT id FROM users WHERE id = $1 FOR NO KEY UPDATE "#)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let activity = get_activity(&mut tx, user_id).await?;
+
+        limits.check(amount, &activity, Utc::now())?;
+
+        let transfer_id = post_transfer(
+            &mut tx,
+            SaveTransfer::withdrawal(user_id, amount, invoice.clone()),
+        )
+        .await?;
+
+        let review_id = match limits.needs_review(amount) {
+            true => Some(
+                sqlx::query_scalar(
+                    r#" INSERT INTO withdrawal_reviews (user_id, transfer_id, amount, invoice) VALUES ($1, $2, $3, $4) RETURNING id "#,
+                )
+                .bind(user_id)
+                .bind(transfer_id)
+                .bind(amount)
+                .bind(invoice)
+                .fetch_one(&mut *tx)
+                .await?,
+            ),
+            false => None,
+        };
+
+        tx.commit().await?;
+
+        Ok(review_id)
+    }
+
+    async fn list_pending_reviews(&self) -> Result<Vec<WithdrawalReview>> {
+        Ok(sqlx::query_as::<_, WithdrawalReview>(
+            r#"
+                SELECT id, user_id, amount, invoice, created_at
+                FROM withdrawal_reviews
+                WHERE status = 'pending'
+                ORDER BY created_at
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?)
+    }
+
+    async fn approve_review(&self, id: Uuid, admin_id: Uuid) -> Result<Option<WithdrawalReview>> {
+        Ok(sqlx::query_as::<_, WithdrawalReview>(
+            r#"
+                UPDATE withdrawal_reviews SET status = 'approved', reviewed_by = $2, reviewed_at = now()
+                WHERE id = $1 AND status = 'pending'
+                RETURNING id, user_id, amount, invoice, created_at
+            "#,
+        )
+        .bind(id)
+        .bind(admin_id)
+        .fetch_optional(&self.db)
+        .await?)
+    }
+
+    async fn reject_review(&self, id: Uuid, admin_id: Uuid) -> Result<Option<WithdrawalReview>> {
+        let mut tx = self.db.begin().await?;
+
+        let review = sqlx::query_as::<_, WithdrawalReview>(
+            r#"
+                UPDATE withdrawal_reviews SET status = 'rejected', reviewed_by = $2, reviewed_at = now()
+                WHERE id = $1 AND status = 'pending'
+                RETURNING id, user_id, amount, invoice, created_at
+            "#,
+        )
+        .bind(id)
+        .bind(admin_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(review) = &review {
+            post_transfer(
+                &mut tx,
+                SaveTransfer::withdrawal_refund(
+                    review.user_id,
+                    review.amount,
+                    review.invoice.clone(),
+                ),
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(review)
+    }
+}
@@ -0,0 +1,67 @@
+use crate::http::{Error, Result};
+use crate::repositories::UserRepositoryTrait;
+use aide::axum::{
+    routing::{get_with, post_with},
+    ApiRouter,
+};
+use uuid::Uuid;
+
+mod reconciliation;
+mod withdrawal_reviews;
+
+pub fn router() -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/admin/reconciliation",
+            post_with(reconciliation::route, reconciliation::docs),
+        )
+        .api_route(
+            "/admin/withdrawals",
+            get_with(withdrawal_reviews::route, withdrawal_reviews::docs),
+        )
+        .api_route(
+            "/admin/withdrawals/:id/approve",
+            post_with(
+                withdrawal_reviews::approve::route,
+                withdrawal_reviews::approve::docs,
+            ),
+        )
+        .api_route(
+            "/admin/withdrawals/:id/reject",
+            post_with(
+                withdrawal_reviews::reject::route,
+                withdrawal_reviews::reject::docs,
+            ),
+        )
+}
+
+/// Rejects users without the admin flag.
+async fn ensure_admin(user_repository: &impl UserRepositoryTrait, user_id: Uuid) -> Result<()> {
+    if !user_repository.is_admin(user_id).await? {
+        return Err(Error::Forbidden);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::MockUserRepositoryTrait;
+
+    #[tokio::test]
+    async fn test_ensure_admin() {
+        let mut mock_user_repository = MockUserRepositoryTrait::new();
+        let admin_id = Uuid::new_v4();
+
+        mock_user_repository
+            .expect_is_admin()
+            .returning(move |id| Ok(id == admin_id));
+
+        assert!(ensure_admin(&mock_user_repository, admin_id).await.is_ok());
+        assert!(matches!(
+            ensure_admin(&mock_user_repository, Uuid::new_v4()).await,
+            Err(Error::Forbidden)
+        ));
+    }
+}
@@ -0,0 +1,26 @@
+use crate::http::{GenericError, Result};
+use crate::jobs::ReconciliationJob;
+use crate::models::{AuthUser, ReconciliationReport};
+use crate::repositories::{ReconciliationRepository, UserRepository};
+use aide::transform::TransformOperation;
+use axum::Json;
+use reqwest::Client;
+
+use super::ensure_admin;
+
+fn resource() -> ReconciliationJob<ReconciliationRepository, Client> {
+    ReconciliationJob::new(ReconciliationRepository::new(), Client::new())
+}
+
+pub async fn route(auth_user: AuthUser) -> Result<Json<ReconciliationReport>> {
+    ensure_admin(&UserRepository::new(), auth_user.user_id).await?;
+
+    Ok(Json(resource().run_once().await?))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Admin")
+        .description("Compare the ledger with the Lightning node and report discrepancies")
+        .response::<200, Json<ReconciliationReport>>()
+        .response::<403, Json<GenericError>>()
+}
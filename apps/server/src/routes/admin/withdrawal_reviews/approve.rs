@@ -0,0 +1,22 @@
+use crate::http::{GenericError, Result};
+use crate::models::AuthUser;
+use crate::repositories::UserRepository;
+use aide::transform::TransformOperation;
+use axum::{extract::Path, Json};
+
+use super::super::ensure_admin;
+use super::{resource, ReviewId};
+
+pub async fn route(auth_user: AuthUser, Path(ReviewId { id }): Path<ReviewId>) -> Result<()> {
+    ensure_admin(&UserRepository::new(), auth_user.user_id).await?;
+
+    resource().approve(id, auth_user.user_id).await
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Admin")
+        .description("Approve a held withdrawal and send its payment")
+        .response::<200, ()>()
+        .response::<403, Json<GenericError>>()
+        .response::<404, Json<GenericError>>()
+}
@@ -0,0 +1,48 @@
+use crate::http::{GenericError, Result};
+use crate::models::AuthUser;
+use crate::repositories::{
+    UserRepository, WalletRepository, WithdrawalRepository, WithdrawalReview,
+};
+use aide::transform::TransformOperation;
+use axum::Json;
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::WithdrawalReviewService;
+use uuid::Uuid;
+
+use super::super::wallet::withdraw;
+use super::ensure_admin;
+
+pub mod approve;
+pub mod reject;
+mod service;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ReviewId {
+    id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WithdrawalReviewsBody {
+    reviews: Vec<WithdrawalReview>,
+}
+
+fn resource() -> WithdrawalReviewService<WithdrawalRepository, WalletRepository, Client> {
+    WithdrawalReviewService::new(WithdrawalRepository::new(), withdraw::resource())
+}
+
+pub async fn route(auth_user: AuthUser) -> Result<Json<WithdrawalReviewsBody>> {
+    ensure_admin(&UserRepository::new(), auth_user.user_id).await?;
+
+    let reviews = resource().list().await?;
+
+    Ok(Json(WithdrawalReviewsBody { reviews }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Admin")
+        .description("List withdrawals waiting for review, oldest first")
+        .response::<200, Json<WithdrawalReviewsBody>>()
+        .response::<403, Json<GenericError>>()
+}
@@ -0,0 +1,208 @@
+use crate::http::{Error, LightningClient, Result};
+use crate::repositories::{WalletRepositoryTrait, WithdrawalRepositoryTrait, WithdrawalReview};
+use uuid::Uuid;
+
+use super::super::super::wallet::withdraw::service::WithdrawService;
+
+pub struct WithdrawalReviewService<
+    W: WithdrawalRepositoryTrait,
+    R: WalletRepositoryTrait,
+    L: LightningClient,
+> {
+    withdrawal_repository: W,
+    withdraw_service: WithdrawService<R, L, W>,
+}
+
+fn not_found() -> Error {
+    Error::NotFound {
+        message: String::from("No pending withdrawal review with this id"),
+    }
+}
+
+impl<W: WithdrawalRepositoryTrait, R: WalletRepositoryTrait, L: LightningClient>
+    WithdrawalReviewService<W, R, L>
+{
+    pub fn new(withdrawal_repository: W, withdraw_service: WithdrawService<R, L, W>) -> Self {
+        Self {
+            withdrawal_repository,
+            withdraw_service,
+        }
+    }
+
+    pub async fn list(&self) -> Result<Vec<WithdrawalReview>> {
+        self.withdrawal_repository.list_pending_reviews().await
+    }
+
+    /// Sends the payment of a held withdrawal. It is refunded if the node
+    /// confirms the payment failed, e.g. because the invoice expired while
+    /// waiting for review.
+    pub async fn approve(&self, id: Uuid, admin_id: Uuid) -> Result<()> {
+        let review = self
+            .withdrawal_repository
+            .approve_review(id, admin_id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        self.withdraw_service
+            .pay(review.user_id, review.amount, review.invoice)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn reject(&self, id: Uuid, admin_id: Uuid) -> Result<()> {
+        self.withdrawal_repository
+            .reject_review(id, admin_id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{MockLightningClient, PaymentStatus};
+    use crate::models::{Msat, WithdrawalLimits};
+    use crate::repositories::{
+        MockWalletRepositoryTrait, MockWithdrawalRepositoryTrait, TransferKind,
+    };
+    use chrono::Utc;
+
+    // BOLT11 specification test vector for 250000 sats.
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+    fn review() -> WithdrawalReview {
+        WithdrawalReview {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            amount: Msat::new(5_000_000),
+            invoice: String::from(INVOICE),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn service(
+        mock_withdrawal_repository: MockWithdrawalRepositoryTrait,
+        mock_wallet_repository: MockWalletRepositoryTrait,
+        mock_lightning_client: MockLightningClient,
+    ) -> WithdrawalReviewService<
+        MockWithdrawalRepositoryTrait,
+        MockWalletRepositoryTrait,
+        MockLightningClient,
+    > {
+        WithdrawalReviewService::new(
+            mock_withdrawal_repository,
+            WithdrawService::new(
+                mock_wallet_repository,
+                mock_lightning_client,
+                MockWithdrawalRepositoryTrait::new(),
+                WithdrawalLimits::default(),
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_approve_pays_invoice() {
+        let mut mock_withdrawal_repository = MockWithdrawalRepositoryTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_withdrawal_repository
+            .expect_approve_review()
+            .once()
+            .returning(|_, _| Ok(Some(review())));
+
+        mock_lightning_client
+            .expect_pay_invoice()
+            .once()
+            .withf(|invoice| invoice == INVOICE)
+            .returning(|_| Ok(()));
+
+        mock_wallet_repository.expect_save_transfer().never();
+
+        let service = service(
+            mock_withdrawal_repository,
+            mock_wallet_repository,
+            mock_lightning_client,
+        );
+
+        assert!(service
+            .approve(Uuid::new_v4(), Uuid::new_v4())
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_approve_refunds_failed_payment() {
+        let mut mock_withdrawal_repository = MockWithdrawalRepositoryTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_withdrawal_repository
+            .expect_approve_review()
+            .once()
+            .returning(|_, _| Ok(Some(review())));
+
+        mock_lightning_client
+            .expect_pay_invoice()
+            .once()
+            .returning(|_| Err(Error::InternalServerError));
+
+        mock_lightning_client
+            .expect_get_invoice()
+            .once()
+            .returning(|_| Ok(PaymentStatus::Failed));
+
+        mock_wallet_repository
+            .expect_save_transfer()
+            .once()
+            .withf(|transfer| {
+                transfer.kind == TransferKind::Refund && transfer.amount == Msat::new(5_000_000)
+            })
+            .returning(|_| Ok(Uuid::new_v4()));
+
+        let service = service(
+            mock_withdrawal_repository,
+            mock_wallet_repository,
+            mock_lightning_client,
+        );
+
+        assert!(service
+            .approve(Uuid::new_v4(), Uuid::new_v4())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_review_already_handled() {
+        let mut mock_withdrawal_repository = MockWithdrawalRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_withdrawal_repository
+            .expect_approve_review()
+            .returning(|_, _| Ok(None));
+
+        mock_withdrawal_repository
+            .expect_reject_review()
+            .returning(|_, _| Ok(None));
+
+        mock_lightning_client.expect_pay_invoice().never();
+
+        let service = service(
+            mock_withdrawal_repository,
+            MockWalletRepositoryTrait::new(),
+            mock_lightning_client,
+        );
+
+        assert!(matches!(
+            service.approve(Uuid::new_v4(), Uuid::new_v4()).await,
+            Err(Error::NotFound { .. })
+        ));
+        assert!(matches!(
+            service.reject(Uuid::new_v4(), Uuid::new_v4()).await,
+            Err(Error::NotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,25 @@
+use crate::http::{GenericError, Result};
+use crate::models::AuthUser;
+use aide::transform::TransformOperation;
+use axum::{extract::Path, Json};
+
+use super::super::game::pairing_game::GameId;
+use super::{resource, ChallengeId};
+
+pub async fn route(
+    auth_user: AuthUser,
+    Path(ChallengeId { id }): Path<ChallengeId>,
+) -> Result<Json<GameId>> {
+    let game_id = resource().accept(id, auth_user.user_id).await?;
+
+    Ok(Json(GameId { game_id }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Challenges")
+        .description("Accept a challenge, taking both stakes and creating its game")
+        .response::<200, Json<GameId>>()
+        .response::<400, Json<GenericError>>()
+        .response::<404, Json<GenericError>>()
+        .response::<409, Json<GenericError>>()
+}
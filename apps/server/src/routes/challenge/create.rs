@@ -0,0 +1,28 @@
+use crate::http::{Error, GenericError, Result};
+use crate::models::{AuthUser, GameRequestSpec};
+use crate::repositories::Challenge;
+use aide::transform::TransformOperation;
+use axum::Json;
+
+use super::resource;
+
+pub async fn route(
+    auth_user: AuthUser,
+    Json(payload): Json<GameRequestSpec>,
+) -> Result<Json<Challenge>> {
+    if let Some(message) = payload.validation_message() {
+        return Err(Error::BadRequest { message });
+    }
+
+    let challenge = resource().create(auth_user.user_id, &payload).await?;
+
+    Ok(Json(challenge))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Challenges")
+        .description("Challenge the user named as the opponent of a game request")
+        .response::<200, Json<Challenge>>()
+        .response::<400, Json<GenericError>>()
+        .response::<409, Json<GenericError>>()
+}
@@ -0,0 +1,17 @@
+use crate::http::{GenericError, Result};
+use crate::models::AuthUser;
+use aide::transform::TransformOperation;
+use axum::{extract::Path, Json};
+
+use super::{resource, ChallengeId};
+
+pub async fn route(auth_user: AuthUser, Path(ChallengeId { id }): Path<ChallengeId>) -> Result<()> {
+    resource().decline(id, auth_user.user_id).await
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Challenges")
+        .description("Decline a challenge sent to the user")
+        .response::<200, ()>()
+        .response::<404, Json<GenericError>>()
+}
@@ -0,0 +1,82 @@
+use crate::http::{GenericError, Result};
+use crate::models::{AuthUser, Lobby, RoomsManager};
+use crate::repositories::{
+    Challenge, ChallengeRepository, GamblingLimitRepository, UserRepository, WalletRepository,
+};
+use crate::states::{challenge_ttl, max_concurrent_games};
+use aide::axum::{
+    routing::{delete_with, post_with},
+    ApiRouter,
+};
+use aide::transform::TransformOperation;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::ChallengeService;
+use uuid::Uuid;
+
+mod accept;
+mod cancel;
+mod create;
+mod decline;
+mod service;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ChallengeId {
+    id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ChallengesBody {
+    challenges: Vec<Challenge>,
+}
+
+fn resource() -> ChallengeService<
+    ChallengeRepository,
+    UserRepository,
+    WalletRepository,
+    GamblingLimitRepository,
+    RoomsManager,
+    Lobby,
+> {
+    ChallengeService::new(
+        ChallengeRepository::new(),
+        UserRepository::new(),
+        WalletRepository::new(),
+        GamblingLimitRepository::new(),
+        RoomsManager::new(),
+        Lobby::new(),
+        max_concurrent_games::get(),
+        challenge_ttl::get(),
+    )
+}
+
+pub fn router() -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/challenge",
+            post_with(create::route, create::docs).get_with(route, docs),
+        )
+        .api_route("/challenge/:id", delete_with(cancel::route, cancel::docs))
+        .api_route(
+            "/challenge/:id/accept",
+            post_with(accept::route, accept::docs),
+        )
+        .api_route(
+            "/challenge/:id/decline",
+            post_with(decline::route, decline::docs),
+        )
+}
+
+pub async fn route(auth_user: AuthUser) -> Result<Json<ChallengesBody>> {
+    let challenges = resource().list(auth_user.user_id).await?;
+
+    Ok(Json(ChallengesBody { challenges }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Challenges")
+        .description("List the pending challenges sent or received by the user")
+        .response::<200, Json<ChallengesBody>>()
+        .response::<401, Json<GenericError>>()
+}
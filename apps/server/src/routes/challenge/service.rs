@@ -0,0 +1,486 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use crate::models::{
+    ChallengeInfo, Event, Game, GameRequest, GameRequestSpec, LobbyTrait, Msat, PlayerColor, Room,
+    RoomsManagerTrait, StakeMode,
+};
+use crate::repositories::{
+    Challenge, ChallengeRepositoryTrait, ChallengeStatus, GamblingLimitRepositoryTrait,
+    SaveTransfer, UserRepositoryTrait, WalletRepositoryTrait,
+};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+pub struct ChallengeService<
+    C: ChallengeRepositoryTrait,
+    U: UserRepositoryTrait,
+    W: WalletRepositoryTrait,
+    G: GamblingLimitRepositoryTrait,
+    M: RoomsManagerTrait,
+    B: LobbyTrait,
+> {
+    challenge_repository: C,
+    user_repository: U,
+    wallet_repository: W,
+    gambling_limit_repository: G,
+    rooms_manager: M,
+    lobby: B,
+    max_concurrent_games: usize,
+    ttl: Duration,
+}
+
+fn not_found() -> Error {
+    Error::NotFound {
+        message: String::from("Challenge not found!"),
+    }
+}
+
+impl<
+        C: ChallengeRepositoryTrait,
+        U: UserRepositoryTrait,
+        W: WalletRepositoryTrait,
+        G: GamblingLimitRepositoryTrait,
+        M: RoomsManagerTrait,
+        B: LobbyTrait,
+    > ChallengeService<C, U, W, G, M, B>
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        challenge_repository: C,
+        user_repository: U,
+        wallet_repository: W,
+        gambling_limit_repository: G,
+        rooms_manager: M,
+        lobby: B,
+        max_concurrent_games: usize,
+        ttl: Duration,
+    ) -> Self {
+        Self {
+            challenge_repository,
+            user_repository,
+            wallet_repository,
+            gambling_limit_repository,
+            rooms_manager,
+            lobby,
+            max_concurrent_games,
+            ttl,
+        }
+    }
+
+    /// Checks that the player can still afford the stake and start another
+    /// game. `insufficient` is the message if their balance is too low.
+    async fn check_player(&self, player_id: Uuid, stake: Msat, insufficient: &str) -> Result<()> {
+        self.gambling_limit_repository
+            .get_protection(player_id)
+            .await?
+            .check_stake(stake, Utc::now())?;
+
+        if self.wallet_repository.get_balance(player_id).await? < stake {
+            return Err(Error::BadRequest {
+                message: insufficient.to_string(),
+            });
+        }
+
+        if self.rooms_manager.count_games(player_id) >= self.max_concurrent_games {
+            return Err(Error::Conflict {
+                message: String::from("A player is already playing as many games as allowed!"),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Offers the game of `spec` to the user it names as the opponent.
+    /// Nothing is staked until the challenge is accepted.
+    pub async fn create(&self, challenger_id: Uuid, spec: &GameRequestSpec) -> Result<Challenge> {
+        let Some(opponent) = spec.opponent.clone() else {
+            return bad_req!("Name the user to challenge");
+        };
+
+        let challenged_id = match self.user_repository.find_by_username(opponent).await {
+            Err(Error::NotFound { .. }) => return bad_req!("Opponent not found"),
+            result => result?.id,
+        };
+
+        if challenged_id == challenger_id {
+            return bad_req!("You cannot challenge yourself");
+        }
+
+        let game_request = GameRequest::from_spec(spec, Some(challenged_id))?;
+
+        if game_request.stake_mode == StakeMode::Hold {
+            return bad_req!("Challenges only take stakes from the players' balances");
+        }
+
+        self.check_player(
+            challenger_id,
+            game_request.bet_value.to_msat()?,
+            "You don't have money enough! Deposit more sats.",
+        )
+        .await?;
+
+        let id = self
+            .challenge_repository
+            .save_challenge(
+                challenger_id,
+                challenged_id,
+                game_request.key,
+                Utc::now() + self.ttl,
+            )
+            .await?
+            .ok_or(Error::Conflict {
+                message: String::from("You already challenged this player!"),
+            })?;
+
+        self.lobby.notify(
+            challenged_id,
+            Event::ChallengeReceived(ChallengeInfo {
+                challenge_id: id,
+                game_id: None,
+            }),
+        );
+
+        self.challenge_repository.get_challenge(id).await
+    }
+
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Challenge>> {
+        self.challenge_repository
+            .list_pending_challenges(user_id)
+            .await
+    }
+
+    /// Creates the game of a challenge sent to `user_id`, with both seats
+    /// taken and both stakes debited. Returns the game id.
+    pub async fn accept(&self, id: Uuid, user_id: Uuid) -> Result<Uuid> {
+        let challenge = self.challenge_repository.get_challenge(id).await?;
+
+        if challenge.challenged_id != user_id || challenge.status != ChallengeStatus::Pending {
+            return Err(not_found());
+        }
+
+        if challenge.expires_at <= Utc::now() {
+            return bad_req!("Challenge has expired");
+        }
+
+        let game_request = GameRequest::from_str(&challenge.request_key)?;
+        let stake = game_request.bet_value.to_msat()?;
+
+        self.check_player(
+            user_id,
+            stake,
+            "You don't have money enough! Deposit more sats.",
+        )
+        .await?;
+        self.check_player(
+            challenge.challenger_id,
+            stake,
+            "The challenger no longer has money enough for this game",
+        )
+        .await?;
+
+        let game_id = Uuid::new_v4();
+        let mut room = Room::new(challenge.request_key);
+
+        room.add_player(
+            challenge.challenger_id,
+            Some(
+                game_request
+                    .player_color
+                    .unwrap_or_else(PlayerColor::random),
+            ),
+        )?;
+        room.add_player(user_id, None)?;
+
+        let game = Game {
+            id: game_id,
+            white_player: room.white_player.ok_or(Error::InternalServerError)?,
+            black_player: room.black_player.ok_or(Error::InternalServerError)?,
+            bet_value: game_request.bet_value,
+            stake_mode: game_request.stake_mode,
+            category: game_request.rated.then(|| game_request.category()),
+            ..Default::default()
+        };
+
+        let transfers = vec![
+            SaveTransfer::stake(challenge.challenger_id, game_id, stake),
+            SaveTransfer::stake(user_id, game_id, stake),
+        ];
+
+        if !self
+            .challenge_repository
+            .accept_challenge(id, game, transfers)
+            .await?
+        {
+            return Err(not_found());
+        }
+
+        self.rooms_manager.open_room(game_id, room);
+        self.lobby.notify(
+            challenge.challenger_id,
+            Event::ChallengeAccepted(ChallengeInfo {
+                challenge_id: id,
+                game_id: Some(game_id),
+            }),
+        );
+
+        Ok(game_id)
+    }
+
+    pub async fn decline(&self, id: Uuid, user_id: Uuid) -> Result<()> {
+        let challenge = self.challenge_repository.get_challenge(id).await?;
+
+        if challenge.challenged_id != user_id {
+            return Err(not_found());
+        }
+
+        self.close(challenge, ChallengeStatus::Declined).await
+    }
+
+    pub async fn cancel(&self, id: Uuid, user_id: Uuid) -> Result<()> {
+        let challenge = self.challenge_repository.get_challenge(id).await?;
+
+        if challenge.challenger_id != user_id {
+            return Err(not_found());
+        }
+
+        self.close(challenge, ChallengeStatus::Canceled).await
+    }
+
+    async fn close(&self, challenge: Challenge, status: ChallengeStatus) -> Result<()> {
+        if !self
+            .challenge_repository
+            .close_challenge(challenge.id, status)
+            .await?
+        {
+            return Err(not_found());
+        }
+
+        let info = ChallengeInfo {
+            challenge_id: challenge.id,
+            game_id: None,
+        };
+        let (other_player, event) = match status {
+            ChallengeStatus::Declined => (challenge.challenger_id, Event::ChallengeDeclined(info)),
+            _ => (challenge.challenged_id, Event::ChallengeCanceled(info)),
+        };
+
+        self.lobby.notify(other_player, event);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MockLobbyTrait, MockRoomsManagerTrait, PlayerProtection, User};
+    use crate::repositories::{
+        MockChallengeRepositoryTrait, MockGamblingLimitRepositoryTrait, MockUserRepositoryTrait,
+        MockWalletRepositoryTrait,
+    };
+    use uuid::uuid;
+
+    const CHALLENGER_ID: Uuid = uuid!("5d6cc3e8-8eec-4dab-881f-fddfb831cc41");
+    const CHALLENGED_ID: Uuid = uuid!("9a4d5d1e-52a1-4c3b-a0f6-4f1f5a7f2b11");
+    const CHALLENGE_ID: Uuid = uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a");
+
+    fn challenge(request_key: &str, status: ChallengeStatus) -> Challenge {
+        Challenge {
+            id: CHALLENGE_ID,
+            challenger_id: CHALLENGER_ID,
+            challenger: String::from("alice"),
+            challenged_id: CHALLENGED_ID,
+            challenged: String::from("bob"),
+            request_key: request_key.to_string(),
+            status,
+            game_id: None,
+            created_at: Utc::now(),
+            expires_at: Utc::now() + Duration::days(1),
+        }
+    }
+
+    fn solvent_players() -> (
+        MockWalletRepositoryTrait,
+        MockGamblingLimitRepositoryTrait,
+        MockRoomsManagerTrait,
+    ) {
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(10_000_000)));
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .returning(|_| Ok(PlayerProtection::default()));
+        mock_rooms_manager.expect_count_games().returning(|_| 0);
+
+        (
+            mock_wallet_repository,
+            mock_gambling_limit_repository,
+            mock_rooms_manager,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_accept_seats_both_players_and_takes_both_stakes() {
+        let mut mock_challenge_repository = MockChallengeRepositoryTrait::new();
+        let mut mock_lobby = MockLobbyTrait::new();
+        let (mock_wallet_repository, mock_gambling_limit_repository, mut mock_rooms_manager) =
+            solvent_players();
+
+        let request = GameRequest::from_spec(
+            &serde_json::from_str(
+                r#"{ "version": 1, "color": "black", "base_seconds": 300, "bet_sats": 10 }"#,
+            )
+            .unwrap(),
+            Some(CHALLENGED_ID),
+        )
+        .unwrap();
+        let request_key = request.key.clone();
+
+        mock_challenge_repository
+            .expect_get_challenge()
+            .returning(move |_| Ok(challenge(&request_key, ChallengeStatus::Pending)));
+        mock_challenge_repository
+            .expect_accept_challenge()
+            .once()
+            .withf(|_, game, transfers| {
+                game.white_player == CHALLENGED_ID
+                    && game.black_player == CHALLENGER_ID
+                    && *transfers
+                        == vec![
+                            SaveTransfer::stake(CHALLENGER_ID, game.id, Msat::new(10_000)),
+                            SaveTransfer::stake(CHALLENGED_ID, game.id, Msat::new(10_000)),
+                        ]
+            })
+            .returning(|_, _, _| Ok(true));
+
+        mock_rooms_manager
+            .expect_open_room()
+            .once()
+            .withf(|_, room| {
+                room.is_full()
+                    && room.white_player == Some(CHALLENGED_ID)
+                    && room.black_player == Some(CHALLENGER_ID)
+            })
+            .return_const(());
+        mock_lobby
+            .expect_notify()
+            .once()
+            .withf(|player_id, _| *player_id == CHALLENGER_ID)
+            .return_const(());
+
+        let service = ChallengeService::new(
+            mock_challenge_repository,
+            MockUserRepositoryTrait::new(),
+            mock_wallet_repository,
+            mock_gambling_limit_repository,
+            mock_rooms_manager,
+            mock_lobby,
+            1,
+            Duration::days(1),
+        );
+
+        assert!(service.accept(CHALLENGE_ID, CHALLENGED_ID).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_only_the_challenged_player_accepts() {
+        let mut mock_challenge_repository = MockChallengeRepositoryTrait::new();
+
+        mock_challenge_repository
+            .expect_get_challenge()
+            .returning(|_| Ok(challenge("n-10-0-10", ChallengeStatus::Pending)));
+        mock_challenge_repository.expect_accept_challenge().never();
+
+        let service = ChallengeService::new(
+            mock_challenge_repository,
+            MockUserRepositoryTrait::new(),
+            MockWalletRepositoryTrait::new(),
+            MockGamblingLimitRepositoryTrait::new(),
+            MockRoomsManagerTrait::new(),
+            MockLobbyTrait::new(),
+            1,
+            Duration::days(1),
+        );
+
+        assert!(matches!(
+            service.accept(CHALLENGE_ID, CHALLENGER_ID).await,
+            Err(Error::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_expired_challenge_cannot_be_accepted() {
+        let mut mock_challenge_repository = MockChallengeRepositoryTrait::new();
+
+        mock_challenge_repository
+            .expect_get_challenge()
+            .returning(|_| {
+                Ok(Challenge {
+                    expires_at: Utc::now() - Duration::minutes(1),
+                    ..challenge("n-10-0-10", ChallengeStatus::Pending)
+                })
+            });
+        mock_challenge_repository.expect_accept_challenge().never();
+
+        let service = ChallengeService::new(
+            mock_challenge_repository,
+            MockUserRepositoryTrait::new(),
+            MockWalletRepositoryTrait::new(),
+            MockGamblingLimitRepositoryTrait::new(),
+            MockRoomsManagerTrait::new(),
+            MockLobbyTrait::new(),
+            1,
+            Duration::days(1),
+        );
+
+        assert!(matches!(
+            service.accept(CHALLENGE_ID, CHALLENGED_ID).await,
+            Err(Error::BadRequest { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_rejects_self_challenge() {
+        let mut mock_user_repository = MockUserRepositoryTrait::new();
+        let mut mock_challenge_repository = MockChallengeRepositoryTrait::new();
+
+        mock_user_repository
+            .expect_find_by_username()
+            .returning(|_| {
+                Ok(User {
+                    id: CHALLENGER_ID,
+                    email: String::from("alice@example.com"),
+                    username: String::from("alice"),
+                    hashed_password: String::new(),
+                    balance: Msat::ZERO,
+                })
+            });
+        mock_challenge_repository.expect_save_challenge().never();
+
+        let service = ChallengeService::new(
+            mock_challenge_repository,
+            mock_user_repository,
+            MockWalletRepositoryTrait::new(),
+            MockGamblingLimitRepositoryTrait::new(),
+            MockRoomsManagerTrait::new(),
+            MockLobbyTrait::new(),
+            1,
+            Duration::days(1),
+        );
+
+        assert!(service
+            .create(
+                CHALLENGER_ID,
+                &serde_json::from_str(
+                    r#"{ "version": 1, "base_seconds": 600, "bet_sats": 10, "opponent": "alice" }"#
+                )
+                .unwrap()
+            )
+            .await
+            .is_err());
+    }
+}
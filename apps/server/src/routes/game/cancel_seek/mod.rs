@@ -0,0 +1,40 @@
+use crate::http::{GenericError, Result};
+use crate::models::{AuthUser, Lobby, RoomsManager};
+use crate::repositories::{StakeHoldRepository, WalletRepository};
+use aide::transform::TransformOperation;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub(crate) mod service;
+
+use service::CancelSeekService;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CanceledSeek {
+    pub game_id: Uuid,
+}
+
+pub(crate) fn resource(
+) -> CancelSeekService<RoomsManager, WalletRepository, StakeHoldRepository, Lobby> {
+    CancelSeekService::new(
+        RoomsManager::new(),
+        WalletRepository::new(),
+        StakeHoldRepository::new(),
+        Lobby::new(),
+    )
+}
+
+pub async fn route(auth_user: AuthUser) -> Result<Json<CanceledSeek>> {
+    let game_id = resource().execute(auth_user.user_id).await?;
+
+    Ok(Json(CanceledSeek { game_id }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Quick Pairing")
+        .description("Cancel the open seek and refund its stake")
+        .response::<200, Json<CanceledSeek>>()
+        .response::<404, Json<GenericError>>()
+}
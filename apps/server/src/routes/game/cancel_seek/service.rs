@@ -0,0 +1,215 @@
+use crate::http::{Error, Result};
+use crate::models::{Event, LobbyTrait, RoomsManagerTrait, Seek, SeekInfo, StakeMode};
+use crate::repositories::{
+    HoldResolution, ResolveHold, SaveTransfer, StakeHoldRepositoryTrait, WalletRepositoryTrait,
+};
+use uuid::Uuid;
+
+pub struct CancelSeekService<
+    M: RoomsManagerTrait,
+    W: WalletRepositoryTrait,
+    H: StakeHoldRepositoryTrait,
+    B: LobbyTrait,
+> {
+    rooms_manager: M,
+    wallet_repository: W,
+    stake_hold_repository: H,
+    lobby: B,
+}
+
+impl<
+        M: RoomsManagerTrait,
+        W: WalletRepositoryTrait,
+        H: StakeHoldRepositoryTrait,
+        B: LobbyTrait,
+    > CancelSeekService<M, W, H, B>
+{
+    pub fn new(rooms_manager: M, wallet_repository: W, stake_hold_repository: H, lobby: B) -> Self {
+        Self {
+            rooms_manager,
+            wallet_repository,
+            stake_hold_repository,
+            lobby,
+        }
+    }
+
+    /// Withdraws the open seek of the player and refunds its stake. Returns
+    /// the id of the game the seek was waiting in.
+    pub async fn execute(&self, player_id: Uuid) -> Result<Uuid> {
+        // Taking the seek out of the pool first means no one can be paired
+        // into the room while it is being closed.
+        let (game_id, seek) = self
+            .rooms_manager
+            .take_seek(player_id)
+            .ok_or(Error::NotFound {
+                message: String::from("You have no open seek!"),
+            })?;
+
+        // The seek goes back in the pool if the refund fails, so the player
+        // can cancel it again.
+        if let Err(error) = self
+            .close(
+                game_id,
+                seek.clone(),
+                Event::SeekCanceled(SeekInfo { game_id }),
+            )
+            .await
+        {
+            self.rooms_manager.restore_seek(game_id, seek);
+
+            return Err(error);
+        }
+
+        Ok(game_id)
+    }
+
+    /// Refunds a seek already taken out of the pool, then closes its room and
+    /// sends `event` to the player.
+    pub async fn close(&self, game_id: Uuid, seek: Seek, event: Event) -> Result<()> {
+        let player_id = seek.player_id;
+
+        match seek.stake_mode {
+            StakeMode::Custodial => {
+                self.wallet_repository
+                    .save_transfer(SaveTransfer::refund(
+                        game_id,
+                        player_id,
+                        seek.bet_value.to_msat()?,
+                    ))
+                    .await?;
+            }
+            StakeMode::Hold => {
+                self.stake_hold_repository
+                    .resolve_stake_hold(
+                        game_id,
+                        ResolveHold {
+                            user_id: player_id,
+                            resolution: HoldResolution::Cancel,
+                        },
+                    )
+                    .await?;
+            }
+        }
+
+        if let Ok(room) = self.rooms_manager.get_room(game_id) {
+            room.relay_event(event.clone());
+        }
+
+        self.rooms_manager.remove_room(game_id);
+        self.lobby.notify(player_id, event);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GameRequest, MockLobbyTrait, MockRoomsManagerTrait, Msat};
+    use crate::repositories::{MockStakeHoldRepositoryTrait, MockWalletRepositoryTrait};
+    use uuid::uuid;
+
+    const PLAYER_ID: Uuid = uuid!("5d6cc3e8-8eec-4dab-881f-fddfb831cc41");
+    const GAME_ID: Uuid = uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a");
+
+    #[tokio::test]
+    async fn test_cancel_seek_refunds_stake() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_lobby = MockLobbyTrait::new();
+
+        mock_rooms_manager.expect_take_seek().once().returning(|_| {
+            Some((
+                GAME_ID,
+                Seek::new(
+                    PLAYER_ID,
+                    &GameRequest::from_str("w-10-0-10").unwrap(),
+                    1500.0,
+                ),
+            ))
+        });
+        mock_rooms_manager
+            .expect_get_room()
+            .returning(|_| Err(Error::InternalServerError));
+        mock_rooms_manager
+            .expect_remove_room()
+            .once()
+            .withf(|id| *id == GAME_ID)
+            .return_const(());
+
+        mock_wallet_repository
+            .expect_save_transfer()
+            .once()
+            .withf(|transfer| {
+                *transfer == SaveTransfer::refund(GAME_ID, PLAYER_ID, Msat::new(10_000))
+            })
+            .returning(|_| Ok(Uuid::new_v4()));
+
+        mock_lobby.expect_notify().once().return_const(());
+
+        let service = CancelSeekService::new(
+            mock_rooms_manager,
+            mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            mock_lobby,
+        );
+
+        assert_eq!(service.execute(PLAYER_ID).await.ok(), Some(GAME_ID));
+    }
+
+    #[tokio::test]
+    async fn test_failed_refund_restores_seek() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let seek = Seek::new(
+            PLAYER_ID,
+            &GameRequest::from_str("w-10-0-10").unwrap(),
+            1500.0,
+        );
+        let taken = seek.clone();
+
+        mock_rooms_manager
+            .expect_take_seek()
+            .once()
+            .returning(move |_| Some((GAME_ID, taken.clone())));
+        mock_rooms_manager
+            .expect_restore_seek()
+            .once()
+            .withf(move |id, restored| *id == GAME_ID && *restored == seek)
+            .return_const(());
+        mock_rooms_manager.expect_remove_room().never();
+
+        mock_wallet_repository
+            .expect_save_transfer()
+            .once()
+            .returning(|_| Err(Error::InternalServerError));
+
+        let service = CancelSeekService::new(
+            mock_rooms_manager,
+            mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            MockLobbyTrait::new(),
+        );
+
+        assert!(service.execute(PLAYER_ID).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_without_seek() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+
+        mock_rooms_manager.expect_take_seek().returning(|_| None);
+        mock_rooms_manager.expect_remove_room().never();
+        mock_wallet_repository.expect_save_transfer().never();
+
+        let service = CancelSeekService::new(
+            mock_rooms_manager,
+            mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            MockLobbyTrait::new(),
+        );
+
+        assert!(service.execute(PLAYER_ID).await.is_err());
+    }
+}
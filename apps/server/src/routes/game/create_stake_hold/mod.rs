@@ -0,0 +1,55 @@
+use crate::http::{GenericError, Result};
+use crate::models::{AuthUser, Sats};
+use crate::repositories::StakeHoldRepository;
+use aide::transform::TransformOperation;
+use axum::Json;
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::{CreateStakeHoldInput, CreateStakeHoldService};
+
+mod service;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct StakeHoldRequestBody {
+    amount: Sats,
+    /// Lightning Address or LNURL-pay link that receives the winnings.
+    payout_destination: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct StakeHoldBody {
+    invoice: String,
+    payment_hash: String,
+}
+
+fn resource() -> CreateStakeHoldService<StakeHoldRepository, Client> {
+    CreateStakeHoldService::new(StakeHoldRepository::new(), Client::new())
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Json(payload): Json<StakeHoldRequestBody>,
+) -> Result<Json<StakeHoldBody>> {
+    let create_stake_hold_service = resource();
+
+    let invoice = create_stake_hold_service
+        .execute(CreateStakeHoldInput {
+            user_id: auth_user.user_id,
+            amount: payload.amount,
+            payout_destination: payload.payout_destination,
+        })
+        .await?;
+
+    Ok(Json(StakeHoldBody {
+        invoice: invoice.payment_request,
+        payment_hash: invoice.payment_hash,
+    }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Quick Pairing")
+        .description("Create a hold invoice that locks the stake of a non-custodial game. Pay it, then pair with its payment hash and a key ending in `-h`")
+        .response::<200, Json<StakeHoldBody>>()
+        .response::<400, Json<GenericError>>()
+}
@@ -0,0 +1,182 @@
+use crate::bad_req;
+use crate::http::{CreateHoldInvoice, Error, LightningClient, Result};
+use crate::models::{pay_request_url, DecodedInvoice, Sats};
+use crate::repositories::{SaveStakeHold, StakeHoldRepositoryTrait};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+// The payer's wallet shows this memo while the stake is locked.
+const STAKE_MEMO: &str = "Chesu stake";
+
+pub struct CreateStakeHoldService<H: StakeHoldRepositoryTrait, L: LightningClient> {
+    stake_hold_repository: H,
+    lightning_client: L,
+}
+
+pub struct CreateStakeHoldInput {
+    pub user_id: Uuid,
+    pub amount: Sats,
+    pub payout_destination: Option<String>,
+}
+
+/// Returns a random preimage and its payment hash, both hex encoded.
+fn new_preimage() -> (String, String) {
+    let preimage = rand::random::<[u8; 32]>();
+    let encode = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    };
+
+    (encode(&preimage), encode(&Sha256::digest(preimage)))
+}
+
+impl<H: StakeHoldRepositoryTrait, L: LightningClient> CreateStakeHoldService<H, L> {
+    pub fn new(stake_hold_repository: H, lightning_client: L) -> Self {
+        Self {
+            stake_hold_repository,
+            lightning_client,
+        }
+    }
+
+    /// Creates the hold invoice that locks a stake in the player's wallet.
+    /// Winnings go to `payout_destination`, or to the player's balance when
+    /// there is none.
+    pub async fn execute(
+        &self,
+        CreateStakeHoldInput {
+            user_id,
+            amount,
+            payout_destination,
+        }: CreateStakeHoldInput,
+    ) -> Result<DecodedInvoice> {
+        if !self.lightning_client.supports_hold_invoices() {
+            return bad_req!("Non-custodial stakes are not available on this server");
+        }
+
+        if !amount.is_positive() {
+            return bad_req!("Invalid stake amount");
+        }
+
+        let amount_msat = amount.to_msat()?;
+
+        if let Some(destination) = &payout_destination {
+            pay_request_url(destination)?;
+        }
+
+        let (preimage, payment_hash) = new_preimage();
+        let payment_request = self
+            .lightning_client
+            .create_hold_invoice(CreateHoldInvoice {
+                amount,
+                payment_hash: payment_hash.clone(),
+                memo: String::from(STAKE_MEMO),
+            })
+            .await?;
+        let invoice = DecodedInvoice::decode(&payment_request)?;
+
+        if invoice.amount != amount_msat || invoice.payment_hash != payment_hash {
+            return Err(Error::InternalServerError);
+        }
+
+        self.stake_hold_repository
+            .save_stake_hold(SaveStakeHold {
+                user_id,
+                payment_hash,
+                preimage,
+                payment_request: invoice.payment_request.clone(),
+                amount: amount_msat,
+                payout_destination,
+            })
+            .await?;
+
+        Ok(invoice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{FakeLightning, HoldInvoiceState, MockLightningClient};
+    use crate::models::Msat;
+    use crate::repositories::MockStakeHoldRepositoryTrait;
+    use uuid::uuid;
+
+    fn input(amount: i64, payout_destination: Option<&str>) -> CreateStakeHoldInput {
+        CreateStakeHoldInput {
+            user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+            amount: Sats::new(amount),
+            payout_destination: payout_destination.map(String::from),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_stake_hold() {
+        let lightning = FakeLightning::new();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+
+        mock_stake_hold_repository
+            .expect_save_stake_hold()
+            .once()
+            .withf(|hold| {
+                hold.amount == Msat::new(100_000)
+                    && hold.payout_destination.as_deref() == Some("bob@chesu.com")
+                    && new_hash(&hold.preimage) == hold.payment_hash
+            })
+            .returning(|_| Ok(()));
+
+        let service = CreateStakeHoldService::new(mock_stake_hold_repository, lightning.clone());
+
+        let invoice = service
+            .execute(input(100, Some("bob@chesu.com")))
+            .await
+            .unwrap();
+
+        assert_eq!(invoice.amount, Msat::new(100_000));
+        assert_eq!(
+            lightning.invoice_state(&invoice.payment_hash),
+            Some(HoldInvoiceState::Open)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_stake_hold_invalid_destination() {
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+
+        mock_stake_hold_repository.expect_save_stake_hold().never();
+
+        let service = CreateStakeHoldService::new(mock_stake_hold_repository, FakeLightning::new());
+
+        assert!(service.execute(input(100, Some("bob"))).await.is_err());
+        assert!(service.execute(input(0, None)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stake_hold_needs_hold_invoices() {
+        let mut mock_lightning_client = MockLightningClient::new();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+
+        mock_lightning_client
+            .expect_supports_hold_invoices()
+            .returning(|| false);
+        mock_lightning_client.expect_create_hold_invoice().never();
+        mock_stake_hold_repository.expect_save_stake_hold().never();
+
+        let service =
+            CreateStakeHoldService::new(mock_stake_hold_repository, mock_lightning_client);
+
+        let result = service.execute(input(100, None)).await;
+
+        assert!(matches!(result, Err(Error::BadRequest { .. })));
+    }
+
+    fn new_hash(preimage: &str) -> String {
+        let bytes = (0..preimage.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&preimage[i..i + 2], 16).unwrap())
+            .collect::<Vec<_>>();
+
+        format!("{:x}", Sha256::digest(bytes))
+    }
+}
@@ -1,74 +1,174 @@
 use uuid::Uuid;
 
-use crate::http::Result;
-use crate::models::{DisconnectInfo, Event, Game, GameRequest, GameState, RoomsManagerTrait};
-use crate::repositories::{GameRepositoryTrait, SaveIncoming, WalletRepositoryTrait};
-
-pub struct DisconnectService<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait>
-{
+use crate::http::{Error, Result};
+use crate::internal_error;
+use crate::models::{
+    DisconnectInfo, Event, Game, GameState, Msat, Rake, RoomsManagerTrait, Seek, StakeMode,
+    Termination,
+};
+use crate::repositories::{
+    GameRepositoryTrait, HoldResolution, ResolveHold, SaveTransfer, Settlement,
+    StakeHoldRepositoryTrait, WalletRepositoryTrait,
+};
+
+pub struct DisconnectService<
+    R: GameRepositoryTrait,
+    M: RoomsManagerTrait,
+    W: WalletRepositoryTrait,
+    H: StakeHoldRepositoryTrait,
+> {
     game_repository: R,
     rooms_manager: M,
     wallet_repository: W,
+    stake_hold_repository: H,
+    rake: Rake,
 }
 
-fn check_new_game_state(game: &Game, player_disconnect: Uuid) -> Option<GameState> {
+fn check_new_game_state(game: &Game, player_disconnect: Uuid) -> Option<(GameState, Termination)> {
     match (game.state, player_disconnect) {
-        (GameState::Waiting, _) => Some(GameState::Draw),
+        (GameState::Waiting, _) => Some((GameState::Draw, Termination::Aborted)),
         (GameState::Running, player_id) if player_id == game.white_player => {
-            Some(GameState::BlackWin)
+            Some((GameState::BlackWin, Termination::Abandoned))
         }
         (GameState::Running, player_id) if player_id == game.black_player => {
-            Some(GameState::WhiteWin)
+            Some((GameState::WhiteWin, Termination::Abandoned))
         }
         _ => None,
     }
 }
 
-pub async fn resolve_bet<W: WalletRepositoryTrait>(
-    wallet_repository: &W,
-    game: &Game,
-) -> Result<()> {
-    let (white_amount, black_amount) = match game.state {
-        GameState::Draw => (game.bet_value, game.bet_value),
-        GameState::WhiteWin => (2 * game.bet_value, 0),
-        GameState::BlackWin => (0, 2 * game.bet_value),
-        _ => (0, 0),
+pub fn resolve_bet(game: &Game, termination: Termination, rake: &Rake) -> Result<Settlement> {
+    let stake = game.bet_value.to_msat()?;
+    let winner = match game.state {
+        GameState::WhiteWin => Some(game.white_player),
+        GameState::BlackWin => Some(game.black_player),
+        _ => None,
     };
 
-    if white_amount > 0 {
-        wallet_repository
-            .save_incoming(SaveIncoming {
-                user_id: game.white_player,
-                amount: white_amount,
-                invoice: None,
-            })
-            .await?;
+    if game.stake_mode == StakeMode::Hold {
+        return Ok(Settlement {
+            game_id: game.id,
+            state: game.state,
+            termination,
+            last_move: None,
+            transfers: vec![],
+            holds: resolve_holds(game, stake, winner, rake)?,
+        });
     }
 
-    if black_amount > 0 {
-        wallet_repository
-            .save_incoming(SaveIncoming {
-                user_id: game.black_player,
-                amount: black_amount,
-                invoice: None,
-            })
-            .await?;
-    }
+    let transfers = match (game.state, winner) {
+        (_, Some(winner)) => {
+            let fee = rake.fee(stake);
+            let pot = stake.checked_add(stake).ok_or(internal_error!())?;
+            let mut transfers = vec![SaveTransfer::payout(game.id, winner, pot)];
 
-    Ok(())
+            if fee.is_positive() {
+                transfers.push(SaveTransfer::fee(game.id, winner, fee));
+            }
+
+            transfers
+        }
+        (GameState::Draw, _) => vec![
+            SaveTransfer::refund(game.id, game.white_player, stake),
+            SaveTransfer::refund(game.id, game.black_player, stake),
+        ],
+        _ => vec![],
+    };
+
+    Ok(Settlement {
+        game_id: game.id,
+        state: game.state,
+        termination,
+        last_move: None,
+        transfers,
+        holds: vec![],
+    })
 }
 
-impl<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait>
-    DisconnectService<R, M, W>
+/// The loser's hold pays the winner, minus the fee. Every other hold is
+/// cancelled so the stake never leaves the player's wallet.
+fn resolve_holds(
+    game: &Game,
+    stake: Msat,
+    winner: Option<Uuid>,
+    rake: &Rake,
+) -> Result<Vec<ResolveHold>> {
+    let players = [game.white_player, game.black_player];
+
+    players
+        .into_iter()
+        .map(|user_id| {
+            let resolution = match winner {
+                Some(winner) if winner != user_id => {
+                    let fee = rake.fee(stake);
+
+                    HoldResolution::Settle {
+                        payout_user_id: winner,
+                        payout_amount: stake.checked_sub(fee).ok_or(internal_error!())?,
+                        fee,
+                    }
+                }
+                _ => HoldResolution::Cancel,
+            };
+
+            Ok(ResolveHold {
+                user_id,
+                resolution,
+            })
+        })
+        .collect()
+}
+
+impl<
+        R: GameRepositoryTrait,
+        M: RoomsManagerTrait,
+        W: WalletRepositoryTrait,
+        H: StakeHoldRepositoryTrait,
+    > DisconnectService<R, M, W, H>
 {
-    pub fn new(game_repository: R, rooms_manager: M, wallet_repository: W) -> Self {
+    pub fn new(
+        game_repository: R,
+        rooms_manager: M,
+        wallet_repository: W,
+        stake_hold_repository: H,
+        rake: Rake,
+    ) -> Self {
         Self {
             game_repository,
             rooms_manager,
             wallet_repository,
+            stake_hold_repository,
+            rake,
         }
     }
 
+    async fn refund_seek(&self, game_id: Uuid, seek: &Seek) -> Result<()> {
+        match seek.stake_mode {
+            StakeMode::Custodial => {
+                self.wallet_repository
+                    .save_transfer(SaveTransfer::refund(
+                        game_id,
+                        seek.player_id,
+                        seek.bet_value.to_msat()?,
+                    ))
+                    .await?;
+            }
+            StakeMode::Hold => {
+                self.stake_hold_repository
+                    .resolve_stake_hold(
+                        game_id,
+                        ResolveHold {
+                            user_id: seek.player_id,
+                            resolution: HoldResolution::Cancel,
+                        },
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn execute(&self, info: DisconnectInfo) -> Result<(), String> {
         let room = self.rooms_manager.get_room(info.game_id)?;
 
@@ -76,34 +176,41 @@ impl<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait>
             return Ok(());
         }
 
-        self.rooms_manager.remove_room(info.game_id);
-
         if !room.is_full() {
-            self.wallet_repository
-                .save_incoming(SaveIncoming {
-                    user_id: info.player_id,
-                    amount: GameRequest::from_str(&room.request_key)?.bet_value,
-                    invoice: None,
-                })
-                .await?;
+            // Taking the seek out of the pool first means no one can be paired
+            // into the room while it is being closed. If it is gone, the room
+            // was paired in the meantime and the game is settled below.
+            if let Some(seek) = self.rooms_manager.remove_seek(info.game_id) {
+                if let Err(err) = self.refund_seek(info.game_id, &seek).await {
+                    self.rooms_manager.restore_seek(info.game_id, seek);
 
-            return Ok(self.rooms_manager.remove_request(&room.request_key));
+                    return Err(err.into());
+                }
+
+                self.rooms_manager.remove_room(info.game_id);
+
+                return Ok(());
+            }
         }
 
         let mut game = self.game_repository.get_game(info.game_id).await?;
 
-        if let Some(new_game_state) = check_new_game_state(&game, info.player_id) {
+        // The room stays open until the game is settled, so a failed
+        // settlement can be retried.
+        if let Some((new_game_state, termination)) = check_new_game_state(&game, info.player_id) {
             game.state = new_game_state;
 
-            self.game_repository
-                .update_state(game.id, new_game_state)
-                .await?;
+            let settlement = resolve_bet(&game, termination, &self.rake)?;
+            let result = settlement.result()?;
 
-            resolve_bet(&self.wallet_repository, &game).await?;
-
-            room.relay_event(Event::GameChangeState(new_game_state));
+            if self.game_repository.settle_game(settlement).await? {
+                room.relay_event(Event::GameChangeState(new_game_state));
+                room.relay_event(Event::GameResult(result));
+            }
         }
 
+        self.rooms_manager.remove_room(info.game_id);
+
         Ok(())
     }
 }
@@ -111,8 +218,12 @@ impl<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait>
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{DisconnectInfo, Game, GameState, MockRoomsManagerTrait, Room};
-    use crate::repositories::{MockGameRepositoryTrait, MockWalletRepositoryTrait};
+    use crate::models::{
+        DisconnectInfo, Game, GameRequest, GameState, MockRoomsManagerTrait, Room, Sats,
+    };
+    use crate::repositories::{
+        MockGameRepositoryTrait, MockStakeHoldRepositoryTrait, MockWalletRepositoryTrait,
+    };
     use tokio::sync::broadcast;
     use uuid::Uuid;
 
@@ -127,15 +238,23 @@ mod tests {
                 request_key: String::from("w-10-0-0"),
                 white_player: Some(uuid::uuid!("73c1fad5-db48-4dce-8e03-6be3b43b0e7b")),
                 black_player: None,
+                rematch_offer: None,
+                finished: false,
                 tx: broadcast::channel(100).0,
             })
         });
 
         mock_rooms_manager
-            .expect_remove_request()
+            .expect_remove_seek()
             .once()
-            .withf(|request_key| request_key == "w-10-0-0")
-            .returning(|_| ());
+            .withf(|id| id == &uuid::uuid!("6a2b4680-e96d-4e33-923f-3979d09d8ade"))
+            .returning(|_| {
+                Some(Seek::new(
+                    uuid::uuid!("73c1fad5-db48-4dce-8e03-6be3b43b0e7b"),
+                    &GameRequest::from_str("w-10-0-10").unwrap(),
+                    1500.0,
+                ))
+            });
 
         mock_rooms_manager
             .expect_remove_room()
@@ -144,8 +263,16 @@ mod tests {
             .returning(|_| ());
 
         mock_wallet_repository
-            .expect_save_incoming()
+            .expect_save_transfer()
             .once()
+            .withf(|transfer| {
+                *transfer
+                    == SaveTransfer::refund(
+                        uuid::uuid!("6a2b4680-e96d-4e33-923f-3979d09d8ade"),
+                        uuid::uuid!("73c1fad5-db48-4dce-8e03-6be3b43b0e7b"),
+                        Msat::new(10_000),
+                    )
+            })
             .returning(|_| Ok(Uuid::new_v4()));
 
         let input = DisconnectInfo {
@@ -157,6 +284,72 @@ mod tests {
             mock_game_repository,
             mock_rooms_manager,
             mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            Rake::default(),
+        );
+
+        let result = service.execute(input).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_disconnection_after_seek_was_paired() {
+        let mut mock_game_repository = MockGameRepositoryTrait::new();
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+
+        let input = DisconnectInfo {
+            game_id: uuid::uuid!("6a2b4680-e96d-4e33-923f-3979d09d8ade"),
+            player_id: uuid::uuid!("73c1fad5-db48-4dce-8e03-6be3b43b0e7b"),
+        };
+
+        mock_rooms_manager.expect_get_room().once().returning(|_| {
+            Ok(Room {
+                white_player: Some(uuid::uuid!("73c1fad5-db48-4dce-8e03-6be3b43b0e7b")),
+                ..Room::new(String::from("w-10-0-10"))
+            })
+        });
+        mock_rooms_manager
+            .expect_remove_seek()
+            .once()
+            .returning(|_| None);
+        mock_rooms_manager
+            .expect_remove_room()
+            .once()
+            .return_const(());
+
+        mock_wallet_repository.expect_save_transfer().never();
+
+        mock_game_repository
+            .expect_get_game()
+            .once()
+            .returning(|id| {
+                Ok(Game {
+                    id,
+                    white_player: uuid::uuid!("73c1fad5-db48-4dce-8e03-6be3b43b0e7b"),
+                    black_player: Uuid::new_v4(),
+                    bet_value: Sats::new(10),
+                    state: GameState::Waiting,
+                    ..Default::default()
+                })
+            });
+        mock_game_repository
+            .expect_settle_game()
+            .once()
+            .withf(|settlement| {
+                settlement.state == GameState::Draw
+                    && settlement.termination == Termination::Aborted
+                    && settlement.transfers.len() == 2
+            })
+            .returning(|_| Ok(true));
+
+        let service = DisconnectService::new(
+            mock_game_repository,
+            mock_rooms_manager,
+            mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            Rake::default(),
         );
 
         let result = service.execute(input).await;
@@ -184,6 +377,8 @@ mod tests {
                     request_key: String::from("w-10-0-0"),
                     white_player: Some(uuid::uuid!("6a2b4680-e96d-4e33-923f-3979d09d8ade")),
                     black_player: Some(Uuid::new_v4()),
+                    rematch_offer: None,
+                    finished: false,
                     tx: broadcast::channel(100).0,
                 })
             });
@@ -197,19 +392,22 @@ mod tests {
                     id,
                     white_player: uuid::uuid!("6a2b4680-e96d-4e33-923f-3979d09d8ade"),
                     black_player: Uuid::new_v4(),
-                    bet_value: 0,
+                    bet_value: Sats::ZERO,
                     state: GameState::Running,
                     moves: Vec::new(),
+                    ..Default::default()
                 })
             });
 
         mock_game_repository
-            .expect_update_state()
-            .withf(|id, g| {
-                id == &uuid::uuid!("73c1fad5-db48-4dce-8e03-6be3b43b0e7b")
-                    && g == &GameState::BlackWin
+            .expect_settle_game()
+            .once()
+            .withf(|settlement| {
+                settlement.game_id == uuid::uuid!("73c1fad5-db48-4dce-8e03-6be3b43b0e7b")
+                    && settlement.state == GameState::BlackWin
+                    && settlement.termination == Termination::Abandoned
             })
-            .returning(|_, _| Ok(()));
+            .returning(|_| Ok(true));
 
         mock_rooms_manager
             .expect_remove_room()
@@ -221,6 +419,8 @@ mod tests {
             mock_game_repository,
             mock_rooms_manager,
             mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            Rake::default(),
         );
 
         let result = service.execute(input).await;
@@ -228,6 +428,61 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_failed_settlement_keeps_the_room() {
+        let mut mock_game_repository = MockGameRepositoryTrait::new();
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let player_id = Uuid::new_v4();
+
+        mock_rooms_manager
+            .expect_get_room()
+            .once()
+            .returning(move |_| {
+                Ok(Room {
+                    white_player: Some(player_id),
+                    black_player: Some(Uuid::new_v4()),
+                    ..Room::new(String::from("w-10-0-10"))
+                })
+            });
+
+        mock_game_repository
+            .expect_get_game()
+            .once()
+            .returning(move |id| {
+                Ok(Game {
+                    id,
+                    white_player: player_id,
+                    black_player: Uuid::new_v4(),
+                    bet_value: Sats::new(10),
+                    state: GameState::Running,
+                    ..Default::default()
+                })
+            });
+        mock_game_repository
+            .expect_settle_game()
+            .once()
+            .returning(|_| Err(Error::InternalServerError));
+
+        mock_rooms_manager.expect_remove_room().never();
+
+        let service = DisconnectService::new(
+            mock_game_repository,
+            mock_rooms_manager,
+            MockWalletRepositoryTrait::new(),
+            MockStakeHoldRepositoryTrait::new(),
+            Rake::default(),
+        );
+
+        let result = service
+            .execute(DisconnectInfo {
+                game_id: Uuid::new_v4(),
+                player_id,
+            })
+            .await;
+
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_viewer_disconnection() {
         let mut mock_game_repository = MockGameRepositoryTrait::new();
@@ -248,22 +503,157 @@ mod tests {
                     request_key: String::from("w-10-0-0"),
                     white_player: Some(Uuid::new_v4()),
                     black_player: Some(Uuid::new_v4()),
+                    rematch_offer: None,
+                    finished: false,
                     tx: broadcast::channel(100).0,
                 })
             });
 
         mock_game_repository.expect_get_game().never();
-        mock_game_repository.expect_update_state().never();
+        mock_game_repository.expect_settle_game().never();
         mock_rooms_manager.expect_remove_room().never();
 
         let service = DisconnectService::new(
             mock_game_repository,
             mock_rooms_manager,
             mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            Rake::default(),
         );
 
         let result = service.execute(input).await;
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_resolve_bet_win() {
+        let game = Game {
+            id: Uuid::new_v4(),
+            white_player: Uuid::new_v4(),
+            black_player: Uuid::new_v4(),
+            bet_value: Sats::new(100),
+            state: GameState::WhiteWin,
+            moves: Vec::new(),
+            ..Default::default()
+        };
+
+        let settlement = resolve_bet(&game, Termination::Checkmate, &Rake::default()).unwrap();
+
+        assert_eq!(settlement.state, GameState::WhiteWin);
+        assert_eq!(
+            settlement.transfers,
+            vec![SaveTransfer::payout(
+                game.id,
+                game.white_player,
+                Msat::new(200_000)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_resolve_bet_win_with_rake() {
+        let game = Game {
+            id: Uuid::new_v4(),
+            white_player: Uuid::new_v4(),
+            black_player: Uuid::new_v4(),
+            bet_value: Sats::new(100),
+            state: GameState::BlackWin,
+            moves: Vec::new(),
+            ..Default::default()
+        };
+
+        let rake = Rake::parse("0:500", "").unwrap();
+        let settlement = resolve_bet(&game, Termination::Checkmate, &rake).unwrap();
+
+        assert_eq!(
+            settlement.transfers,
+            vec![
+                SaveTransfer::payout(game.id, game.black_player, Msat::new(200_000)),
+                SaveTransfer::fee(game.id, game.black_player, Msat::new(10_000)),
+            ]
+        );
+        assert_eq!(settlement.result().unwrap().fee, Msat::new(10_000));
+    }
+
+    #[test]
+    fn test_resolve_bet_draw() {
+        let game = Game {
+            id: Uuid::new_v4(),
+            white_player: Uuid::new_v4(),
+            black_player: Uuid::new_v4(),
+            bet_value: Sats::new(100),
+            state: GameState::Draw,
+            moves: Vec::new(),
+            ..Default::default()
+        };
+
+        let rake = Rake::parse("0:500", "1").unwrap();
+        let settlement = resolve_bet(&game, Termination::Draw, &rake).unwrap();
+
+        assert_eq!(
+            settlement.transfers,
+            vec![
+                SaveTransfer::refund(game.id, game.white_player, Msat::new(100_000)),
+                SaveTransfer::refund(game.id, game.black_player, Msat::new(100_000)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_bet_hold_win() {
+        let game = Game {
+            id: Uuid::new_v4(),
+            white_player: Uuid::new_v4(),
+            black_player: Uuid::new_v4(),
+            bet_value: Sats::new(100),
+            state: GameState::WhiteWin,
+            stake_mode: StakeMode::Hold,
+            ..Default::default()
+        };
+
+        let rake = Rake::parse("0:500", "").unwrap();
+        let settlement = resolve_bet(&game, Termination::Checkmate, &rake).unwrap();
+
+        assert!(settlement.transfers.is_empty());
+        assert_eq!(
+            settlement.holds,
+            vec![
+                ResolveHold {
+                    user_id: game.white_player,
+                    resolution: HoldResolution::Cancel,
+                },
+                ResolveHold {
+                    user_id: game.black_player,
+                    resolution: HoldResolution::Settle {
+                        payout_user_id: game.white_player,
+                        payout_amount: Msat::new(90_000),
+                        fee: Msat::new(10_000),
+                    },
+                },
+            ]
+        );
+        assert_eq!(settlement.result().unwrap().fee, Msat::new(10_000));
+    }
+
+    #[test]
+    fn test_resolve_bet_hold_draw() {
+        let game = Game {
+            id: Uuid::new_v4(),
+            white_player: Uuid::new_v4(),
+            black_player: Uuid::new_v4(),
+            bet_value: Sats::new(100),
+            state: GameState::Draw,
+            stake_mode: StakeMode::Hold,
+            ..Default::default()
+        };
+
+        let settlement = resolve_bet(&game, Termination::Draw, &Rake::default()).unwrap();
+
+        assert!(settlement.transfers.is_empty());
+        assert!(settlement
+            .holds
+            .iter()
+            .all(|hold| hold.resolution == HoldResolution::Cancel));
+    }
 }
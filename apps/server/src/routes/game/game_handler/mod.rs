@@ -1,6 +1,9 @@
 use crate::{
-    models::{Event, RoomsManager, RoomsManagerTrait},
-    repositories::{GameRepository, WalletRepository},
+    models::{AuthUser, Event, Rake, RematchInfo, RoomsManager, RoomsManagerTrait},
+    repositories::{
+        GamblingLimitRepository, GameRepository, StakeHoldRepository, WalletRepository,
+    },
+    states::{max_concurrent_games, rake},
 };
 use aide::{transform::TransformOperation, NoApi};
 use axum::{
@@ -14,32 +17,58 @@ use disconnect_service::DisconnectService;
 use futures::SinkExt;
 use futures::StreamExt;
 use play_move_service::PlayMoveService;
+use rematch_service::RematchService;
 use tokio::sync::broadcast;
-use tracing;
+use uuid::Uuid;
 
 mod disconnect_service;
 mod play_move_service;
+mod rematch_service;
 
-fn resource() -> (
-    PlayMoveService<GameRepository, RoomsManager, WalletRepository>,
-    DisconnectService<GameRepository, RoomsManager, WalletRepository>,
+fn resource(
+    rake: Rake,
+) -> (
+    PlayMoveService<GameRepository, RoomsManager>,
+    DisconnectService<GameRepository, RoomsManager, WalletRepository, StakeHoldRepository>,
 ) {
     (
-        PlayMoveService::new(
-            GameRepository::new(),
-            RoomsManager::new(),
-            WalletRepository::new(),
-        ),
+        PlayMoveService::new(GameRepository::new(), RoomsManager::new(), rake.clone()),
         DisconnectService::new(
             GameRepository::new(),
             RoomsManager::new(),
             WalletRepository::new(),
+            StakeHoldRepository::new(),
+            rake,
         ),
     )
 }
 
-pub async fn route(NoApi(ws): NoApi<WebSocketUpgrade>) -> NoApi<impl IntoResponse> {
-    NoApi(ws.on_upgrade(game_handler))
+fn rematch_resource(
+) -> RematchService<GameRepository, RoomsManager, WalletRepository, GamblingLimitRepository> {
+    RematchService::new(
+        GameRepository::new(),
+        RoomsManager::new(),
+        WalletRepository::new(),
+        GamblingLimitRepository::new(),
+        max_concurrent_games::get(),
+    )
+}
+
+/// Anyone can watch a game, but only a signed in player can ask for a rematch.
+pub async fn route(
+    auth_user: Option<AuthUser>,
+    NoApi(ws): NoApi<WebSocketUpgrade>,
+) -> NoApi<impl IntoResponse> {
+    let user_id = auth_user.map(|auth_user| auth_user.user_id);
+
+    NoApi(ws.on_upgrade(move |socket| game_handler(socket, user_id)))
+}
+
+/// The rematch event of `data` as sent by the player of the session.
+fn rematch_info(data: RematchInfo, user_id: Option<Uuid>) -> Result<RematchInfo, String> {
+    let player_id = user_id.ok_or(String::from("Sign in to ask for a rematch!"))?;
+
+    Ok(RematchInfo { player_id, ..data })
 }
 
 fn connect_channel(
@@ -53,15 +82,15 @@ fn connect_channel(
     tx.map(|tx| (tx.clone(), tx.subscribe()))
 }
 
-async fn game_handler(socket: WebSocket) {
-    let (play_move, disconnect) = resource();
+async fn game_handler(socket: WebSocket, user_id: Option<Uuid>) {
+    let (play_move, disconnect) = resource(rake::get());
+    let rematch = rematch_resource();
 
     let (mut sender, mut receiver) = socket.split();
     let mut channel = None::<(broadcast::Sender<String>, broadcast::Receiver<String>)>;
 
-    while let Some(Ok(Message::Text(room_id))) = receiver.next().await {
+    if let Some(Ok(Message::Text(room_id))) = receiver.next().await {
         channel = connect_channel(room_id);
-        break;
     }
 
     let (tx, mut rx) = channel.unwrap();
@@ -78,13 +107,29 @@ async fn game_handler(socket: WebSocket) {
                 tracing::info!("{json_event}");
 
                 let result = match Event::from_json(&json_event) {
-                    Ok(Event::PlayMove(data)) => play_move.execute(data).await,
-                    Ok(Event::Disconnect(data)) => disconnect.execute(data).await,
+                    Ok(Event::PlayMove(data)) => play_move.execute(data).await.map(|()| json_event),
+                    Ok(Event::Disconnect(data)) => {
+                        disconnect.execute(data).await.map(|()| json_event)
+                    }
+                    Ok(Event::OfferRematch(data)) => match rematch_info(data, user_id) {
+                        Ok(info) => rematch
+                            .offer(info.clone())
+                            .await
+                            .map(|()| Event::OfferRematch(info).json()),
+                        Err(err_msg) => Err(err_msg),
+                    },
+                    Ok(Event::AcceptRematch(data)) => match rematch_info(data, user_id) {
+                        Ok(info) => rematch
+                            .accept(info.clone())
+                            .await
+                            .map(|()| Event::AcceptRematch(info).json()),
+                        Err(err_msg) => Err(err_msg),
+                    },
                     _ => Err(String::from("Could not build event!")),
                 };
 
                 match result {
-                    Ok(()) => tx.send(json_event),
+                    Ok(relayed) => tx.send(relayed),
                     Err(err_msg) => tx.send(err_msg),
                 }
                 .unwrap();
@@ -1,23 +1,21 @@
 use crate::http::Result;
-use crate::models::{Event, MoveInfo, RoomsManagerTrait};
-use crate::repositories::{GameRepositoryTrait, WalletRepositoryTrait};
+use crate::models::{Event, MoveInfo, Rake, RoomsManagerTrait, Termination};
+use crate::repositories::{GameRepositoryTrait, Settlement};
 
 use super::disconnect_service::resolve_bet;
 
-pub struct PlayMoveService<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait> {
+pub struct PlayMoveService<R: GameRepositoryTrait, M: RoomsManagerTrait> {
     game_repository: R,
     rooms_manager: M,
-    wallet_repository: W,
+    rake: Rake,
 }
 
-impl<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait>
-    PlayMoveService<R, M, W>
-{
-    pub fn new(game_repository: R, rooms_manager: M, wallet_repository: W) -> Self {
+impl<R: GameRepositoryTrait, M: RoomsManagerTrait> PlayMoveService<R, M> {
+    pub fn new(game_repository: R, rooms_manager: M, rake: Rake) -> Self {
         Self {
             game_repository,
             rooms_manager,
-            wallet_repository,
+            rake,
         }
     }
 
@@ -31,12 +29,31 @@ impl<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait>
         if let Some(new_game_state) = game.check_move(&info.move_played)? {
             game.state = new_game_state;
 
+            if let Some(termination) = Termination::from_move(new_game_state) {
+                let settlement = Settlement {
+                    last_move: Some(info.move_played),
+                    ..resolve_bet(&game, termination, &self.rake)?
+                };
+                let result = settlement.result()?;
+
+                if !self.game_repository.settle_game(settlement).await? {
+                    return Err(String::from("Game is already over!"));
+                }
+
+                self.rooms_manager.finish_room(info.game_id);
+
+                let room = self.rooms_manager.get_room(info.game_id)?;
+
+                room.relay_event(Event::GameChangeState(new_game_state));
+                room.relay_event(Event::GameResult(result));
+
+                return Ok(());
+            }
+
             self.game_repository
                 .update_state(info.game_id, new_game_state)
                 .await?;
 
-            resolve_bet(&self.wallet_repository, &game).await?;
-
             self.rooms_manager
                 .get_room(info.game_id)?
                 .relay_event(Event::GameChangeState(new_game_state));
@@ -54,15 +71,14 @@ impl<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait>
 mod tests {
     use super::*;
     use crate::http::Error;
-    use crate::models::{Game, GameState, MockRoomsManagerTrait, MoveInfo};
-    use crate::repositories::{MockGameRepositoryTrait, MockWalletRepositoryTrait};
+    use crate::models::{Game, GameState, MockRoomsManagerTrait, MoveInfo, Room, Sats};
+    use crate::repositories::MockGameRepositoryTrait;
     use uuid::Uuid;
 
     #[tokio::test]
     async fn test_not_player_try_play_move() {
         let mut mock_game_repository = MockGameRepositoryTrait::new();
         let mock_rooms_manager = MockRoomsManagerTrait::new();
-        let mock_wallet_repository = MockWalletRepositoryTrait::new();
 
         mock_game_repository.expect_get_game().returning(|_| {
             Ok(Game {
@@ -70,15 +86,13 @@ mod tests {
                 white_player: Uuid::new_v4(),
                 black_player: Uuid::new_v4(),
                 state: GameState::Waiting,
-                bet_value: 0,
+                bet_value: Sats::ZERO,
                 moves: vec![],
+                ..Default::default()
             })
         });
-        let service = PlayMoveService::new(
-            mock_game_repository,
-            mock_rooms_manager,
-            mock_wallet_repository,
-        );
+        let service =
+            PlayMoveService::new(mock_game_repository, mock_rooms_manager, Rake::default());
 
         let input = MoveInfo {
             player_id: Uuid::new_v4(),
@@ -96,7 +110,6 @@ mod tests {
     async fn test_not_turned_player_try_play_move() {
         let mut mock_game_repository = MockGameRepositoryTrait::new();
         let mock_rooms_manager = MockRoomsManagerTrait::new();
-        let mock_wallet_repository = MockWalletRepositoryTrait::new();
 
         mock_game_repository.expect_get_game().returning(|_| {
             Ok(Game {
@@ -107,11 +120,8 @@ mod tests {
             })
         });
 
-        let service = PlayMoveService::new(
-            mock_game_repository,
-            mock_rooms_manager,
-            mock_wallet_repository,
-        );
+        let service =
+            PlayMoveService::new(mock_game_repository, mock_rooms_manager, Rake::default());
 
         let input = MoveInfo {
             player_id: uuid::uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a"),
@@ -129,7 +139,6 @@ mod tests {
     async fn test_right_player_play_move() {
         let mut mock_game_repository = MockGameRepositoryTrait::new();
         let mock_rooms_manager = MockRoomsManagerTrait::new();
-        let mock_wallet_repository = MockWalletRepositoryTrait::new();
 
         mock_game_repository.expect_get_game().returning(|_| {
             Ok(Game {
@@ -145,11 +154,8 @@ mod tests {
             .once()
             .returning(|_, _| Ok(()));
 
-        let service = PlayMoveService::new(
-            mock_game_repository,
-            mock_rooms_manager,
-            mock_wallet_repository,
-        );
+        let service =
+            PlayMoveService::new(mock_game_repository, mock_rooms_manager, Rake::default());
 
         let input = MoveInfo {
             player_id: uuid::uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a"),
@@ -166,7 +172,6 @@ mod tests {
     async fn test_game_not_found() {
         let mut mock_game_repository = MockGameRepositoryTrait::new();
         let mock_rooms_manager = MockRoomsManagerTrait::new();
-        let mock_wallet_repository = MockWalletRepositoryTrait::new();
 
         mock_game_repository
             .expect_get_game()
@@ -183,15 +188,67 @@ mod tests {
             move_played: String::from("e4"),
         };
 
-        let service = PlayMoveService::new(
-            mock_game_repository,
-            mock_rooms_manager,
-            mock_wallet_repository,
-        );
+        let service =
+            PlayMoveService::new(mock_game_repository, mock_rooms_manager, Rake::default());
 
         let result = service.execute(input).await;
 
         assert!(result.is_err());
         assert_eq!(result, Err(String::from("Item not found!")));
     }
+
+    #[tokio::test]
+    async fn test_checkmate_settles_game() {
+        let mut mock_game_repository = MockGameRepositoryTrait::new();
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+
+        mock_game_repository.expect_get_game().returning(|id| {
+            Ok(Game {
+                id,
+                white_player: uuid::uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a"),
+                black_player: Uuid::new_v4(),
+                bet_value: Sats::new(10),
+                state: GameState::Running,
+                moves: ["e4", "e5", "Bc4", "a6", "Qf3", "a5"]
+                    .map(String::from)
+                    .to_vec(),
+                ..Default::default()
+            })
+        });
+
+        mock_game_repository
+            .expect_settle_game()
+            .once()
+            .withf(|settlement| {
+                settlement.state == GameState::WhiteWin
+                    && settlement.termination == Termination::Checkmate
+                    && settlement.last_move == Some(String::from("Qxf7#"))
+                    && settlement.transfers.len() == 1
+            })
+            .returning(|_| Ok(true));
+
+        mock_game_repository.expect_record_move().never();
+
+        mock_rooms_manager
+            .expect_finish_room()
+            .once()
+            .return_const(());
+        mock_rooms_manager
+            .expect_get_room()
+            .once()
+            .returning(|_| Ok(Room::new(String::from("w-10-0-10"))));
+
+        let service =
+            PlayMoveService::new(mock_game_repository, mock_rooms_manager, Rake::default());
+
+        let input = MoveInfo {
+            player_id: uuid::uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a"),
+            game_id: Uuid::new_v4(),
+            move_played: String::from("Qxf7#"),
+        };
+
+        let result = service.execute(input).await;
+
+        assert!(result.is_ok());
+    }
 }
@@ -0,0 +1,355 @@
+use crate::http::Result;
+use crate::models::{
+    Event, Game, GameRequest, GameState, PlayerColor, RematchGameInfo, RematchInfo, Room,
+    RoomsManagerTrait, StakeMode,
+};
+use crate::repositories::{
+    GamblingLimitRepositoryTrait, GameRepositoryTrait, SaveTransfer, WalletRepositoryTrait,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+pub struct RematchService<
+    R: GameRepositoryTrait,
+    M: RoomsManagerTrait,
+    W: WalletRepositoryTrait,
+    G: GamblingLimitRepositoryTrait,
+> {
+    game_repository: R,
+    rooms_manager: M,
+    wallet_repository: W,
+    gambling_limit_repository: G,
+    max_concurrent_games: usize,
+}
+
+impl<
+        R: GameRepositoryTrait,
+        M: RoomsManagerTrait,
+        W: WalletRepositoryTrait,
+        G: GamblingLimitRepositoryTrait,
+    > RematchService<R, M, W, G>
+{
+    pub fn new(
+        game_repository: R,
+        rooms_manager: M,
+        wallet_repository: W,
+        gambling_limit_repository: G,
+        max_concurrent_games: usize,
+    ) -> Self {
+        Self {
+            game_repository,
+            rooms_manager,
+            wallet_repository,
+            gambling_limit_repository,
+            max_concurrent_games,
+        }
+    }
+
+    /// The finished game of `info`, which the player took part in.
+    async fn finished_game(&self, info: &RematchInfo) -> Result<Game, String> {
+        let game = self.game_repository.get_game(info.game_id).await?;
+
+        game.get_player_color(info.player_id)?;
+
+        if matches!(game.state, GameState::Waiting | GameState::Running) {
+            return Err(String::from("Game is not over yet!"));
+        }
+
+        Ok(game)
+    }
+
+    pub async fn offer(&self, info: RematchInfo) -> Result<(), String> {
+        self.finished_game(&info).await?;
+        self.rooms_manager
+            .offer_rematch(info.game_id, info.player_id)?;
+
+        Ok(())
+    }
+
+    /// Starts the rematch the opponent offered, with the same time control
+    /// and stake and the colours swapped, and sends both players to it.
+    pub async fn accept(&self, info: RematchInfo) -> Result<(), String> {
+        let game = self.finished_game(&info).await?;
+        let opponent = match game.get_player_color(info.player_id)? {
+            PlayerColor::White => game.black_player,
+            PlayerColor::Black => game.white_player,
+        };
+        let room = self.rooms_manager.get_room(info.game_id)?;
+        let game_request = GameRequest::from_str(&room.request_key)?;
+
+        if game_request.stake_mode == StakeMode::Hold {
+            return Err(String::from(
+                "Held stakes need new stake holds, pair again for a rematch!",
+            ));
+        }
+
+        if room.rematch_offer != Some(opponent) {
+            return Err(String::from("Your opponent has not offered a rematch!"));
+        }
+
+        let stake = game_request.bet_value.to_msat()?;
+
+        for player_id in [game.white_player, game.black_player] {
+            self.gambling_limit_repository
+                .get_protection(player_id)
+                .await?
+                .check_stake(stake, Utc::now())?;
+
+            if self.wallet_repository.get_balance(player_id).await? < stake {
+                return Err(String::from("A player doesn't have money enough!"));
+            }
+
+            if self.rooms_manager.count_games(player_id) >= self.max_concurrent_games {
+                return Err(String::from(
+                    "A player is already playing as many games as allowed!",
+                ));
+            }
+        }
+
+        // The offer is only consumed once both players can play, and taking
+        // it means a concurrent accept cannot start the rematch too.
+        if !self
+            .rooms_manager
+            .take_rematch_offer(info.game_id, opponent)
+        {
+            return Err(String::from("Your opponent has not offered a rematch!"));
+        }
+
+        let new_game_id = Uuid::new_v4();
+        let mut new_room = Room::new(room.request_key.clone());
+
+        new_room.add_player(game.black_player, Some(PlayerColor::White))?;
+        new_room.add_player(game.white_player, Some(PlayerColor::Black))?;
+
+        let started = self
+            .game_repository
+            .start_game(
+                Game {
+                    id: new_game_id,
+                    white_player: game.black_player,
+                    black_player: game.white_player,
+                    bet_value: game.bet_value,
+                    stake_mode: game.stake_mode,
+                    category: game.category,
+                    ..Default::default()
+                },
+                vec![
+                    SaveTransfer::stake(game.black_player, new_game_id, stake),
+                    SaveTransfer::stake(game.white_player, new_game_id, stake),
+                ],
+                vec![],
+            )
+            .await;
+
+        if let Err(error) = started {
+            // The offer stands, so the rematch can be accepted again.
+            let _ = self.rooms_manager.offer_rematch(info.game_id, opponent);
+
+            return Err(error.into());
+        }
+
+        self.rooms_manager.open_room(new_game_id, new_room);
+        room.relay_event(Event::RematchStarted(RematchGameInfo {
+            game_id: info.game_id,
+            new_game_id,
+        }));
+        self.rooms_manager.remove_room(info.game_id);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MockRoomsManagerTrait, Msat, PlayerProtection, Sats};
+    use crate::repositories::{
+        MockGamblingLimitRepositoryTrait, MockGameRepositoryTrait, MockWalletRepositoryTrait,
+    };
+    use uuid::uuid;
+
+    const WHITE_ID: Uuid = uuid!("5d6cc3e8-8eec-4dab-881f-fddfb831cc41");
+    const BLACK_ID: Uuid = uuid!("9a4d5d1e-52a1-4c3b-a0f6-4f1f5a7f2b11");
+    const GAME_ID: Uuid = uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a");
+
+    fn finished_game(state: GameState) -> MockGameRepositoryTrait {
+        let mut mock_game_repository = MockGameRepositoryTrait::new();
+
+        mock_game_repository.expect_get_game().returning(move |_| {
+            Ok(Game {
+                id: GAME_ID,
+                white_player: WHITE_ID,
+                black_player: BLACK_ID,
+                bet_value: Sats::new(10),
+                state,
+                ..Default::default()
+            })
+        });
+
+        mock_game_repository
+    }
+
+    #[tokio::test]
+    async fn test_accepted_rematch_swaps_colours() {
+        let mut mock_game_repository = finished_game(GameState::WhiteWin);
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_rooms_manager.expect_get_room().returning(|_| {
+            Ok(Room {
+                rematch_offer: Some(WHITE_ID),
+                ..Room::new(String::from("w-10-0-10"))
+            })
+        });
+        mock_rooms_manager
+            .expect_take_rematch_offer()
+            .once()
+            .withf(|_, offered_by| *offered_by == WHITE_ID)
+            .return_const(true);
+        mock_rooms_manager.expect_count_games().returning(|_| 0);
+        mock_rooms_manager
+            .expect_open_room()
+            .once()
+            .withf(|_, room| {
+                room.white_player == Some(BLACK_ID) && room.black_player == Some(WHITE_ID)
+            })
+            .return_const(());
+        mock_rooms_manager
+            .expect_remove_room()
+            .once()
+            .withf(|id| *id == GAME_ID)
+            .return_const(());
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(10_000_000)));
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .returning(|_| Ok(PlayerProtection::default()));
+
+        mock_game_repository
+            .expect_start_game()
+            .once()
+            .withf(|game, transfers, _| {
+                game.white_player == BLACK_ID
+                    && game.black_player == WHITE_ID
+                    && game.bet_value == Sats::new(10)
+                    && transfers.len() == 2
+            })
+            .returning(|_, _, _| Ok(()));
+
+        let service = RematchService::new(
+            mock_game_repository,
+            mock_rooms_manager,
+            mock_wallet_repository,
+            mock_gambling_limit_repository,
+            1,
+        );
+
+        let result = service
+            .accept(RematchInfo {
+                game_id: GAME_ID,
+                player_id: BLACK_ID,
+            })
+            .await;
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_rematch_offer_stays_when_player_cannot_pay() {
+        let mut mock_game_repository = finished_game(GameState::WhiteWin);
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_rooms_manager.expect_get_room().returning(|_| {
+            Ok(Room {
+                rematch_offer: Some(WHITE_ID),
+                ..Room::new(String::from("w-10-0-10"))
+            })
+        });
+        mock_rooms_manager.expect_count_games().returning(|_| 0);
+        mock_rooms_manager.expect_take_rematch_offer().never();
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::ZERO));
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .returning(|_| Ok(PlayerProtection::default()));
+
+        mock_game_repository.expect_start_game().never();
+
+        let service = RematchService::new(
+            mock_game_repository,
+            mock_rooms_manager,
+            mock_wallet_repository,
+            mock_gambling_limit_repository,
+            1,
+        );
+
+        let result = service
+            .accept(RematchInfo {
+                game_id: GAME_ID,
+                player_id: BLACK_ID,
+            })
+            .await;
+
+        assert_eq!(
+            result,
+            Err(String::from("A player doesn't have money enough!"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_rematch_while_playing() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+
+        mock_rooms_manager.expect_offer_rematch().never();
+
+        let service = RematchService::new(
+            finished_game(GameState::Running),
+            mock_rooms_manager,
+            MockWalletRepositoryTrait::new(),
+            MockGamblingLimitRepositoryTrait::new(),
+            1,
+        );
+
+        let result = service
+            .offer(RematchInfo {
+                game_id: GAME_ID,
+                player_id: WHITE_ID,
+            })
+            .await;
+
+        assert_eq!(result, Err(String::from("Game is not over yet!")));
+    }
+
+    #[tokio::test]
+    async fn test_outsider_cannot_accept_rematch() {
+        let mut mock_game_repository = finished_game(GameState::WhiteWin);
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+
+        mock_rooms_manager.expect_take_rematch_offer().never();
+        mock_game_repository.expect_start_game().never();
+
+        let service = RematchService::new(
+            mock_game_repository,
+            mock_rooms_manager,
+            MockWalletRepositoryTrait::new(),
+            MockGamblingLimitRepositoryTrait::new(),
+            1,
+        );
+
+        let result = service
+            .accept(RematchInfo {
+                game_id: GAME_ID,
+                player_id: Uuid::new_v4(),
+            })
+            .await;
+
+        assert_eq!(result, Err(String::from("You are not playing this game!")));
+    }
+}
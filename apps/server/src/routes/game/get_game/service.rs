@@ -42,12 +42,12 @@ impl<R: GameRepositoryTrait, M: RoomsManagerTrait> GetGameService<R, M> {
                     _ => Err(Error::InternalServerError),
                 }?;
 
-                return Ok(GameWithPlayers {
+                Ok(GameWithPlayers {
                     id: room_id,
                     white_player,
                     black_player,
                     ..Default::default()
-                });
+                })
             }
             _ => self.game_repository.get_game_with_players(room_id).await,
         }
@@ -57,7 +57,7 @@ impl<R: GameRepositoryTrait, M: RoomsManagerTrait> GetGameService<R, M> {
 #[cfg(test)]
 mod tests {
     use crate::{
-        models::{MockRoomsManagerTrait, Room},
+        models::{MockRoomsManagerTrait, Room, Sats},
         repositories::MockGameRepositoryTrait,
     };
     use tokio::sync::broadcast;
@@ -75,6 +75,8 @@ mod tests {
                 request_key: String::from("w-10-0-0"),
                 white_player: Some(uuid!("7e72d61a-c7d0-4260-94ab-7c5a3a41ac72")),
                 black_player: None,
+                rematch_offer: None,
+                finished: false,
                 tx: broadcast::channel(100).0,
             })
         });
@@ -115,6 +117,8 @@ mod tests {
                 request_key: String::from("w-10-0-0"),
                 white_player: Some(uuid!("7e72d61a-c7d0-4260-94ab-7c5a3a41ac72")),
                 black_player: Some(uuid!("8734278b-1363-42d1-8c24-c13214d23b0b")),
+                rematch_offer: None,
+                finished: false,
                 tx: broadcast::channel(100).0,
             })
         });
@@ -134,7 +138,7 @@ mod tests {
                         id: uuid!("8734278b-1363-42d1-8c24-c13214d23b0b"),
                         ..Default::default()
                     },
-                    bet_value: 10,
+                    bet_value: Sats::new(10),
                     ..Default::default()
                 })
             });
@@ -156,6 +160,6 @@ mod tests {
             uuid!("8734278b-1363-42d1-8c24-c13214d23b0b")
         );
 
-        assert_eq!(result.bet_value, 10);
+        assert_eq!(result.bet_value, Sats::new(10));
     }
 }
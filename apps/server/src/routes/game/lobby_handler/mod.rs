@@ -0,0 +1,77 @@
+use crate::models::{AuthUser, Event, Lobby, LobbyTrait};
+use aide::{transform::TransformOperation, NoApi};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        WebSocketUpgrade,
+    },
+    response::IntoResponse,
+};
+use futures::SinkExt;
+use futures::StreamExt;
+use uuid::Uuid;
+
+use super::cancel_seek;
+
+pub async fn route(
+    auth_user: AuthUser,
+    NoApi(ws): NoApi<WebSocketUpgrade>,
+) -> NoApi<impl IntoResponse> {
+    NoApi(ws.on_upgrade(move |socket| lobby_handler(socket, auth_user.user_id)))
+}
+
+/// Relays the events of the player's seeks and takes their lobby commands.
+async fn lobby_handler(socket: WebSocket, player_id: Uuid) {
+    let cancel_seek = cancel_seek::resource();
+
+    let (mut sender, mut receiver) = socket.split();
+    let (tx, mut rx) = Lobby::new().join(player_id);
+
+    let mut relay_messages = tokio::spawn(async move {
+        while let Ok(msg) = rx.recv().await {
+            sender.send(Message::Text(msg)).await.unwrap_or(());
+        }
+    });
+
+    let mut process_received_messages = tokio::spawn(async move {
+        while let Some(Ok(Message::Text(json_event))) = receiver.next().await {
+            let result = match Event::from_json(&json_event) {
+                Ok(Event::CancelSeek) => cancel_seek
+                    .execute(player_id)
+                    .await
+                    .map(|_| ())
+                    .map_err(String::from),
+                _ => Err(String::from("Could not build event!")),
+            };
+
+            if let Err(err_msg) = result {
+                tx.send(err_msg).unwrap_or(0);
+            }
+        }
+    });
+
+    let relayed_all = tokio::select! {
+        _ = (&mut process_received_messages) => {
+            relay_messages.abort();
+            false
+        }
+        _ = (&mut relay_messages) => {
+            process_received_messages.abort();
+            true
+        }
+    };
+
+    // The relay holds the receiver of this socket, which has to be gone
+    // before the channel is checked for other open sockets.
+    if !relayed_all {
+        relay_messages.await.unwrap_or(());
+    }
+
+    Lobby::new().leave(player_id);
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Game")
+        .description("Websocket for the events of the player's seeks")
+        .hidden(true)
+}
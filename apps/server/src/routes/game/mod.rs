@@ -3,19 +3,39 @@ use aide::axum::{
     ApiRouter,
 };
 
+pub(crate) mod cancel_seek;
+mod create_stake_hold;
 mod game_handler;
 mod get_game;
-mod pairing_game;
+mod lobby_handler;
+pub(crate) mod pairing_game;
 
 pub fn router() -> ApiRouter {
     ApiRouter::new()
         .api_route(
             "/game/pairing",
-            post_with(pairing_game::route, pairing_game::docs),
+            post_with(pairing_game::route, pairing_game::docs)
+                .delete_with(cancel_seek::route, cancel_seek::docs),
+        )
+        .api_route(
+            "/game/private",
+            post_with(pairing_game::private::route, pairing_game::private::docs),
+        )
+        .api_route(
+            "/game/invite/:token",
+            post_with(pairing_game::invite::route, pairing_game::invite::docs),
+        )
+        .api_route(
+            "/game/stake-hold",
+            post_with(create_stake_hold::route, create_stake_hold::docs),
         )
         .api_route("/game/:id", get_with(get_game::route, get_game::docs))
         .api_route(
             "/game/ws",
             get_with(game_handler::route, game_handler::docs),
         )
+        .api_route(
+            "/game/lobby/ws",
+            get_with(lobby_handler::route, lobby_handler::docs),
+        )
 }
@@ -0,0 +1,40 @@
+use crate::http::{GenericError, Result};
+use crate::models::AuthUser;
+use aide::transform::TransformOperation;
+use axum::{extract::Path, Json};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{resource, GameId};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InviteToken {
+    token: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct JoinInviteBody {
+    /// Payment hash of a paid stake hold, for games with held stakes.
+    stake_hold: Option<String>,
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Path(InviteToken { token }): Path<InviteToken>,
+    Json(payload): Json<JoinInviteBody>,
+) -> Result<Json<GameId>> {
+    let game_id = resource()
+        .join_private(auth_user.user_id, &token, payload.stake_hold)
+        .await?;
+
+    Ok(Json(GameId { game_id }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Private Games")
+        .description("Take the free seat of a private game through its invite link")
+        .response::<200, Json<GameId>>()
+        .response::<400, Json<GenericError>>()
+        .response::<404, Json<GenericError>>()
+        .response::<409, Json<GenericError>>()
+}
@@ -1,8 +1,14 @@
+use crate::bad_req;
 use crate::http::{Error, Result};
-use crate::models::{AuthUser, GameRequest, RoomsManager};
-use crate::repositories::{GameRepository, WalletRepository};
+use crate::models::{AuthUser, GameRequest, GameRequestSpec, RoomsManager};
+use crate::repositories::{
+    GamblingLimitRepository, GameRepository, RatingRepository, StakeHoldRepository, UserRepository,
+    UserRepositoryTrait, WalletRepository,
+};
+use crate::states::max_concurrent_games;
 use aide::transform::TransformOperation;
 use axum::Json;
+use reqwest::Client;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use service::PairingGameService;
@@ -10,6 +16,8 @@ use uuid::Uuid;
 
 use crate::http::GenericError;
 
+pub mod invite;
+pub mod private;
 mod service;
 
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -17,16 +25,33 @@ pub struct GameId {
     pub game_id: Uuid,
 }
 
-#[derive(Serialize, Deserialize, JsonSchema)]
+/// Either a legacy `key` or a structured `request`.
+#[derive(Deserialize, JsonSchema)]
 pub struct GameRequestBody {
-    key: String,
+    key: Option<String>,
+    request: Option<GameRequestSpec>,
+    /// Payment hash of a paid stake hold, for keys ending in `-h`.
+    stake_hold: Option<String>,
 }
 
-fn resource() -> PairingGameService<GameRepository, RoomsManager, WalletRepository> {
+fn resource() -> PairingGameService<
+    GameRepository,
+    RoomsManager,
+    WalletRepository,
+    StakeHoldRepository,
+    Client,
+    GamblingLimitRepository,
+    RatingRepository,
+> {
     PairingGameService::new(
         GameRepository::new(),
         RoomsManager::new(),
         WalletRepository::new(),
+        StakeHoldRepository::new(),
+        Client::new(),
+        GamblingLimitRepository::new(),
+        RatingRepository::new(),
+        max_concurrent_games::get(),
     )
 }
 
@@ -36,17 +61,56 @@ pub async fn route(
 ) -> Result<Json<GameId>> {
     let pairing_service = resource();
 
-    let game_request = GameRequest::from_str(&payload.key).map_err(|_| Error::BadRequest {
-        message: String::from("Invalid game request key"),
-    })?;
+    let game_request = parse_game_request(&auth_user, payload.key, payload.request).await?;
 
     let game_id = pairing_service
-        .execute(auth_user.user_id, game_request)
+        .execute(auth_user.user_id, game_request, payload.stake_hold)
         .await?;
 
     Ok(Json(GameId { game_id }))
 }
 
+async fn parse_game_request(
+    auth_user: &AuthUser,
+    key: Option<String>,
+    request: Option<GameRequestSpec>,
+) -> Result<GameRequest> {
+    match (key, request) {
+        (Some(key), None) => GameRequest::from_str(&key).map_err(|_| Error::BadRequest {
+            message: String::from("Invalid game request key"),
+        }),
+        (None, Some(spec)) => {
+            if let Some(message) = spec.validation_message() {
+                return Err(Error::BadRequest { message });
+            }
+
+            let opponent = match &spec.opponent {
+                Some(username) => Some(resolve_opponent(auth_user, username).await?),
+                None => None,
+            };
+
+            GameRequest::from_spec(&spec, opponent)
+        }
+        _ => bad_req!("Send either a game request key or a request"),
+    }
+}
+
+async fn resolve_opponent(auth_user: &AuthUser, username: &str) -> Result<Uuid> {
+    let opponent = match UserRepository::new()
+        .find_by_username(username.to_string())
+        .await
+    {
+        Err(Error::NotFound { .. }) => return bad_req!("Opponent not found"),
+        result => result?,
+    };
+
+    if opponent.id == auth_user.user_id {
+        return bad_req!("You cannot challenge yourself");
+    }
+
+    Ok(opponent.id)
+}
+
 pub fn docs(op: TransformOperation) -> TransformOperation {
     op.tag("Quick Pairing")
         .description("Quick Pair players to play")
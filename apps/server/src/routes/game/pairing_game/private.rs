@@ -0,0 +1,42 @@
+use crate::http::{GenericError, Result};
+use crate::models::AuthUser;
+use aide::transform::TransformOperation;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{parse_game_request, resource, GameRequestBody};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PrivateGame {
+    pub game_id: Uuid,
+    /// Token of the invite link. Whoever opens it first takes the free seat.
+    pub invite_token: String,
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Json(payload): Json<GameRequestBody>,
+) -> Result<Json<PrivateGame>> {
+    let game_request = parse_game_request(&auth_user, payload.key, payload.request).await?;
+
+    let (game_id, invite_token) = resource()
+        .create_private(auth_user.user_id, game_request, payload.stake_hold)
+        .await?;
+
+    Ok(Json(PrivateGame {
+        game_id,
+        invite_token,
+    }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Private Games")
+        .description(
+            "Open a private game that is only joined through its invite link. The invite expires after INVITE_TTL_MINUTES, a day by default",
+        )
+        .response::<200, Json<PrivateGame>>()
+        .response::<400, Json<GenericError>>()
+        .response::<409, Json<GenericError>>()
+}
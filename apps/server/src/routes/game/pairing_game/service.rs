@@ -1,92 +1,569 @@
-use crate::http::{Error, Result};
-use crate::internal_error;
-use crate::models::{Game, GameRequest, PairedGame, RoomsManagerTrait};
-use crate::repositories::{GameRepositoryTrait, SaveOutgoing, WalletRepositoryTrait};
+use crate::http::{Error, HoldInvoiceState, LightningClient, Result};
+use crate::models::{
+    Game, GameRequest, Msat, PairedGame, PlayerColor, RoomsManagerTrait, Seek, StakeMode,
+};
+use crate::repositories::{
+    GamblingLimitRepositoryTrait, GameRepositoryTrait, HoldStatus, RatingRepositoryTrait,
+    SaveTransfer, StakeHoldRepositoryTrait, WalletRepositoryTrait,
+};
+use crate::{bad_req, internal_error};
+use chrono::Utc;
 use uuid::Uuid;
 
 pub struct PairingGameService<
     R: GameRepositoryTrait,
     M: RoomsManagerTrait,
     W: WalletRepositoryTrait,
+    H: StakeHoldRepositoryTrait,
+    L: LightningClient,
+    G: GamblingLimitRepositoryTrait,
+    Q: RatingRepositoryTrait,
 > {
     game_repository: R,
     rooms_manager: M,
     wallet_repository: W,
+    stake_hold_repository: H,
+    lightning_client: L,
+    gambling_limit_repository: G,
+    rating_repository: Q,
+    max_concurrent_games: usize,
 }
 
-impl<R: GameRepositoryTrait, M: RoomsManagerTrait, W: WalletRepositoryTrait>
-    PairingGameService<R, M, W>
+impl<
+        R: GameRepositoryTrait,
+        M: RoomsManagerTrait,
+        W: WalletRepositoryTrait,
+        H: StakeHoldRepositoryTrait,
+        L: LightningClient,
+        G: GamblingLimitRepositoryTrait,
+        Q: RatingRepositoryTrait,
+    > PairingGameService<R, M, W, H, L, G, Q>
 {
-    pub fn new(game_repository: R, rooms_manager: M, wallet_repository: W) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        game_repository: R,
+        rooms_manager: M,
+        wallet_repository: W,
+        stake_hold_repository: H,
+        lightning_client: L,
+        gambling_limit_repository: G,
+        rating_repository: Q,
+        max_concurrent_games: usize,
+    ) -> Self {
         Self {
             game_repository,
             rooms_manager,
             wallet_repository,
+            stake_hold_repository,
+            lightning_client,
+            gambling_limit_repository,
+            rating_repository,
+            max_concurrent_games,
         }
     }
 
-    pub async fn execute(&self, player_id: Uuid, game_request: GameRequest) -> Result<Uuid> {
-        let balance = self.wallet_repository.get_balance(player_id).await?;
+    /// Checks that the hold invoice of `payment_hash` locks the stake and
+    /// reserves it for this pairing.
+    async fn claim_stake_hold(
+        &self,
+        player_id: Uuid,
+        payment_hash: &str,
+        stake: Msat,
+    ) -> Result<()> {
+        let hold = self
+            .stake_hold_repository
+            .get_stake_hold(payment_hash.to_string())
+            .await?;
 
-        if balance < game_request.bet_value {
-            return Err(Error::BadRequest {
-                message: String::from("You don't have money enough! Deposit more sats."),
+        if hold.user_id != player_id {
+            return Err(Error::NotFound {
+                message: String::from("Stake hold not found!"),
             });
         }
 
-        let paired_game = self.rooms_manager.pair_new_player(&game_request.key);
+        if hold.amount != stake {
+            return bad_req!("Stake hold does not match the bet value");
+        }
 
-        let paired_game_id = match paired_game {
-            PairedGame::NewGame(game_id) => {
-                self.rooms_manager.create_room(game_id, &game_request.key);
-                self.rooms_manager
-                    .add_player(game_id, player_id, game_request.player_color)?;
+        if hold.status != HoldStatus::Pending {
+            return bad_req!("Stake hold was already used");
+        }
+
+        let state = self
+            .lightning_client
+            .get_hold_invoice(payment_hash.to_string())
+            .await?;
+
+        if state != HoldInvoiceState::Accepted {
+            return bad_req!("Stake hold has not been paid yet");
+        }
+
+        if !self
+            .stake_hold_repository
+            .claim_stake_hold(player_id, payment_hash.to_string())
+            .await?
+        {
+            return bad_req!("Stake hold was already used");
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the player can afford the stake of `game_request`, and
+    /// reserves their hold invoice for it if the stake is held.
+    async fn reserve_stake(
+        &self,
+        player_id: Uuid,
+        game_request: &GameRequest,
+        stake_hold: &Option<String>,
+    ) -> Result<()> {
+        let stake = game_request.bet_value.to_msat()?;
 
-                game_id
+        match (game_request.stake_mode, stake_hold) {
+            (StakeMode::Hold, _) if !self.lightning_client.supports_hold_invoices() => {
+                return bad_req!("Non-custodial stakes are not available on this server");
             }
+            (StakeMode::Custodial, _) => {
+                let balance = self.wallet_repository.get_balance(player_id).await?;
 
-            PairedGame::ExistingGame(game_id) => {
-                self.rooms_manager.add_player(game_id, player_id, None)?;
+                if balance < stake {
+                    return Err(Error::BadRequest {
+                        message: String::from("You don't have money enough! Deposit more sats."),
+                    });
+                }
+            }
+            (StakeMode::Hold, Some(payment_hash)) => {
+                self.claim_stake_hold(player_id, payment_hash, stake)
+                    .await?;
+            }
+            (StakeMode::Hold, None) => {
+                return bad_req!("Pay a stake hold invoice to join this game");
+            }
+        }
 
-                let room = self.rooms_manager.get_room(game_id).unwrap();
+        Ok(())
+    }
 
-                let game = Game {
-                    id: game_id,
-                    white_player: room.white_player.ok_or(internal_error!())?,
-                    black_player: room.black_player.ok_or(internal_error!())?,
-                    bet_value: game_request.bet_value,
-                    ..Default::default()
-                };
+    /// Frees the hold invoice reserved by `reserve_stake` when no game took
+    /// it, so the player can pair with it again.
+    async fn release_stake(&self, game_request: &GameRequest, stake_hold: &Option<String>) {
+        let Some(payment_hash) = stake_hold else {
+            return;
+        };
+
+        if game_request.stake_mode != StakeMode::Hold {
+            return;
+        }
+
+        if let Err(error) = self
+            .stake_hold_repository
+            .release_stake_hold(payment_hash.clone())
+            .await
+        {
+            tracing::warn!("Could not release stake hold {payment_hash}: {error}");
+        }
+    }
 
-                self.game_repository.save_game(game).await?;
+    /// Moves the reserved stake of the player into the game.
+    async fn take_stake(
+        &self,
+        player_id: Uuid,
+        game_id: Uuid,
+        game_request: &GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<()> {
+        match stake_hold {
+            Some(payment_hash) if game_request.stake_mode == StakeMode::Hold => {
+                self.stake_hold_repository
+                    .assign_stake_hold(payment_hash, game_id)
+                    .await
+            }
+            _ => {
+                self.wallet_repository
+                    .save_transfer(SaveTransfer::stake(
+                        player_id,
+                        game_id,
+                        game_request.bet_value.to_msat()?,
+                    ))
+                    .await?;
 
-                game_id
+                Ok(())
             }
+        }
+    }
+
+    /// Takes the stake of a seek waiting in a new room, so it can be paired.
+    /// The room is closed if the stake cannot be taken.
+    async fn stake_seek(
+        &self,
+        player_id: Uuid,
+        game_id: Uuid,
+        game_request: &GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<()> {
+        if let Err(error) = self
+            .take_stake(player_id, game_id, game_request, stake_hold)
+            .await
+        {
+            self.rooms_manager.abandon_seek(game_id);
+
+            return Err(error);
+        }
+
+        self.rooms_manager.stake_seek(game_id);
+
+        Ok(())
+    }
+
+    /// Saves the game of a room whose free seat the player just took, with
+    /// their stake. The seat is freed for another player if that fails.
+    async fn start_game(
+        &self,
+        player_id: Uuid,
+        game_id: Uuid,
+        waiting: Seek,
+        game_request: &GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<()> {
+        let result = self
+            .save_started_game(player_id, game_id, game_request, stake_hold)
+            .await;
+
+        if result.is_err() {
+            self.rooms_manager.unpair(game_id, waiting);
+        }
+
+        result
+    }
+
+    async fn save_started_game(
+        &self,
+        player_id: Uuid,
+        game_id: Uuid,
+        game_request: &GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<()> {
+        let room = self.rooms_manager.get_room(game_id)?;
+
+        let game = Game {
+            id: game_id,
+            white_player: room.white_player.ok_or(internal_error!())?,
+            black_player: room.black_player.ok_or(internal_error!())?,
+            bet_value: game_request.bet_value,
+            stake_mode: game_request.stake_mode,
+            category: game_request.rated.then(|| game_request.category()),
+            ..Default::default()
         };
 
-        self.wallet_repository
-            .save_outgoing(SaveOutgoing {
-                user_id: player_id,
-                amount: game_request.bet_value,
+        match stake_hold {
+            Some(payment_hash) if game_request.stake_mode == StakeMode::Hold => {
+                self.game_repository
+                    .start_game(game, vec![], vec![payment_hash])
+                    .await
+            }
+            _ => {
+                let stake = game_request.bet_value.to_msat()?;
+
+                self.game_repository
+                    .start_game(
+                        game,
+                        vec![SaveTransfer::stake(player_id, game_id, stake)],
+                        vec![],
+                    )
+                    .await
+            }
+        }
+    }
+
+    fn ensure_can_play(&self, player_id: Uuid) -> Result<()> {
+        if self.rooms_manager.count_games(player_id) >= self.max_concurrent_games {
+            return Err(Error::Conflict {
+                message: String::from("You are already playing as many games as allowed!"),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The running game of the player on the terms of `game_request`, with
+    /// their seat matching the colour it asks for.
+    fn find_running_game(&self, player_id: Uuid, game_request: &GameRequest) -> Option<Uuid> {
+        self.rooms_manager
+            .find_games(player_id)
+            .into_iter()
+            .find_map(|(game_id, room)| {
+                let room_request = GameRequest::from_str(&room.request_key).ok()?;
+                let player_color = match room.white_player == Some(player_id) {
+                    true => PlayerColor::White,
+                    false => PlayerColor::Black,
+                };
+
+                (room_request.same_terms(game_request)
+                    && game_request
+                        .player_color
+                        .is_none_or(|color| color == player_color))
+                .then_some(game_id)
             })
+    }
+
+    /// `stake_hold` is the payment hash of a paid hold invoice, required for
+    /// games whose stakes are held in the players' wallets. Repeating the
+    /// request of an open seek returns its game id without staking again,
+    /// and so does repeating the request of a running game when the player
+    /// cannot start another one.
+    pub async fn execute(
+        &self,
+        player_id: Uuid,
+        game_request: GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<Uuid> {
+        if self.rooms_manager.count_games(player_id) >= self.max_concurrent_games {
+            if let Some(game_id) = self.find_running_game(player_id, &game_request) {
+                return Ok(game_id);
+            }
+        }
+
+        let stake = game_request.bet_value.to_msat()?;
+
+        self.gambling_limit_repository
+            .get_protection(player_id)
+            .await?
+            .check_stake(stake, Utc::now())?;
+
+        if let Some((game_id, seek)) = self.rooms_manager.find_seek(player_id) {
+            if seek.key == game_request.key {
+                return Ok(game_id);
+            }
+
+            return Err(Error::Conflict {
+                message: String::from("You are already looking for another game!"),
+            });
+        }
+
+        self.ensure_can_play(player_id)?;
+        self.reserve_stake(player_id, &game_request, &stake_hold)
+            .await?;
+
+        let result = self
+            .pair(player_id, &game_request, stake_hold.clone())
+            .await;
+
+        if result.is_err() {
+            self.release_stake(&game_request, &stake_hold).await;
+        }
+
+        result
+    }
+
+    /// Pairs the player once their stake is reserved.
+    async fn pair(
+        &self,
+        player_id: Uuid,
+        game_request: &GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<Uuid> {
+        let rating = self
+            .rating_repository
+            .get_rating(player_id, game_request.category())
+            .await?;
+
+        let paired_game = self.rooms_manager.pair_new_player(Seek::new(
+            player_id,
+            game_request,
+            rating.rating,
+        ))?;
+
+        match paired_game {
+            PairedGame::NewGame(game_id) => {
+                self.stake_seek(player_id, game_id, game_request, stake_hold)
+                    .await?;
+
+                Ok(game_id)
+            }
+
+            // A concurrent request of the player opened a seek first, and
+            // it holds their stake already.
+            PairedGame::Seeking(game_id) => {
+                self.release_stake(game_request, &stake_hold).await;
+
+                Ok(game_id)
+            }
+
+            PairedGame::ExistingGame(game_id, waiting) => {
+                self.start_game(player_id, game_id, waiting, game_request, stake_hold)
+                    .await?;
+
+                Ok(game_id)
+            }
+        }
+    }
+
+    /// Opens a private room that only the player holding its invite token can
+    /// join. Returns the room id and the token.
+    pub async fn create_private(
+        &self,
+        player_id: Uuid,
+        game_request: GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<(Uuid, String)> {
+        self.gambling_limit_repository
+            .get_protection(player_id)
+            .await?
+            .check_stake(game_request.bet_value.to_msat()?, Utc::now())?;
+
+        if self.rooms_manager.find_seek(player_id).is_some() {
+            return Err(Error::Conflict {
+                message: String::from("You are already looking for another game!"),
+            });
+        }
+
+        self.ensure_can_play(player_id)?;
+        self.reserve_stake(player_id, &game_request, &stake_hold)
+            .await?;
+
+        let result = self
+            .open_private(player_id, &game_request, stake_hold.clone())
+            .await;
+
+        if result.is_err() {
+            self.release_stake(&game_request, &stake_hold).await;
+        }
+
+        result
+    }
+
+    async fn open_private(
+        &self,
+        player_id: Uuid,
+        game_request: &GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<(Uuid, String)> {
+        let invite_token = new_invite_token();
+        let seek = Seek {
+            invite_token: Some(invite_token.clone()),
+            ..Seek::new(player_id, game_request, 0.0)
+        };
+
+        let PairedGame::NewGame(game_id) = self.rooms_manager.open_private_room(seek)? else {
+            return Err(Error::Conflict {
+                message: String::from("You are already looking for another game!"),
+            });
+        };
+
+        self.stake_seek(player_id, game_id, game_request, stake_hold)
+            .await?;
+
+        Ok((game_id, invite_token))
+    }
+
+    /// Takes the free seat of the private room of `invite_token`, on the
+    /// terms its creator chose.
+    pub async fn join_private(
+        &self,
+        player_id: Uuid,
+        invite_token: &str,
+        stake_hold: Option<String>,
+    ) -> Result<Uuid> {
+        let (_, seek) = self
+            .rooms_manager
+            .find_invite(invite_token)
+            .ok_or(Error::NotFound {
+                message: String::from("Invite not found!"),
+            })?;
+        let game_request = GameRequest::from_str(&seek.key)?;
+
+        self.gambling_limit_repository
+            .get_protection(player_id)
+            .await?
+            .check_stake(game_request.bet_value.to_msat()?, Utc::now())?;
+
+        self.ensure_can_play(player_id)?;
+        self.reserve_stake(player_id, &game_request, &stake_hold)
             .await?;
 
-        Ok(paired_game_id)
+        let result = self
+            .take_private_seat(player_id, invite_token, &game_request, stake_hold.clone())
+            .await;
+
+        if result.is_err() {
+            self.release_stake(&game_request, &stake_hold).await;
+        }
+
+        result
     }
+
+    async fn take_private_seat(
+        &self,
+        player_id: Uuid,
+        invite_token: &str,
+        game_request: &GameRequest,
+        stake_hold: Option<String>,
+    ) -> Result<Uuid> {
+        let (game_id, waiting) = self
+            .rooms_manager
+            .join_private_room(invite_token, player_id)?;
+
+        self.start_game(player_id, game_id, waiting, game_request, stake_hold)
+            .await?;
+
+        Ok(game_id)
+    }
+}
+
+fn new_invite_token() -> String {
+    rand::random::<[u8; 16]>()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{GameRequest, MockRoomsManagerTrait, PairedGame, Player, PlayerColor};
-    use crate::repositories::{MockGameRepositoryTrait, MockWalletRepositoryTrait};
+    use crate::http::{CreateHoldInvoice, FakeLightning, MockLightningClient};
+    use crate::models::{GameRequest, MockRoomsManagerTrait, PairedGame, Player, Room, Sats};
+    use crate::models::{Limit, LimitKind, PlayerLimit, PlayerProtection};
+    use crate::repositories::{
+        MockGamblingLimitRepositoryTrait, MockGameRepositoryTrait, MockRatingRepositoryTrait,
+        MockStakeHoldRepositoryTrait, MockWalletRepositoryTrait, StakeHold,
+    };
     use mockall::predicate::*;
     use uuid::uuid;
 
+    fn no_limits() -> MockGamblingLimitRepositoryTrait {
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .returning(|_| Ok(PlayerProtection::default()));
+
+        mock_gambling_limit_repository
+    }
+
+    /// A rooms manager where the player has no seek or game yet.
+    fn idle_rooms_manager() -> MockRoomsManagerTrait {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+
+        mock_rooms_manager.expect_find_seek().returning(|_| None);
+        mock_rooms_manager.expect_count_games().returning(|_| 0);
+
+        mock_rooms_manager
+    }
+
+    fn unrated() -> MockRatingRepositoryTrait {
+        let mut mock_rating_repository = MockRatingRepositoryTrait::new();
+
+        mock_rating_repository
+            .expect_get_rating()
+            .returning(|_, _| Ok(Default::default()));
+
+        mock_rating_repository
+    }
+
     #[tokio::test]
     async fn quick_pairing_service() {
         let mut mock_game_repository = MockGameRepositoryTrait::new();
-        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_rooms_manager = idle_rooms_manager();
         let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
 
         let request_key = "w-10-0-10";
@@ -98,11 +575,20 @@ mod tests {
         mock_wallet_repository
             .expect_get_balance()
             .once()
-            .returning(|_| Ok(10000));
+            .returning(|_| Ok(Msat::new(10_000_000)));
 
         mock_rooms_manager
             .expect_pair_new_player()
-            .returning(|_| PairedGame::NewGame(uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a")));
+            .withf(move |seek| seek.key == "v1:w:600+0:standard:rated:*:*:10")
+            .returning(|_| {
+                Ok(PairedGame::NewGame(uuid!(
+                    "06d6a0d9-97a8-48d0-9f81-0172c5a81b8a"
+                )))
+            });
+        mock_rooms_manager
+            .expect_stake_seek()
+            .once()
+            .return_const(());
 
         mock_game_repository
             .expect_get_player()
@@ -114,35 +600,37 @@ mod tests {
                 })
             });
 
-        mock_rooms_manager
-            .expect_create_room()
-            .with(
-                eq(uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a")),
-                eq(request_key.to_string()),
-            )
-            .return_const(());
-
-        mock_rooms_manager
-            .expect_add_player()
-            .returning(|_, _, c| Ok(c.unwrap_or(PlayerColor::White)));
-
         mock_wallet_repository
-            .expect_save_outgoing()
+            .expect_save_transfer()
             .once()
-            .withf(|info| info.amount == 10)
+            .withf(|transfer| {
+                *transfer
+                    == SaveTransfer::stake(
+                        uuid!("5d6cc3e8-8eec-4dab-881f-fddfb831cc41"),
+                        uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a"),
+                        Msat::new(10_000),
+                    )
+            })
             .returning(|_| Ok(Uuid::new_v4()));
 
         let service = PairingGameService::new(
             mock_game_repository,
             mock_rooms_manager,
             mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            MockLightningClient::new(),
+            no_limits(),
+            unrated(),
+            1,
         );
 
         let game_request = GameRequest::from_str(request_key);
 
         assert!(game_request.is_ok());
 
-        let result = service.execute(player.id, game_request.unwrap()).await;
+        let result = service
+            .execute(player.id, game_request.unwrap(), None)
+            .await;
 
         assert!(result.is_ok());
         assert_eq!(
@@ -150,4 +638,552 @@ mod tests {
             uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a")
         );
     }
+
+    const PLAYER_ID: Uuid = uuid!("5d6cc3e8-8eec-4dab-881f-fddfb831cc41");
+    const PAYMENT_HASH: &str = "0001020304050607080900010203040506070809000102030405060708090102";
+
+    fn stake_hold(amount: Msat) -> StakeHold {
+        StakeHold {
+            user_id: PLAYER_ID,
+            game_id: None,
+            payment_hash: String::from(PAYMENT_HASH),
+            preimage: String::new(),
+            amount,
+            status: HoldStatus::Pending,
+            resolution: None,
+        }
+    }
+
+    async fn hold_invoice(lightning: &FakeLightning) -> String {
+        lightning
+            .create_hold_invoice(CreateHoldInvoice {
+                amount: Sats::new(10),
+                payment_hash: String::from(PAYMENT_HASH),
+                memo: String::new(),
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pairing_with_stake_hold() {
+        let mut mock_game_repository = MockGameRepositoryTrait::new();
+        let mut mock_rooms_manager = idle_rooms_manager();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+        let lightning = FakeLightning::new();
+
+        lightning.pay(&hold_invoice(&lightning).await);
+
+        mock_stake_hold_repository
+            .expect_get_stake_hold()
+            .once()
+            .returning(|_| Ok(stake_hold(Msat::new(10_000))));
+
+        mock_stake_hold_repository
+            .expect_claim_stake_hold()
+            .once()
+            .returning(|_, _| Ok(true));
+
+        mock_stake_hold_repository
+            .expect_release_stake_hold()
+            .never();
+
+        mock_rooms_manager
+            .expect_pair_new_player()
+            .withf(|seek| seek.player_id == PLAYER_ID && seek.stake_mode == StakeMode::Hold)
+            .returning(|seek| {
+                Ok(PairedGame::ExistingGame(
+                    uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a"),
+                    Seek {
+                        player_id: Uuid::new_v4(),
+                        ..seek
+                    },
+                ))
+            });
+
+        mock_rooms_manager.expect_get_room().returning(|_| {
+            Ok(Room {
+                white_player: Some(Uuid::new_v4()),
+                black_player: Some(PLAYER_ID),
+                ..Room::new(String::from("n-10-0-10-h"))
+            })
+        });
+
+        mock_game_repository
+            .expect_start_game()
+            .once()
+            .withf(|game, transfers, stake_holds| {
+                game.stake_mode == StakeMode::Hold
+                    && game.bet_value == Sats::new(10)
+                    && transfers.is_empty()
+                    && *stake_holds == vec![String::from(PAYMENT_HASH)]
+            })
+            .returning(|_, _, _| Ok(()));
+
+        mock_wallet_repository.expect_get_balance().never();
+        mock_wallet_repository.expect_save_transfer().never();
+
+        let service = PairingGameService::new(
+            mock_game_repository,
+            mock_rooms_manager,
+            mock_wallet_repository,
+            mock_stake_hold_repository,
+            lightning,
+            no_limits(),
+            unrated(),
+            1,
+        );
+
+        let result = service
+            .execute(
+                PLAYER_ID,
+                GameRequest::from_str("n-10-0-10-h").unwrap(),
+                Some(String::from(PAYMENT_HASH)),
+            )
+            .await;
+
+        assert_eq!(
+            result.ok(),
+            Some(uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a"))
+        );
+    }
+
+    /// Pairs with a paid stake hold, the rooms manager answering `paired`.
+    async fn pair_with_stake_hold(paired: Result<PairedGame>) -> Result<Uuid> {
+        let mut mock_rooms_manager = idle_rooms_manager();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+        let lightning = FakeLightning::new();
+
+        lightning.pay(&hold_invoice(&lightning).await);
+
+        mock_stake_hold_repository
+            .expect_get_stake_hold()
+            .returning(|_| Ok(stake_hold(Msat::new(10_000))));
+
+        mock_stake_hold_repository
+            .expect_claim_stake_hold()
+            .once()
+            .returning(|_, _| Ok(true));
+
+        mock_stake_hold_repository
+            .expect_release_stake_hold()
+            .once()
+            .with(eq(String::from(PAYMENT_HASH)))
+            .returning(|_| Ok(()));
+
+        mock_rooms_manager
+            .expect_pair_new_player()
+            .return_once(|_| paired);
+
+        let service = PairingGameService::new(
+            MockGameRepositoryTrait::new(),
+            mock_rooms_manager,
+            MockWalletRepositoryTrait::new(),
+            mock_stake_hold_repository,
+            lightning,
+            no_limits(),
+            unrated(),
+            1,
+        );
+
+        service
+            .execute(
+                PLAYER_ID,
+                GameRequest::from_str("n-10-0-10-h").unwrap(),
+                Some(String::from(PAYMENT_HASH)),
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_unused_stake_hold_is_released() {
+        let game_id = uuid!("06d6a0d9-97a8-48d0-9f81-0172c5a81b8a");
+
+        let seeking = pair_with_stake_hold(Ok(PairedGame::Seeking(game_id))).await;
+        let failed = pair_with_stake_hold(Err(Error::Conflict {
+            message: String::from("Room is full"),
+        }))
+        .await;
+
+        assert_eq!(seeking.ok(), Some(game_id));
+        assert!(failed.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pairing_with_unpaid_stake_hold() {
+        let mut mock_rooms_manager = idle_rooms_manager();
+        let mut mock_stake_hold_repository = MockStakeHoldRepositoryTrait::new();
+        let lightning = FakeLightning::new();
+
+        hold_invoice(&lightning).await;
+
+        mock_stake_hold_repository
+            .expect_get_stake_hold()
+            .returning(|_| Ok(stake_hold(Msat::new(10_000))));
+
+        mock_stake_hold_repository.expect_claim_stake_hold().never();
+        mock_rooms_manager.expect_pair_new_player().never();
+
+        let service = PairingGameService::new(
+            MockGameRepositoryTrait::new(),
+            mock_rooms_manager,
+            MockWalletRepositoryTrait::new(),
+            mock_stake_hold_repository,
+            lightning,
+            no_limits(),
+            unrated(),
+            1,
+        );
+
+        let game_request = || GameRequest::from_str("n-10-0-10-h").unwrap();
+        let payment_hash = Some(String::from(PAYMENT_HASH));
+
+        assert!(service
+            .execute(PLAYER_ID, game_request(), payment_hash)
+            .await
+            .is_err());
+        assert!(service
+            .execute(PLAYER_ID, game_request(), None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pairing_above_stake_limit() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .once()
+            .returning(|_| {
+                Ok(PlayerProtection {
+                    limits: vec![PlayerLimit {
+                        kind: LimitKind::Stake,
+                        limit: Limit {
+                            amount: Sats::new(5),
+                            period: None,
+                        },
+                        pending: None,
+                    }],
+                    ..Default::default()
+                })
+            });
+
+        mock_rooms_manager.expect_count_games().returning(|_| 0);
+        mock_rooms_manager.expect_pair_new_player().never();
+
+        let service = PairingGameService::new(
+            MockGameRepositoryTrait::new(),
+            mock_rooms_manager,
+            MockWalletRepositoryTrait::new(),
+            MockStakeHoldRepositoryTrait::new(),
+            MockLightningClient::new(),
+            mock_gambling_limit_repository,
+            MockRatingRepositoryTrait::new(),
+            1,
+        );
+
+        let result = service
+            .execute(
+                Uuid::new_v4(),
+                GameRequest::from_str("w-10-0-10").unwrap(),
+                None,
+            )
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    fn service_with_rooms(
+        mock_rooms_manager: MockRoomsManagerTrait,
+    ) -> PairingGameService<
+        MockGameRepositoryTrait,
+        MockRoomsManagerTrait,
+        MockWalletRepositoryTrait,
+        MockStakeHoldRepositoryTrait,
+        MockLightningClient,
+        MockGamblingLimitRepositoryTrait,
+        MockRatingRepositoryTrait,
+    > {
+        PairingGameService::new(
+            MockGameRepositoryTrait::new(),
+            mock_rooms_manager,
+            MockWalletRepositoryTrait::new(),
+            MockStakeHoldRepositoryTrait::new(),
+            MockLightningClient::new(),
+            no_limits(),
+            unrated(),
+            1,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_repeated_request_returns_open_seek() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let game_id = Uuid::new_v4();
+
+        mock_rooms_manager
+            .expect_find_seek()
+            .returning(move |player_id| {
+                Some((
+                    game_id,
+                    Seek::new(
+                        player_id,
+                        &GameRequest::from_str("w-10-0-10").unwrap(),
+                        1500.0,
+                    ),
+                ))
+            });
+        mock_rooms_manager.expect_count_games().returning(|_| 0);
+        mock_rooms_manager.expect_pair_new_player().never();
+
+        let service = service_with_rooms(mock_rooms_manager);
+        let request = |key| GameRequest::from_str(key).unwrap();
+
+        assert_eq!(
+            service
+                .execute(PLAYER_ID, request("w-10-0-10"), None)
+                .await
+                .ok(),
+            Some(game_id)
+        );
+        assert!(service
+            .execute(PLAYER_ID, request("w-3-0-10"), None)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pairing_above_concurrent_games() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+
+        mock_rooms_manager.expect_find_seek().returning(|_| None);
+        mock_rooms_manager.expect_count_games().returning(|_| 1);
+        mock_rooms_manager
+            .expect_find_games()
+            .returning(|_| vec![(Uuid::new_v4(), playing_room("w-3-0-10"))]);
+        mock_rooms_manager.expect_pair_new_player().never();
+
+        let result = service_with_rooms(mock_rooms_manager)
+            .execute(PLAYER_ID, GameRequest::from_str("w-10-0-10").unwrap(), None)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    fn playing_room(request_key: &str) -> Room {
+        Room {
+            white_player: Some(Uuid::new_v4()),
+            black_player: Some(PLAYER_ID),
+            ..Room::new(String::from(request_key))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_request_returns_running_game() {
+        let mut mock_rooms_manager = MockRoomsManagerTrait::new();
+        let game_id = Uuid::new_v4();
+
+        mock_rooms_manager.expect_count_games().returning(|_| 1);
+        mock_rooms_manager
+            .expect_find_games()
+            .returning(move |_| vec![(game_id, playing_room("w-10-0-10"))]);
+        mock_rooms_manager.expect_find_seek().returning(|_| None);
+        mock_rooms_manager.expect_pair_new_player().never();
+
+        let service = service_with_rooms(mock_rooms_manager);
+
+        let result = service
+            .execute(PLAYER_ID, GameRequest::from_str("n-10-0-10").unwrap(), None)
+            .await;
+
+        assert_eq!(result.ok(), Some(game_id));
+
+        let result = service
+            .execute(PLAYER_ID, GameRequest::from_str("w-10-0-10").unwrap(), None)
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_join_private_game_through_invite() {
+        let mut mock_game_repository = MockGameRepositoryTrait::new();
+        let mut mock_rooms_manager = idle_rooms_manager();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let host_id = Uuid::new_v4();
+        let game_id = Uuid::new_v4();
+
+        mock_rooms_manager
+            .expect_find_invite()
+            .returning(move |token| {
+                Some((
+                    game_id,
+                    Seek {
+                        invite_token: Some(token.to_string()),
+                        ..Seek::new(
+                            host_id,
+                            &GameRequest::from_str("w-10-0-10").unwrap(),
+                            1500.0,
+                        )
+                    },
+                ))
+            });
+        mock_rooms_manager
+            .expect_join_private_room()
+            .once()
+            .withf(|token, player_id| token == "invite" && *player_id == PLAYER_ID)
+            .returning(move |token, _| {
+                Ok((
+                    game_id,
+                    Seek {
+                        invite_token: Some(token.to_string()),
+                        ..Seek::new(
+                            host_id,
+                            &GameRequest::from_str("w-10-0-10").unwrap(),
+                            1500.0,
+                        )
+                    },
+                ))
+            });
+        mock_rooms_manager.expect_get_room().returning(move |_| {
+            Ok(Room {
+                white_player: Some(host_id),
+                black_player: Some(PLAYER_ID),
+                ..Room::new(String::from("w-10-0-10"))
+            })
+        });
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(10_000_000)));
+        mock_wallet_repository.expect_save_transfer().never();
+
+        mock_game_repository
+            .expect_start_game()
+            .once()
+            .withf(move |game, transfers, _| {
+                game.white_player == host_id
+                    && game.black_player == PLAYER_ID
+                    && *transfers
+                        == vec![SaveTransfer::stake(PLAYER_ID, game_id, Msat::new(10_000))]
+            })
+            .returning(|_, _, _| Ok(()));
+
+        let service = PairingGameService::new(
+            mock_game_repository,
+            mock_rooms_manager,
+            mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            MockLightningClient::new(),
+            no_limits(),
+            unrated(),
+            1,
+        );
+
+        assert_eq!(
+            service.join_private(PLAYER_ID, "invite", None).await.ok(),
+            Some(game_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_stake_closes_new_room() {
+        let mut mock_rooms_manager = idle_rooms_manager();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let game_id = Uuid::new_v4();
+
+        mock_rooms_manager
+            .expect_pair_new_player()
+            .returning(move |_| Ok(PairedGame::NewGame(game_id)));
+        mock_rooms_manager
+            .expect_abandon_seek()
+            .once()
+            .withf(move |id| *id == game_id)
+            .return_const(());
+        mock_rooms_manager.expect_stake_seek().never();
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(10_000_000)));
+        mock_wallet_repository
+            .expect_save_transfer()
+            .once()
+            .returning(|_| Err(Error::InternalServerError));
+
+        let service = PairingGameService::new(
+            MockGameRepositoryTrait::new(),
+            mock_rooms_manager,
+            mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            MockLightningClient::new(),
+            no_limits(),
+            unrated(),
+            1,
+        );
+
+        let result = service
+            .execute(PLAYER_ID, GameRequest::from_str("w-10-0-10").unwrap(), None)
+            .await;
+
+        assert!(matches!(result, Err(Error::InternalServerError)));
+    }
+
+    #[tokio::test]
+    async fn test_failed_game_start_frees_the_seat() {
+        let mut mock_game_repository = MockGameRepositoryTrait::new();
+        let mut mock_rooms_manager = idle_rooms_manager();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let game_id = Uuid::new_v4();
+        let waiting = Seek::new(
+            Uuid::new_v4(),
+            &GameRequest::from_str("b-10-0-10").unwrap(),
+            1500.0,
+        );
+        let waiting_id = waiting.player_id;
+        let paired = waiting.clone();
+
+        mock_rooms_manager
+            .expect_pair_new_player()
+            .returning(move |_| Ok(PairedGame::ExistingGame(game_id, paired.clone())));
+        mock_rooms_manager.expect_get_room().returning(move |_| {
+            Ok(Room {
+                white_player: Some(PLAYER_ID),
+                black_player: Some(waiting_id),
+                ..Room::new(String::from("b-10-0-10"))
+            })
+        });
+        mock_rooms_manager
+            .expect_unpair()
+            .once()
+            .withf(move |id, seek| *id == game_id && *seek == waiting)
+            .return_const(());
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(10_000_000)));
+        mock_game_repository
+            .expect_start_game()
+            .once()
+            .returning(|_, _, _| Err(Error::InternalServerError));
+
+        let service = PairingGameService::new(
+            mock_game_repository,
+            mock_rooms_manager,
+            mock_wallet_repository,
+            MockStakeHoldRepositoryTrait::new(),
+            MockLightningClient::new(),
+            no_limits(),
+            unrated(),
+            1,
+        );
+
+        let result = service
+            .execute(PLAYER_ID, GameRequest::from_str("w-10-0-10").unwrap(), None)
+            .await;
+
+        assert!(matches!(result, Err(Error::InternalServerError)));
+    }
 }
@@ -1,7 +1,9 @@
 use aide::axum::ApiRouter;
 
+mod admin;
+mod challenge;
 mod docs;
-mod game;
+pub(crate) mod game;
 mod user;
 mod wallet;
 
@@ -11,4 +13,6 @@ pub fn mount() -> ApiRouter {
         .merge(wallet::router())
         .merge(docs::router())
         .merge(game::router())
+        .merge(admin::router())
+        .merge(challenge::router())
 }
@@ -0,0 +1,37 @@
+use crate::http::{GenericError, Result};
+use crate::models::{AuthUser, Exclusion, ExclusionKind};
+use aide::transform::TransformOperation;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::resource;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ExclusionBody {
+    kind: ExclusionKind,
+    /// 1 to 42 days for a time-out, 180 to 1825 for a self-exclusion.
+    days: i64,
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Json(ExclusionBody { kind, days }): Json<ExclusionBody>,
+) -> Result<Json<Exclusion>> {
+    let gambling_limit_service = resource();
+
+    Ok(Json(
+        gambling_limit_service
+            .exclude(auth_user.user_id, kind, days)
+            .await?,
+    ))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Responsible Gambling")
+        .description(
+            "Take a time-out or self-exclude, blocking deposits and games until it ends. It cannot be shortened",
+        )
+        .response::<200, Json<Exclusion>>()
+        .response::<400, Json<GenericError>>()
+}
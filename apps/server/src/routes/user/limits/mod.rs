@@ -0,0 +1,47 @@
+use crate::http::{GenericError, Result};
+use crate::models::{AuthUser, Exclusion, Msat, PlayerLimit};
+use crate::repositories::GamblingLimitRepository;
+use crate::states::cooling_off;
+use aide::transform::TransformOperation;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::GamblingLimitService;
+
+pub mod exclusion;
+mod service;
+pub mod update;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct LimitsBody {
+    limits: Vec<PlayerLimit>,
+    exclusion: Option<Exclusion>,
+    /// Deposits counted against the deposit limit in its current period.
+    deposited_msat: Msat,
+    /// Net game losses counted against the loss limit in its current period.
+    lost_msat: Msat,
+}
+
+fn resource() -> GamblingLimitService<GamblingLimitRepository> {
+    GamblingLimitService::new(GamblingLimitRepository::new(), cooling_off::get())
+}
+
+pub async fn route(auth_user: AuthUser) -> Result<Json<LimitsBody>> {
+    let gambling_limit_service = resource();
+
+    let protection = gambling_limit_service.get(auth_user.user_id).await?;
+
+    Ok(Json(LimitsBody {
+        limits: protection.limits,
+        exclusion: protection.exclusion,
+        deposited_msat: protection.deposited,
+        lost_msat: protection.lost,
+    }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Responsible Gambling")
+        .description("Get the deposit, stake and loss limits and the exclusion of the logged user")
+        .response::<200, Json<LimitsBody>>()
+        .response::<401, Json<GenericError>>()
+}
@@ -0,0 +1,158 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use crate::models::{Exclusion, ExclusionKind, Limit, LimitKind, PlayerLimit, PlayerProtection};
+use crate::repositories::GamblingLimitRepositoryTrait;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+pub struct GamblingLimitService<G: GamblingLimitRepositoryTrait> {
+    gambling_limit_repository: G,
+    cooling_off: Duration,
+}
+
+impl<G: GamblingLimitRepositoryTrait> GamblingLimitService<G> {
+    pub fn new(gambling_limit_repository: G, cooling_off: Duration) -> Self {
+        Self {
+            gambling_limit_repository,
+            cooling_off,
+        }
+    }
+
+    pub async fn get(&self, user_id: Uuid) -> Result<PlayerProtection> {
+        self.gambling_limit_repository.get_protection(user_id).await
+    }
+
+    /// Sets or, with `requested` unset, removes the limit of `kind`. Returns
+    /// the limit in place afterwards.
+    pub async fn set_limit(
+        &self,
+        user_id: Uuid,
+        kind: LimitKind,
+        requested: Option<Limit>,
+    ) -> Result<Option<PlayerLimit>> {
+        let protection = self
+            .gambling_limit_repository
+            .get_protection(user_id)
+            .await?;
+
+        let current = protection.limits.iter().find(|limit| limit.kind == kind);
+        let limit = PlayerLimit::change(kind, current, requested, Utc::now(), self.cooling_off)?;
+
+        self.gambling_limit_repository
+            .save_limit(user_id, kind, limit)
+            .await?;
+
+        Ok(limit)
+    }
+
+    pub async fn exclude(
+        &self,
+        user_id: Uuid,
+        kind: ExclusionKind,
+        days: i64,
+    ) -> Result<Exclusion> {
+        let range = kind.days();
+
+        if !range.contains(&days) {
+            return bad_req!(format!(
+                "This exclusion lasts between {} and {} days",
+                range.start(),
+                range.end()
+            ));
+        }
+
+        self.gambling_limit_repository
+            .exclude(
+                user_id,
+                Exclusion {
+                    kind,
+                    until: Utc::now() + Duration::days(days),
+                },
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{LimitPeriod, Sats};
+    use crate::repositories::MockGamblingLimitRepositoryTrait;
+
+    fn daily(amount: i64) -> Limit {
+        Limit {
+            amount: Sats::new(amount),
+            period: Some(LimitPeriod::Day),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_raising_limit_is_delayed() {
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .returning(|_| {
+                Ok(PlayerProtection {
+                    limits: vec![PlayerLimit {
+                        kind: LimitKind::Deposit,
+                        limit: daily(1000),
+                        pending: None,
+                    }],
+                    ..Default::default()
+                })
+            });
+
+        mock_gambling_limit_repository
+            .expect_save_limit()
+            .once()
+            .withf(|_, kind, limit| {
+                *kind == LimitKind::Deposit
+                    && limit.is_some_and(|limit| {
+                        limit.limit == daily(1000)
+                            && limit.pending.is_some_and(|pending| {
+                                pending.limit == Some(daily(5000))
+                                    && pending.effective_at > Utc::now() + Duration::hours(23)
+                            })
+                    })
+            })
+            .returning(|_, _, _| Ok(()));
+
+        let service =
+            GamblingLimitService::new(mock_gambling_limit_repository, Duration::hours(24));
+
+        let result = service
+            .set_limit(Uuid::new_v4(), LimitKind::Deposit, Some(daily(5000)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(result.effective(Utc::now()), Some(daily(1000)));
+    }
+
+    #[tokio::test]
+    async fn test_exclusion_length() {
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_gambling_limit_repository
+            .expect_exclude()
+            .once()
+            .returning(|_, exclusion| Ok(exclusion));
+
+        let service =
+            GamblingLimitService::new(mock_gambling_limit_repository, Duration::hours(24));
+
+        assert!(service
+            .exclude(Uuid::new_v4(), ExclusionKind::TimeOut, 43)
+            .await
+            .is_err());
+        assert!(service
+            .exclude(Uuid::new_v4(), ExclusionKind::SelfExclusion, 30)
+            .await
+            .is_err());
+        assert!(service
+            .exclude(Uuid::new_v4(), ExclusionKind::TimeOut, 7)
+            .await
+            .is_ok());
+    }
+}
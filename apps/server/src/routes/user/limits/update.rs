@@ -0,0 +1,42 @@
+use crate::http::{GenericError, Result};
+use crate::models::{AuthUser, Limit, LimitKind, PlayerLimit};
+use aide::transform::TransformOperation;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::resource;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct LimitBody {
+    kind: LimitKind,
+    /// Leave unset to remove the limit.
+    limit: Option<Limit>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct UpdatedLimitBody {
+    limit: Option<PlayerLimit>,
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Json(LimitBody { kind, limit }): Json<LimitBody>,
+) -> Result<Json<UpdatedLimitBody>> {
+    let gambling_limit_service = resource();
+
+    let limit = gambling_limit_service
+        .set_limit(auth_user.user_id, kind, limit)
+        .await?;
+
+    Ok(Json(UpdatedLimitBody { limit }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Responsible Gambling")
+        .description(
+            "Set or remove a limit. Lower limits apply at once, higher ones after a cooling-off period",
+        )
+        .response::<200, Json<UpdatedLimitBody>>()
+        .response::<400, Json<GenericError>>()
+}
@@ -1,7 +1,11 @@
 use crate::{
     http::Result,
-    models::AuthUser,
-    repositories::{UserRepository, UserRepositoryTrait},
+    models::{lightning_address, AuthUser, Msat, Sats},
+    repositories::{
+        CategoryRating, RatingChange, RatingRepository, RatingRepositoryTrait, UserRepository,
+        UserRepositoryTrait,
+    },
+    Env,
 };
 use aide::transform::TransformOperation;
 use axum::Json;
@@ -21,15 +25,23 @@ pub struct UserWithoutPassword {
     id: Uuid,
     username: String,
     email: String,
-    balance: i32,
+    /// Spendable balance in whole sats, rounded down.
+    balance: Sats,
+    balance_msat: Msat,
+    lightning_address: Option<String>,
+    ratings: Vec<CategoryRating>,
+    /// Latest rating changes first.
+    rating_history: Vec<RatingChange>,
 }
 
-fn resource() -> UserRepository {
-    UserRepository::new()
+const RATING_HISTORY_LIMIT: i64 = 50;
+
+fn resource() -> (UserRepository, RatingRepository) {
+    (UserRepository::new(), RatingRepository::new())
 }
 
 pub async fn route(auth_user: AuthUser) -> Result<Json<UserBody<UserWithoutPassword>>> {
-    let user_repository = resource();
+    let (user_repository, rating_repository) = resource();
 
     let crate::models::User {
         id,
@@ -39,12 +51,21 @@ pub async fn route(auth_user: AuthUser) -> Result<Json<UserBody<UserWithoutPassw
         ..
     } = user_repository.find_by_id(auth_user.user_id).await?;
 
+    let ratings = rating_repository.get_ratings(id).await?;
+    let rating_history = rating_repository
+        .get_rating_history(id, RATING_HISTORY_LIMIT)
+        .await?;
+
     Ok(Json(UserBody {
         user: UserWithoutPassword {
             id,
             email,
+            lightning_address: lightning_address(&Env::get().public_url, &username),
             username,
-            balance,
+            balance: balance.floor_sats(),
+            balance_msat: balance,
+            ratings,
+            rating_history,
         },
     }))
 }
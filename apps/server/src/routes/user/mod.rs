@@ -3,6 +3,7 @@ use aide::axum::{
     ApiRouter,
 };
 
+mod limits;
 mod login;
 mod logout;
 mod me;
@@ -14,4 +15,13 @@ pub fn router() -> ApiRouter {
         .api_route("/auth/login", post_with(login::route, login::docs))
         .api_route("/auth/logout", get_with(logout::route, logout::docs))
         .api_route("/user/me", get_with(me::route, me::docs))
+        .api_route(
+            "/user/limits",
+            get_with(limits::route, limits::docs)
+                .put_with(limits::update::route, limits::update::docs),
+        )
+        .api_route(
+            "/user/exclusion",
+            post_with(limits::exclusion::route, limits::exclusion::docs),
+        )
 }
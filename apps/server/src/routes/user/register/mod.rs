@@ -2,7 +2,7 @@ use crate::http::{
     Result, {Error, GenericError},
 };
 use crate::{
-    models::{User, COOKIE_NAME},
+    models::{is_valid_username, User, COOKIE_NAME},
     repositories::UserRepository,
 };
 use aide::transform::TransformOperation;
@@ -15,7 +15,8 @@ use axum_extra::extract::cookie::{Cookie, SameSite};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use service::{RegisterInput, RegisterUserService};
-use validator::Validate;
+use std::borrow::Cow;
+use validator::{Validate, ValidationError};
 
 mod service;
 
@@ -74,6 +75,7 @@ pub struct UserBody<T> {
 
 #[derive(Validate, Deserialize, JsonSchema)]
 pub struct RegisterUser {
+    #[validate(custom(function = "validate_username"))]
     username: String,
     #[validate(email(message = "Invalid email"))]
     email: String,
@@ -81,6 +83,16 @@ pub struct RegisterUser {
     password: String,
 }
 
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if is_valid_username(username) {
+        return Ok(());
+    }
+
+    Err(ValidationError::new("username").with_message(Cow::from(
+        "Username may only contain letters, numbers, '.', '-' and '_'",
+    )))
+}
+
 fn validate_user_payload<T: Validate>(user_body: &UserBody<T>) -> Option<String> {
     let validation = user_body.user.validate();
 
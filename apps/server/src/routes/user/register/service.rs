@@ -1,5 +1,5 @@
 use crate::http::Result;
-use crate::models::{AuthUser, User};
+use crate::models::{AuthUser, Msat, User};
 use crate::repositories::{SaveUser, UserRepositoryTrait};
 use anyhow::Context;
 use argon2::{password_hash::SaltString, Argon2, PasswordHash};
@@ -47,7 +47,7 @@ impl<R: UserRepositoryTrait> RegisterUserService<R> {
                 username,
                 email,
                 hashed_password: password_hash,
-                balance: 0,
+                balance: Msat::ZERO,
             },
             token,
         ))
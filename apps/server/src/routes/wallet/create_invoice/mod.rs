@@ -0,0 +1,65 @@
+use crate::http::{CreateInvoice, GenericError, Result};
+use crate::models::{AuthUser, Sats};
+use crate::repositories::{GamblingLimitRepository, InvoiceRepository};
+use aide::transform::TransformOperation;
+use axum::Json;
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::CreateInvoiceService;
+
+pub(super) mod service;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AmountBody {
+    amount: Sats,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InvoiceBody {
+    invoice: String,
+    payment_hash: String,
+}
+
+// Deposits are matched to users by payment hash, so the memo shown to the
+// payer carries nothing about the account.
+const INVOICE_MEMO: &str = "Chesu deposit";
+
+pub(super) fn resource() -> CreateInvoiceService<Client, InvoiceRepository, GamblingLimitRepository>
+{
+    CreateInvoiceService::new(
+        Client::new(),
+        InvoiceRepository::new(),
+        GamblingLimitRepository::new(),
+    )
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Json(payload): Json<AmountBody>,
+) -> Result<Json<InvoiceBody>> {
+    let create_invoice_service = resource();
+
+    let invoice = create_invoice_service
+        .execute(
+            auth_user.user_id,
+            CreateInvoice {
+                amount: payload.amount,
+                memo: Some(String::from(INVOICE_MEMO)),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    Ok(Json(InvoiceBody {
+        invoice: invoice.payment_request,
+        payment_hash: invoice.payment_hash,
+    }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Create Deposit Invoice")
+        .description("Create an invoice to deposit satoshis")
+        .response::<200, Json<InvoiceBody>>()
+        .response::<400, Json<GenericError>>()
+}
@@ -0,0 +1,175 @@
+use crate::bad_req;
+use crate::http::{CreateInvoice, Error, LightningClient, Result};
+use crate::models::DecodedInvoice;
+use crate::repositories::{GamblingLimitRepositoryTrait, InvoiceRepositoryTrait};
+use chrono::Utc;
+use uuid::Uuid;
+
+pub struct CreateInvoiceService<
+    L: LightningClient,
+    R: InvoiceRepositoryTrait,
+    G: GamblingLimitRepositoryTrait,
+> {
+    lightning_client: L,
+    invoice_repository: R,
+    gambling_limit_repository: G,
+}
+
+impl<L: LightningClient, R: InvoiceRepositoryTrait, G: GamblingLimitRepositoryTrait>
+    CreateInvoiceService<L, R, G>
+{
+    pub fn new(lightning_client: L, invoice_repository: R, gambling_limit_repository: G) -> Self {
+        Self {
+            lightning_client,
+            invoice_repository,
+            gambling_limit_repository,
+        }
+    }
+
+    /// Creates a deposit invoice and tracks it so its payment is credited to
+    /// `user_id`.
+    pub async fn execute(&self, user_id: Uuid, request: CreateInvoice) -> Result<DecodedInvoice> {
+        if !request.amount.is_positive() {
+            return bad_req!("Invalid invoice input");
+        }
+
+        let amount = request.amount.to_msat()?;
+
+        self.gambling_limit_repository
+            .get_protection(user_id)
+            .await?
+            .check_deposit(amount, Utc::now())?;
+
+        let payment_request = self.lightning_client.create_invoice(request).await?;
+        let invoice = DecodedInvoice::decode(&payment_request)?;
+
+        if invoice.amount != amount {
+            return Err(Error::InternalServerError);
+        }
+
+        self.invoice_repository
+            .save_invoice(user_id, invoice.clone())
+            .await?;
+
+        Ok(invoice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::MockLightningClient;
+    use crate::models::{Exclusion, ExclusionKind, Msat, PlayerProtection, Sats};
+    use crate::repositories::{MockGamblingLimitRepositoryTrait, MockInvoiceRepositoryTrait};
+    use uuid::uuid;
+
+    // BOLT11 specification test vector for 250000 sats.
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+    #[tokio::test]
+    async fn test_create_invoice() {
+        let mut mock_lightning_client = MockLightningClient::new();
+        let mut mock_invoice_repository = MockInvoiceRepositoryTrait::new();
+
+        mock_lightning_client
+            .expect_create_invoice()
+            .once()
+            .withf(|request| request.amount == Sats::new(250_000))
+            .returning(|_| Ok(String::from(INVOICE)));
+
+        mock_invoice_repository
+            .expect_save_invoice()
+            .once()
+            .withf(|user_id, invoice| {
+                user_id == &uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a")
+                    && invoice.amount == Msat::new(250_000_000)
+            })
+            .returning(|_, _| Ok(()));
+
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .returning(|_| Ok(PlayerProtection::default()));
+
+        let service = CreateInvoiceService::new(
+            mock_lightning_client,
+            mock_invoice_repository,
+            mock_gambling_limit_repository,
+        );
+
+        let result = service
+            .execute(
+                uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+                CreateInvoice {
+                    amount: Sats::new(250_000),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        assert_eq!(
+            result.unwrap().payment_hash,
+            "0001020304050607080900010203040506070809000102030405060708090102"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_invoice_invalid_amount() {
+        let mut mock_lightning_client = MockLightningClient::new();
+        let mock_invoice_repository = MockInvoiceRepositoryTrait::new();
+
+        mock_lightning_client.expect_create_invoice().never();
+
+        let service = CreateInvoiceService::new(
+            mock_lightning_client,
+            mock_invoice_repository,
+            MockGamblingLimitRepositoryTrait::new(),
+        );
+
+        let result = service
+            .execute(Uuid::new_v4(), CreateInvoice::default())
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_invoice_while_excluded() {
+        let mut mock_lightning_client = MockLightningClient::new();
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .once()
+            .returning(|_| {
+                Ok(PlayerProtection {
+                    exclusion: Some(Exclusion {
+                        kind: ExclusionKind::SelfExclusion,
+                        until: Utc::now() + chrono::Duration::days(180),
+                    }),
+                    ..Default::default()
+                })
+            });
+
+        mock_lightning_client.expect_create_invoice().never();
+
+        let service = CreateInvoiceService::new(
+            mock_lightning_client,
+            MockInvoiceRepositoryTrait::new(),
+            mock_gambling_limit_repository,
+        );
+
+        let result = service
+            .execute(
+                Uuid::new_v4(),
+                CreateInvoice {
+                    amount: Sats::new(1000),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+        assert!(result.is_err());
+    }
+}
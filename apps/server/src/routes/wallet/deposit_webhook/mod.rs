@@ -0,0 +1,52 @@
+use crate::http::Result;
+use crate::repositories::InvoiceRepository;
+use crate::Env;
+use aide::transform::TransformOperation;
+use axum::{extract::Query, Json};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::DepositWebhookService;
+
+use crate::http::GenericError;
+
+mod service;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InvoiceSettled {
+    payment_request: String,
+}
+
+/// The webhook URL registered with the Lightning backend carries the secret.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WebhookQuery {
+    secret: String,
+}
+
+fn resource() -> DepositWebhookService<Client, InvoiceRepository> {
+    DepositWebhookService::new(
+        Client::new(),
+        InvoiceRepository::new(),
+        Env::get().deposit_webhook_secret,
+    )
+}
+
+pub async fn route(
+    Query(WebhookQuery { secret }): Query<WebhookQuery>,
+    Json(payload): Json<InvoiceSettled>,
+) -> Result<()> {
+    let deposit_webhook_service = resource();
+
+    deposit_webhook_service
+        .execute(&secret, &payload.payment_request)
+        .await
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Deposit Webhook Handler")
+        .description("Confirms deposit")
+        .response::<200, ()>()
+        .response::<400, Json<GenericError>>()
+        .response::<401, Json<GenericError>>()
+        .response::<404, Json<GenericError>>()
+}
@@ -0,0 +1,140 @@
+use crate::bad_req;
+use crate::http::{Error, LightningClient, PaymentStatus, Result};
+use crate::models::DecodedInvoice;
+use crate::repositories::InvoiceRepositoryTrait;
+use sha2::{Digest, Sha256};
+
+pub struct DepositWebhookService<L: LightningClient, R: InvoiceRepositoryTrait> {
+    lightning_client: L,
+    invoice_repository: R,
+    secret: String,
+}
+
+/// Compares the digests, so the time taken says nothing about the secret.
+fn secret_matches(given: &str, expected: &str) -> bool {
+    let given = Sha256::digest(given.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+
+    given
+        .iter()
+        .zip(expected.iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+impl<L: LightningClient, R: InvoiceRepositoryTrait> DepositWebhookService<L, R> {
+    pub fn new(lightning_client: L, invoice_repository: R, secret: String) -> Self {
+        Self {
+            lightning_client,
+            invoice_repository,
+            secret,
+        }
+    }
+
+    /// Credits the deposit of `payment_request` once the node confirms it
+    /// was paid. The webhook only tells which invoice to look at.
+    pub async fn execute(&self, secret: &str, payment_request: &str) -> Result<()> {
+        if !secret_matches(secret, &self.secret) {
+            return Err(Error::Unauthorized {
+                message: String::from("Invalid webhook secret!"),
+            });
+        }
+
+        let invoice = DecodedInvoice::decode(payment_request)?;
+
+        let status = self
+            .lightning_client
+            .get_invoice(invoice.payment_hash.clone())
+            .await?;
+
+        if status != PaymentStatus::Settled {
+            return bad_req!("Invoice is not paid");
+        }
+
+        self.invoice_repository
+            .settle_invoice(invoice.payment_hash)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::MockLightningClient;
+    use crate::repositories::MockInvoiceRepositoryTrait;
+
+    // BOLT11 specification test vector for 250000 sats.
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+    const PAYMENT_HASH: &str = "0001020304050607080900010203040506070809000102030405060708090102";
+    const SECRET: &str = "webhook-secret";
+
+    #[tokio::test]
+    async fn test_paid_invoice_is_credited() {
+        let mut mock_lightning_client = MockLightningClient::new();
+        let mut mock_invoice_repository = MockInvoiceRepositoryTrait::new();
+
+        mock_lightning_client
+            .expect_get_invoice()
+            .once()
+            .withf(|payment_hash| payment_hash == PAYMENT_HASH)
+            .returning(|_| Ok(PaymentStatus::Settled));
+
+        mock_invoice_repository
+            .expect_settle_invoice()
+            .once()
+            .withf(|payment_hash| payment_hash == PAYMENT_HASH)
+            .returning(|_| Ok(true));
+
+        let service = DepositWebhookService::new(
+            mock_lightning_client,
+            mock_invoice_repository,
+            String::from(SECRET),
+        );
+
+        assert!(service.execute(SECRET, INVOICE).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unpaid_invoice_is_rejected() {
+        let mut mock_lightning_client = MockLightningClient::new();
+        let mut mock_invoice_repository = MockInvoiceRepositoryTrait::new();
+
+        mock_lightning_client
+            .expect_get_invoice()
+            .once()
+            .returning(|_| Ok(PaymentStatus::Pending));
+
+        mock_invoice_repository.expect_settle_invoice().never();
+
+        let service = DepositWebhookService::new(
+            mock_lightning_client,
+            mock_invoice_repository,
+            String::from(SECRET),
+        );
+
+        let result = service.execute(SECRET, INVOICE).await;
+
+        assert!(matches!(result, Err(Error::BadRequest { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_secret_is_rejected() {
+        let mut mock_lightning_client = MockLightningClient::new();
+        let mut mock_invoice_repository = MockInvoiceRepositoryTrait::new();
+
+        mock_lightning_client.expect_get_invoice().never();
+        mock_invoice_repository.expect_settle_invoice().never();
+
+        let service = DepositWebhookService::new(
+            mock_lightning_client,
+            mock_invoice_repository,
+            String::from(SECRET),
+        );
+
+        let result = service.execute("guess", INVOICE).await;
+
+        assert!(matches!(result, Err(Error::Unauthorized { .. })));
+    }
+}
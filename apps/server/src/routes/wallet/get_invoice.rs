@@ -0,0 +1,59 @@
+use crate::http::{Error, GenericError, Result};
+use crate::models::{AuthUser, InvoiceStatus, Msat};
+use crate::repositories::{InvoiceRepository, InvoiceRepositoryTrait};
+use aide::transform::TransformOperation;
+use axum::{extract::Path, Json};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PaymentHash {
+    payment_hash: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InvoiceStatusBody {
+    payment_hash: String,
+    invoice: String,
+    #[serde(rename = "amount_msat")]
+    amount: Msat,
+    status: InvoiceStatus,
+    expires_at: DateTime<Utc>,
+    paid_at: Option<DateTime<Utc>>,
+}
+
+fn resource() -> InvoiceRepository {
+    InvoiceRepository::new()
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Path(PaymentHash { payment_hash }): Path<PaymentHash>,
+) -> Result<Json<InvoiceStatusBody>> {
+    let invoice_repository = resource();
+
+    let invoice = invoice_repository.get_invoice(payment_hash).await?;
+
+    if invoice.user_id != auth_user.user_id {
+        return Err(Error::NotFound {
+            message: String::from("Invoice not found!"),
+        });
+    }
+
+    Ok(Json(InvoiceStatusBody {
+        payment_hash: invoice.payment_hash,
+        invoice: invoice.payment_request,
+        amount: invoice.amount,
+        status: invoice.status,
+        expires_at: invoice.expires_at,
+        paid_at: invoice.paid_at,
+    }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Check Invoice")
+        .description("Check whether a deposit invoice is pending, paid or expired")
+        .response::<200, Json<InvoiceStatusBody>>()
+        .response::<404, Json<GenericError>>()
+}
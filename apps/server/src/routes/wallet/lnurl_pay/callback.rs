@@ -0,0 +1,31 @@
+use crate::models::{LnurlResponse, Msat, PayRequestInvoice};
+use aide::transform::TransformOperation;
+use axum::{
+    extract::{Path, Query},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{resource, Username};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AmountQuery {
+    /// Amount in millisatoshis.
+    amount: Msat,
+}
+
+pub async fn route(
+    Path(Username { username }): Path<Username>,
+    Query(AmountQuery { amount }): Query<AmountQuery>,
+) -> Json<LnurlResponse<PayRequestInvoice>> {
+    let lnurl_pay_service = resource();
+
+    Json(lnurl_pay_service.callback(username, amount).await.into())
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("LNURL")
+        .description("LNURL-pay callback returning the invoice to pay (LUD-06)")
+        .response::<200, Json<LnurlResponse<PayRequestInvoice>>>()
+}
@@ -0,0 +1,32 @@
+use crate::http::{GenericError, Result};
+use crate::models::AuthUser;
+use aide::transform::TransformOperation;
+use axum::Json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::resource;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct LnurlBody {
+    lnurl: String,
+    lightning_address: Option<String>,
+}
+
+pub async fn route(auth_user: AuthUser) -> Result<Json<LnurlBody>> {
+    let lnurl_pay_service = resource();
+
+    let (lnurl, lightning_address) = lnurl_pay_service.link(auth_user.user_id).await?;
+
+    Ok(Json(LnurlBody {
+        lnurl,
+        lightning_address,
+    }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("LNURL")
+        .description("Get the static LNURL-pay code and Lightning Address that deposit into the logged user's account")
+        .response::<200, Json<LnurlBody>>()
+        .response::<404, Json<GenericError>>()
+}
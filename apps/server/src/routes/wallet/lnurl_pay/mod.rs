@@ -0,0 +1,47 @@
+use crate::models::{LnurlResponse, PayRequest};
+use crate::repositories::{GamblingLimitRepository, InvoiceRepository, UserRepository};
+use crate::Env;
+use aide::transform::TransformOperation;
+use axum::{extract::Path, Json};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::LnurlPayService;
+
+use super::create_invoice;
+
+pub mod callback;
+pub mod link;
+mod service;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Username {
+    username: String,
+}
+
+fn resource() -> LnurlPayService<UserRepository, Client, InvoiceRepository, GamblingLimitRepository>
+{
+    LnurlPayService::new(
+        UserRepository::new(),
+        create_invoice::resource(),
+        Env::get().public_url,
+    )
+}
+
+pub async fn route(Path(Username { username }): Path<Username>) -> Json<LnurlResponse<PayRequest>> {
+    let lnurl_pay_service = resource();
+
+    Json(lnurl_pay_service.pay_request(username).await.into())
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("LNURL")
+        .description("LNURL-pay request to deposit into an account (LUD-06)")
+        .response::<200, Json<LnurlResponse<PayRequest>>>()
+}
+
+pub fn lightning_address_docs(op: TransformOperation) -> TransformOperation {
+    op.tag("LNURL")
+        .description("Lightning Address pay request for `username@domain` (LUD-16)")
+        .response::<200, Json<LnurlResponse<PayRequest>>>()
+}
@@ -0,0 +1,259 @@
+use crate::bad_req;
+use crate::http::{CreateInvoice, Error, LightningClient, Result};
+use crate::models::{
+    description_hash, encode_lnurl, lightning_address, pay_metadata, public_url, LnurlTag, Msat,
+    PayRequest, PayRequestInvoice,
+};
+use crate::repositories::{
+    GamblingLimitRepositoryTrait, InvoiceRepositoryTrait, UserRepositoryTrait,
+};
+use uuid::Uuid;
+
+use super::super::create_invoice::service::CreateInvoiceService;
+
+pub const MIN_SENDABLE: Msat = Msat::new(1_000);
+pub const MAX_SENDABLE: Msat = Msat::new(1_000_000_000);
+
+pub struct LnurlPayService<
+    U: UserRepositoryTrait,
+    L: LightningClient,
+    R: InvoiceRepositoryTrait,
+    G: GamblingLimitRepositoryTrait,
+> {
+    user_repository: U,
+    create_invoice_service: CreateInvoiceService<L, R, G>,
+    public_url: String,
+}
+
+impl<
+        U: UserRepositoryTrait,
+        L: LightningClient,
+        R: InvoiceRepositoryTrait,
+        G: GamblingLimitRepositoryTrait,
+    > LnurlPayService<U, L, R, G>
+{
+    pub fn new(
+        user_repository: U,
+        create_invoice_service: CreateInvoiceService<L, R, G>,
+        public_url: String,
+    ) -> Self {
+        Self {
+            user_repository,
+            create_invoice_service,
+            public_url,
+        }
+    }
+
+    /// Both the LNURL and the Lightning Address resolve to the same pay
+    /// request, so its metadata always carries the address when there is one.
+    fn metadata(&self, username: &str) -> String {
+        pay_metadata(
+            &format!("Deposit to {username} on Chesu"),
+            lightning_address(&self.public_url, username).as_deref(),
+        )
+    }
+
+    /// Static LNURL wallets can scan to fund the account of `user_id`, and its
+    /// Lightning Address if the username allows one.
+    pub async fn link(&self, user_id: Uuid) -> Result<(String, Option<String>)> {
+        let user = self.user_repository.find_by_id(user_id).await?;
+
+        let lnurl = encode_lnurl(&public_url(
+            &self.public_url,
+            &["lnurl", "pay", &user.username],
+        )?)?;
+
+        Ok((lnurl, lightning_address(&self.public_url, &user.username)))
+    }
+
+    pub async fn pay_request(&self, username: String) -> Result<PayRequest> {
+        let user = self.user_repository.find_by_username(username).await?;
+
+        Ok(PayRequest {
+            tag: LnurlTag::PayRequest,
+            callback: public_url(
+                &self.public_url,
+                &["lnurl", "pay", &user.username, "callback"],
+            )?,
+            min_sendable: MIN_SENDABLE,
+            max_sendable: MAX_SENDABLE,
+            metadata: self.metadata(&user.username),
+        })
+    }
+
+    /// Creates an invoice committing to the pay request metadata, credited to
+    /// the user once paid.
+    pub async fn callback(&self, username: String, amount: Msat) -> Result<PayRequestInvoice> {
+        if !(MIN_SENDABLE..=MAX_SENDABLE).contains(&amount) {
+            return bad_req!(format!(
+                "Amount must be between {} and {} sats",
+                MIN_SENDABLE.ceil_sats(),
+                MAX_SENDABLE.floor_sats()
+            ));
+        }
+
+        let amount = amount.to_sats()?;
+
+        let user = self.user_repository.find_by_username(username).await?;
+
+        let invoice = self
+            .create_invoice_service
+            .execute(
+                user.id,
+                CreateInvoice {
+                    amount,
+                    description_hash: Some(description_hash(&self.metadata(&user.username))),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        Ok(PayRequestInvoice {
+            pr: invoice.payment_request,
+            routes: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::MockLightningClient;
+    use crate::models::{PlayerProtection, Sats, User};
+    use crate::repositories::{
+        MockGamblingLimitRepositoryTrait, MockInvoiceRepositoryTrait, MockUserRepositoryTrait,
+    };
+    use uuid::uuid;
+
+    // BOLT11 specification test vector for 250000 sats.
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+    fn user() -> User {
+        User {
+            id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+            username: String::from("alice"),
+            email: String::from("alice@chesu.com"),
+            hashed_password: String::new(),
+            balance: Msat::ZERO,
+        }
+    }
+
+    fn service(
+        mock_user_repository: MockUserRepositoryTrait,
+        mock_lightning_client: MockLightningClient,
+        mock_invoice_repository: MockInvoiceRepositoryTrait,
+    ) -> LnurlPayService<
+        MockUserRepositoryTrait,
+        MockLightningClient,
+        MockInvoiceRepositoryTrait,
+        MockGamblingLimitRepositoryTrait,
+    > {
+        let mut mock_gambling_limit_repository = MockGamblingLimitRepositoryTrait::new();
+
+        mock_gambling_limit_repository
+            .expect_get_protection()
+            .returning(|_| Ok(PlayerProtection::default()));
+
+        LnurlPayService::new(
+            mock_user_repository,
+            CreateInvoiceService::new(
+                mock_lightning_client,
+                mock_invoice_repository,
+                mock_gambling_limit_repository,
+            ),
+            String::from("https://chesu.com"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_pay_request() {
+        let mut mock_user_repository = MockUserRepositoryTrait::new();
+
+        mock_user_repository
+            .expect_find_by_username()
+            .once()
+            .returning(|_| Ok(user()));
+
+        let service = service(
+            mock_user_repository,
+            MockLightningClient::new(),
+            MockInvoiceRepositoryTrait::new(),
+        );
+
+        let result = service.pay_request(String::from("ALICE")).await.unwrap();
+
+        assert_eq!(
+            result.callback,
+            "https://chesu.com/lnurl/pay/alice/callback"
+        );
+        assert_eq!(
+            result.metadata,
+            r#"[["text/plain","Deposit to alice on Chesu"],["text/identifier","alice@chesu.com"]]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_callback_commits_to_metadata() {
+        let mut mock_user_repository = MockUserRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+        let mut mock_invoice_repository = MockInvoiceRepositoryTrait::new();
+
+        mock_user_repository
+            .expect_find_by_username()
+            .once()
+            .returning(|_| Ok(user()));
+
+        mock_lightning_client
+            .expect_create_invoice()
+            .once()
+            .withf(|request| {
+                request.amount == Sats::new(250_000)
+                    && request.description_hash
+                        == Some(description_hash(
+                            r#"[["text/plain","Deposit to alice on Chesu"],["text/identifier","alice@chesu.com"]]"#,
+                        ))
+            })
+            .returning(|_| Ok(String::from(INVOICE)));
+
+        mock_invoice_repository
+            .expect_save_invoice()
+            .once()
+            .withf(|user_id, _| user_id == &uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"))
+            .returning(|_, _| Ok(()));
+
+        let service = service(
+            mock_user_repository,
+            mock_lightning_client,
+            mock_invoice_repository,
+        );
+
+        let result = service
+            .callback(String::from("alice"), Msat::new(250_000_000))
+            .await
+            .unwrap();
+
+        assert_eq!(result.pr, INVOICE);
+    }
+
+    #[tokio::test]
+    async fn test_callback_rejects_fractional_sats() {
+        let mut mock_user_repository = MockUserRepositoryTrait::new();
+
+        mock_user_repository.expect_find_by_username().never();
+
+        let service = service(
+            mock_user_repository,
+            MockLightningClient::new(),
+            MockInvoiceRepositoryTrait::new(),
+        );
+
+        assert!(service
+            .callback(String::from("alice"), Msat::new(1_500))
+            .await
+            .is_err());
+        assert!(service
+            .callback(String::from("alice"), Msat::ZERO)
+            .await
+            .is_err());
+    }
+}
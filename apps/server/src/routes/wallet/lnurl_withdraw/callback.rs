@@ -0,0 +1,30 @@
+use crate::models::LnurlStatus;
+use aide::transform::TransformOperation;
+use axum::{extract::Query, Json};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::resource;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WithdrawCallbackQuery {
+    k1: String,
+    pr: String,
+}
+
+pub async fn route(
+    Query(WithdrawCallbackQuery { k1, pr }): Query<WithdrawCallbackQuery>,
+) -> Json<LnurlStatus> {
+    let lnurl_withdraw_service = resource();
+
+    Json(match lnurl_withdraw_service.callback(k1, pr).await {
+        Ok(()) => LnurlStatus::Ok,
+        Err(error) => error.into(),
+    })
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("LNURL")
+        .description("LNURL-withdraw callback paying the wallet's invoice (LUD-03)")
+        .response::<200, Json<LnurlStatus>>()
+}
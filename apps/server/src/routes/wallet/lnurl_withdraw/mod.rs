@@ -0,0 +1,66 @@
+use crate::http::{GenericError, Result};
+use crate::models::{AuthUser, Msat, Sats};
+use crate::repositories::{WalletRepository, WithdrawLinkRepository, WithdrawalRepository};
+use crate::Env;
+use aide::transform::TransformOperation;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::LnurlWithdrawService;
+
+use super::withdraw;
+
+pub mod callback;
+pub mod request;
+mod service;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AmountBody {
+    amount: Sats,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WithdrawLinkBody {
+    lnurl: String,
+    k1: String,
+    #[serde(rename = "amount_msat")]
+    amount: Msat,
+    expires_at: DateTime<Utc>,
+}
+
+fn resource(
+) -> LnurlWithdrawService<WithdrawLinkRepository, WalletRepository, Client, WithdrawalRepository> {
+    LnurlWithdrawService::new(
+        WithdrawLinkRepository::new(),
+        WalletRepository::new(),
+        withdraw::resource(),
+        Env::get().public_url,
+    )
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Json(payload): Json<AmountBody>,
+) -> Result<Json<WithdrawLinkBody>> {
+    let lnurl_withdraw_service = resource();
+
+    let (lnurl, link) = lnurl_withdraw_service
+        .create_link(auth_user.user_id, payload.amount)
+        .await?;
+
+    Ok(Json(WithdrawLinkBody {
+        lnurl,
+        k1: link.k1,
+        amount: link.amount,
+        expires_at: link.expires_at,
+    }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("LNURL")
+        .description("Create a one-time LNURL-withdraw link for the logged user (LUD-03)")
+        .response::<200, Json<WithdrawLinkBody>>()
+        .response::<400, Json<GenericError>>()
+}
@@ -0,0 +1,24 @@
+use crate::models::{LnurlResponse, WithdrawRequest};
+use aide::transform::TransformOperation;
+use axum::{extract::Path, Json};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::resource;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct K1 {
+    k1: String,
+}
+
+pub async fn route(Path(K1 { k1 }): Path<K1>) -> Json<LnurlResponse<WithdrawRequest>> {
+    let lnurl_withdraw_service = resource();
+
+    Json(lnurl_withdraw_service.withdraw_request(k1).await.into())
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("LNURL")
+        .description("LNURL-withdraw request of a withdraw link (LUD-03)")
+        .response::<200, Json<LnurlResponse<WithdrawRequest>>>()
+}
@@ -0,0 +1,307 @@
+use crate::bad_req;
+use crate::http::{Error, LightningClient, Result};
+use crate::models::{
+    encode_lnurl, public_url, DecodedInvoice, LnurlTag, Msat, Sats, WithdrawRequest,
+};
+use crate::repositories::{
+    SaveWithdrawLink, WalletRepositoryTrait, WithdrawLinkRepositoryTrait, WithdrawalRepositoryTrait,
+};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use super::super::withdraw::service::{WithdrawInput, WithdrawService};
+
+const LINK_TTL_MINUTES: i64 = 10;
+
+pub struct LnurlWithdrawService<
+    K: WithdrawLinkRepositoryTrait,
+    R: WalletRepositoryTrait,
+    L: LightningClient,
+    W: WithdrawalRepositoryTrait,
+> {
+    withdraw_link_repository: K,
+    wallet_repository: R,
+    withdraw_service: WithdrawService<R, L, W>,
+    public_url: String,
+}
+
+fn new_k1() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+impl<
+        K: WithdrawLinkRepositoryTrait,
+        R: WalletRepositoryTrait,
+        L: LightningClient,
+        W: WithdrawalRepositoryTrait,
+    > LnurlWithdrawService<K, R, L, W>
+{
+    pub fn new(
+        withdraw_link_repository: K,
+        wallet_repository: R,
+        withdraw_service: WithdrawService<R, L, W>,
+        public_url: String,
+    ) -> Self {
+        Self {
+            withdraw_link_repository,
+            wallet_repository,
+            withdraw_service,
+            public_url,
+        }
+    }
+
+    /// Creates a one-time link the user's wallet can pull up to `amount` sats
+    /// from. Returns the LNURL with the saved link.
+    pub async fn create_link(
+        &self,
+        user_id: Uuid,
+        amount: Sats,
+    ) -> Result<(String, SaveWithdrawLink)> {
+        let amount = amount.to_msat()?;
+        let balance = self.wallet_repository.get_balance(user_id).await?;
+
+        if !amount.is_positive() || amount > balance {
+            return bad_req!("Invalid withdraw amount");
+        }
+
+        let link = SaveWithdrawLink {
+            user_id,
+            k1: new_k1(),
+            amount,
+            expires_at: Utc::now() + Duration::minutes(LINK_TTL_MINUTES),
+        };
+
+        self.withdraw_link_repository
+            .save_withdraw_link(link.clone())
+            .await?;
+
+        let lnurl = encode_lnurl(&public_url(
+            &self.public_url,
+            &["lnurl", "withdraw", &link.k1],
+        )?)?;
+
+        Ok((lnurl, link))
+    }
+
+    pub async fn withdraw_request(&self, k1: String) -> Result<WithdrawRequest> {
+        let link = self.withdraw_link_repository.get_withdraw_link(k1).await?;
+
+        if !link.is_usable() {
+            return bad_req!("Withdraw link is expired or was already used");
+        }
+
+        Ok(WithdrawRequest {
+            tag: LnurlTag::WithdrawRequest,
+            callback: public_url(&self.public_url, &["lnurl", "withdraw", "callback"])?,
+            k1: link.k1,
+            default_description: String::from("Chesu withdrawal"),
+            min_withdrawable: Msat::new(1_000),
+            max_withdrawable: link.amount,
+        })
+    }
+
+    /// Pays `pr` from the link owner's balance. The link is used up only if
+    /// the withdrawal goes through or waits for review.
+    pub async fn callback(&self, k1: String, pr: String) -> Result<()> {
+        let invoice = DecodedInvoice::decode(&pr)?;
+
+        let Some(link) = self
+            .withdraw_link_repository
+            .claim_withdraw_link(k1.clone())
+            .await?
+        else {
+            return bad_req!("Withdraw link is expired or was already used");
+        };
+
+        let result = if !invoice.amount.is_positive() || invoice.amount > link.amount {
+            bad_req!(format!(
+                "Invoice amount must be between 1 and {} sats",
+                link.amount.floor_sats()
+            ))
+        } else {
+            self.withdraw_service
+                .execute(WithdrawInput {
+                    user_id: link.user_id,
+                    amount: invoice.amount,
+                    invoice: invoice.payment_request,
+                })
+                .await
+                .map(|_| ())
+        };
+
+        if result.is_err() {
+            self.withdraw_link_repository
+                .release_withdraw_link(k1)
+                .await?;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::MockLightningClient;
+    use crate::models::WithdrawalLimits;
+    use crate::repositories::{
+        MockWalletRepositoryTrait, MockWithdrawLinkRepositoryTrait, MockWithdrawalRepositoryTrait,
+        WithdrawLink,
+    };
+    use uuid::uuid;
+
+    // BOLT11 specification test vector for 250000 sats.
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+    fn link(amount: i64) -> WithdrawLink {
+        WithdrawLink {
+            user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+            k1: String::from("k1"),
+            amount: Msat::new(amount),
+            expires_at: Utc::now() + Duration::minutes(1),
+            used_at: None,
+        }
+    }
+
+    fn service(
+        mock_withdraw_link_repository: MockWithdrawLinkRepositoryTrait,
+        mock_wallet_repository: MockWalletRepositoryTrait,
+        mock_lightning_client: MockLightningClient,
+    ) -> LnurlWithdrawService<
+        MockWithdrawLinkRepositoryTrait,
+        MockWalletRepositoryTrait,
+        MockLightningClient,
+        MockWithdrawalRepositoryTrait,
+    > {
+        let mut mock_withdrawal_repository = MockWithdrawalRepositoryTrait::new();
+
+        mock_withdrawal_repository
+            .expect_debit_withdrawal()
+            .returning(|_, _, _, _| Ok(None));
+
+        LnurlWithdrawService::new(
+            mock_withdraw_link_repository,
+            MockWalletRepositoryTrait::new(),
+            WithdrawService::new(
+                mock_wallet_repository,
+                mock_lightning_client,
+                mock_withdrawal_repository,
+                WithdrawalLimits::default(),
+            ),
+            String::from("https://chesu.com"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_request() {
+        let mut mock_withdraw_link_repository = MockWithdrawLinkRepositoryTrait::new();
+
+        mock_withdraw_link_repository
+            .expect_get_withdraw_link()
+            .once()
+            .returning(|_| Ok(link(100_000)));
+
+        let service = service(
+            mock_withdraw_link_repository,
+            MockWalletRepositoryTrait::new(),
+            MockLightningClient::new(),
+        );
+
+        let result = service.withdraw_request(String::from("k1")).await.unwrap();
+
+        assert_eq!(result.callback, "https://chesu.com/lnurl/withdraw/callback");
+        assert_eq!(result.max_withdrawable, Msat::new(100_000));
+    }
+
+    #[tokio::test]
+    async fn test_callback_pays_invoice() {
+        let mut mock_withdraw_link_repository = MockWithdrawLinkRepositoryTrait::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_withdraw_link_repository
+            .expect_claim_withdraw_link()
+            .once()
+            .returning(|_| Ok(Some(link(300_000_000))));
+
+        mock_withdraw_link_repository
+            .expect_release_withdraw_link()
+            .never();
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(300_000_000)));
+
+        mock_lightning_client
+            .expect_pay_invoice()
+            .once()
+            .returning(|_| Ok(()));
+
+        let service = service(
+            mock_withdraw_link_repository,
+            mock_wallet_repository,
+            mock_lightning_client,
+        );
+
+        let result = service
+            .callback(String::from("k1"), String::from(INVOICE))
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_callback_above_link_amount_releases_link() {
+        let mut mock_withdraw_link_repository = MockWithdrawLinkRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_withdraw_link_repository
+            .expect_claim_withdraw_link()
+            .once()
+            .returning(|_| Ok(Some(link(100_000))));
+
+        mock_withdraw_link_repository
+            .expect_release_withdraw_link()
+            .once()
+            .returning(|_| Ok(()));
+
+        mock_lightning_client.expect_pay_invoice().never();
+
+        let service = service(
+            mock_withdraw_link_repository,
+            MockWalletRepositoryTrait::new(),
+            mock_lightning_client,
+        );
+
+        let result = service
+            .callback(String::from("k1"), String::from(INVOICE))
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_callback_used_link() {
+        let mut mock_withdraw_link_repository = MockWithdrawLinkRepositoryTrait::new();
+
+        mock_withdraw_link_repository
+            .expect_claim_withdraw_link()
+            .once()
+            .returning(|_| Ok(None));
+
+        let service = service(
+            mock_withdraw_link_repository,
+            MockWalletRepositoryTrait::new(),
+            MockLightningClient::new(),
+        );
+
+        let result = service
+            .callback(String::from("k1"), String::from(INVOICE))
+            .await;
+
+        assert!(result.is_err());
+    }
+}
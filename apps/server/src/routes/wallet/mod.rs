@@ -6,7 +6,12 @@ use aide::axum::{
 mod check_invoice;
 mod create_invoice;
 mod deposit_webhook;
-mod withdraw;
+mod get_invoice;
+mod lnurl_pay;
+mod lnurl_withdraw;
+mod transactions;
+pub(crate) mod withdraw;
+mod withdraw_to_address;
 
 pub fn router() -> ApiRouter {
     ApiRouter::new()
@@ -18,12 +23,58 @@ pub fn router() -> ApiRouter {
             "/invoice/check",
             get_with(check_invoice::route, check_invoice::docs),
         )
+        .api_route(
+            "/invoice/:payment_hash",
+            get_with(get_invoice::route, get_invoice::docs),
+        )
         .api_route(
             "/invoice/withdraw",
             post_with(withdraw::route, withdraw::docs),
         )
+        .api_route(
+            "/wallet/withdraw",
+            post_with(withdraw_to_address::route, withdraw_to_address::docs),
+        )
         .api_route(
             "/invoice/settled",
             post_with(deposit_webhook::route, deposit_webhook::docs),
         )
+        .api_route(
+            "/lnurl/pay",
+            get_with(lnurl_pay::link::route, lnurl_pay::link::docs),
+        )
+        .api_route(
+            "/lnurl/pay/:username",
+            get_with(lnurl_pay::route, lnurl_pay::docs),
+        )
+        .api_route(
+            "/lnurl/pay/:username/callback",
+            get_with(lnurl_pay::callback::route, lnurl_pay::callback::docs),
+        )
+        .api_route(
+            "/.well-known/lnurlp/:username",
+            get_with(lnurl_pay::route, lnurl_pay::lightning_address_docs),
+        )
+        .api_route(
+            "/lnurl/withdraw",
+            post_with(lnurl_withdraw::route, lnurl_withdraw::docs),
+        )
+        .api_route(
+            "/lnurl/withdraw/callback",
+            get_with(
+                lnurl_withdraw::callback::route,
+                lnurl_withdraw::callback::docs,
+            ),
+        )
+        .api_route(
+            "/lnurl/withdraw/:k1",
+            get_with(
+                lnurl_withdraw::request::route,
+                lnurl_withdraw::request::docs,
+            ),
+        )
+        .api_route(
+            "/wallet/transactions",
+            get_with(transactions::route, transactions::docs),
+        )
 }
@@ -0,0 +1,64 @@
+use crate::http::{GenericError, Result};
+use crate::models::AuthUser;
+use crate::repositories::{Transaction, TransferKind, WalletRepository};
+use aide::transform::TransformOperation;
+use axum::{extract::Query, Json};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::{ListTransactionsInput, ListTransactionsService, TransactionsPage};
+
+mod service;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct TransactionsQuery {
+    #[serde(rename = "type")]
+    kind: Option<TransferKind>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TransactionsBody {
+    transactions: Vec<Transaction>,
+    next_cursor: Option<String>,
+}
+
+fn resource() -> ListTransactionsService<WalletRepository> {
+    ListTransactionsService::new(WalletRepository::new())
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Query(query): Query<TransactionsQuery>,
+) -> Result<Json<TransactionsBody>> {
+    let list_transactions_service = resource();
+
+    let TransactionsPage {
+        transactions,
+        next_cursor,
+    } = list_transactions_service
+        .execute(ListTransactionsInput {
+            user_id: auth_user.user_id,
+            kind: query.kind,
+            from: query.from,
+            to: query.to,
+            cursor: query.cursor,
+            limit: query.limit,
+        })
+        .await?;
+
+    Ok(Json(TransactionsBody {
+        transactions,
+        next_cursor,
+    }))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Wallet Transactions")
+        .description("List wallet transactions, newest first")
+        .response::<200, Json<TransactionsBody>>()
+        .response::<400, Json<GenericError>>()
+}
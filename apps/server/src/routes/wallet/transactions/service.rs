@@ -0,0 +1,239 @@
+use crate::bad_req;
+use crate::http::{Error, Result};
+use crate::repositories::{Transaction, TransactionFilter, TransferKind, WalletRepositoryTrait};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+pub struct ListTransactionsService<R: WalletRepositoryTrait> {
+    wallet_repository: R,
+}
+
+pub struct ListTransactionsInput {
+    pub user_id: Uuid,
+    pub kind: Option<TransferKind>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+pub struct TransactionsPage {
+    pub transactions: Vec<Transaction>,
+    pub next_cursor: Option<String>,
+}
+
+fn encode_cursor(transaction: &Transaction) -> String {
+    format!(
+        "{}_{}",
+        transaction.created_at.timestamp_micros(),
+        transaction.id
+    )
+}
+
+fn decode_cursor(cursor: &str) -> Option<(DateTime<Utc>, Uuid)> {
+    let (created_at, id) = cursor.split_once('_')?;
+
+    Some((
+        DateTime::from_timestamp_micros(created_at.parse().ok()?)?,
+        Uuid::parse_str(id).ok()?,
+    ))
+}
+
+impl<R: WalletRepositoryTrait> ListTransactionsService<R> {
+    pub fn new(wallet_repository: R) -> Self {
+        Self { wallet_repository }
+    }
+
+    pub async fn execute(
+        &self,
+        ListTransactionsInput {
+            user_id,
+            kind,
+            from,
+            to,
+            cursor,
+            limit,
+        }: ListTransactionsInput,
+    ) -> Result<TransactionsPage> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return bad_req!(format!("Limit must be between 1 and {MAX_LIMIT}"));
+        }
+
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return bad_req!("Invalid date range");
+            }
+        }
+
+        let before = match cursor {
+            Some(cursor) => Some(decode_cursor(&cursor).ok_or(Error::BadRequest {
+                message: String::from("Invalid cursor"),
+            })?),
+            None => None,
+        };
+
+        let mut transactions = self
+            .wallet_repository
+            .list_transactions(TransactionFilter {
+                user_id,
+                kind,
+                from,
+                to,
+                before,
+                limit: limit + 1,
+            })
+            .await?;
+
+        let next_cursor = if transactions.len() as i64 > limit {
+            transactions.truncate(limit as usize);
+            transactions.last().map(encode_cursor)
+        } else {
+            None
+        };
+
+        Ok(TransactionsPage {
+            transactions,
+            next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Msat;
+    use crate::repositories::MockWalletRepositoryTrait;
+    use uuid::uuid;
+
+    fn transaction(micros: i64) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            transfer_id: Uuid::new_v4(),
+            kind: TransferKind::Deposit,
+            amount: Msat::new(100_000),
+            balance: Msat::new(100_000),
+            game_id: None,
+            invoice: Some(String::from("lnbc1")),
+            created_at: DateTime::from_timestamp_micros(micros).unwrap(),
+        }
+    }
+
+    fn input() -> ListTransactionsInput {
+        ListTransactionsInput {
+            user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+            kind: None,
+            from: None,
+            to: None,
+            cursor: None,
+            limit: Some(2),
+        }
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let transaction = transaction(1_700_000_000_123_456);
+
+        assert_eq!(
+            decode_cursor(&encode_cursor(&transaction)),
+            Some((transaction.created_at, transaction.id))
+        );
+        assert_eq!(decode_cursor("not-a-cursor"), None);
+    }
+
+    #[tokio::test]
+    async fn test_list_transactions_next_page() {
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+
+        mock_wallet_repository
+            .expect_list_transactions()
+            .once()
+            .withf(|filter| {
+                filter.user_id == uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a")
+                    && filter.limit == 3
+                    && filter.before.is_none()
+            })
+            .returning(|_| Ok(vec![transaction(3), transaction(2), transaction(1)]));
+
+        let service = ListTransactionsService::new(mock_wallet_repository);
+
+        let page = service.execute(input()).await.unwrap();
+
+        assert_eq!(page.transactions.len(), 2);
+        assert_eq!(
+            page.next_cursor.as_deref().and_then(decode_cursor),
+            Some((page.transactions[1].created_at, page.transactions[1].id))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_transactions_last_page() {
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+
+        mock_wallet_repository
+            .expect_list_transactions()
+            .once()
+            .withf(|filter| {
+                filter.kind == Some(TransferKind::Payout)
+                    && filter.before
+                        == Some((
+                            DateTime::from_timestamp_micros(5).unwrap(),
+                            uuid!("8734278b-1363-42d1-8c24-c13214d23b0b"),
+                        ))
+            })
+            .returning(|_| Ok(vec![transaction(4)]));
+
+        let service = ListTransactionsService::new(mock_wallet_repository);
+
+        let page = service
+            .execute(ListTransactionsInput {
+                kind: Some(TransferKind::Payout),
+                cursor: Some(String::from("5_8734278b-1363-42d1-8c24-c13214d23b0b")),
+                ..input()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(page.transactions.len(), 1);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_transactions_invalid_input() {
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+
+        mock_wallet_repository.expect_list_transactions().never();
+
+        let service = ListTransactionsService::new(mock_wallet_repository);
+
+        let invalid_limit = service
+            .execute(ListTransactionsInput {
+                limit: Some(0),
+                ..input()
+            })
+            .await;
+
+        let invalid_cursor = service
+            .execute(ListTransactionsInput {
+                cursor: Some(String::from("abc")),
+                ..input()
+            })
+            .await;
+
+        let invalid_range = service
+            .execute(ListTransactionsInput {
+                from: DateTime::from_timestamp(10, 0),
+                to: DateTime::from_timestamp(5, 0),
+                ..input()
+            })
+            .await;
+
+        assert!(invalid_limit.is_err());
+        assert!(invalid_cursor.is_err());
+        assert!(invalid_range.is_err());
+    }
+}
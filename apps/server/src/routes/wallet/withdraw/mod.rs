@@ -1,41 +1,41 @@
-use crate::http::Error;
 use crate::http::Result;
-use crate::models::AuthUser;
-use crate::repositories::WalletRepository;
+use crate::models::{AuthUser, DecodedInvoice};
+use crate::repositories::{WalletRepository, WithdrawalRepository};
+use crate::states::withdrawal_limits;
 use aide::transform::TransformOperation;
 use axum::Json;
-use lightning_invoice::Bolt11Invoice;
 use reqwest::Client;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use service::{WithdrawInput, WithdrawService};
-use std::str::FromStr;
+use service::{WithdrawInput, WithdrawService, WithdrawStatus};
 
 use crate::http::GenericError;
 
-mod service;
+pub(crate) mod service;
 
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct InvoiceBody {
     invoice: String,
 }
 
-fn resource() -> WithdrawService<WalletRepository, Client> {
-    WithdrawService::new(WalletRepository::new(), Client::new())
+pub(crate) fn resource() -> WithdrawService<WalletRepository, Client, WithdrawalRepository> {
+    WithdrawService::new(
+        WalletRepository::new(),
+        Client::new(),
+        WithdrawalRepository::new(),
+        withdrawal_limits::get(),
+    )
 }
 
-pub async fn route(auth_user: AuthUser, Json(payload): Json<InvoiceBody>) -> Result<()> {
+pub async fn route(
+    auth_user: AuthUser,
+    Json(payload): Json<InvoiceBody>,
+) -> Result<Json<WithdrawStatus>> {
     let withdraw_service = resource();
 
-    let amount = (Bolt11Invoice::from_str(&payload.invoice)
-        .map_err(|_| Error::BadRequest {
-            message: String::from("Invalid invoice input"),
-        })?
-        .amount_milli_satoshis()
-        .unwrap_or(0)
-        / 1000) as i32;
+    let amount = DecodedInvoice::decode(&payload.invoice)?.amount;
 
-    withdraw_service
+    let status = withdraw_service
         .execute(WithdrawInput {
             user_id: auth_user.user_id,
             amount,
@@ -43,12 +43,12 @@ pub async fn route(auth_user: AuthUser, Json(payload): Json<InvoiceBody>) -> Res
         })
         .await?;
 
-    Ok(())
+    Ok(Json(status))
 }
 
 pub fn docs(op: TransformOperation) -> TransformOperation {
     op.tag("Deposit Webhook Handler")
         .description("Confirms deposit payment")
-        .response::<200, ()>()
+        .response::<200, Json<WithdrawStatus>>()
         .response::<400, Json<GenericError>>()
 }
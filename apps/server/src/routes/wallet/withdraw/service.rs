@@ -1,32 +1,55 @@
 use crate::bad_req;
-use crate::http::HttpClient;
-use crate::http::{Error, Result};
-use crate::repositories::{SaveOutgoing, WalletRepositoryTrait};
+use crate::http::{Error, LightningClient, PaymentStatus, Result};
+use crate::models::{DecodedInvoice, Msat, WithdrawalLimits};
+use crate::repositories::{SaveTransfer, WalletRepositoryTrait, WithdrawalRepositoryTrait};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-pub struct WithdrawService<R: WalletRepositoryTrait, C: HttpClient> {
+pub struct WithdrawService<
+    R: WalletRepositoryTrait,
+    L: LightningClient,
+    W: WithdrawalRepositoryTrait,
+> {
     wallet_repository: R,
-    client: C,
+    lightning_client: L,
+    withdrawal_repository: W,
+    limits: WithdrawalLimits,
 }
 
 pub struct WithdrawInput {
     pub user_id: Uuid,
-    pub amount: i32,
+    pub amount: Msat,
     pub invoice: String,
 }
 
-#[derive(Serialize, Deserialize, JsonSchema)]
-struct InvoiceBody {
-    invoice: String,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WithdrawStatus {
+    Paid,
+    /// The amount is debited and is paid once an admin approves it.
+    PendingReview {
+        review_id: Uuid,
+    },
+    /// The amount is debited but the node did not say whether the payment
+    /// went through yet.
+    Pending,
 }
 
-impl<R: WalletRepositoryTrait, C: HttpClient> WithdrawService<R, C> {
-    pub fn new(wallet_repository: R, client: C) -> Self {
+impl<R: WalletRepositoryTrait, L: LightningClient, W: WithdrawalRepositoryTrait>
+    WithdrawService<R, L, W>
+{
+    pub fn new(
+        wallet_repository: R,
+        lightning_client: L,
+        withdrawal_repository: W,
+        limits: WithdrawalLimits,
+    ) -> Self {
         Self {
             wallet_repository,
-            client,
+            lightning_client,
+            withdrawal_repository,
+            limits,
         }
     }
 
@@ -37,32 +60,72 @@ impl<R: WalletRepositoryTrait, C: HttpClient> WithdrawService<R, C> {
             amount,
             invoice,
         }: WithdrawInput,
-    ) -> Result<()> {
+    ) -> Result<WithdrawStatus> {
         let balance = self.wallet_repository.get_balance(user_id).await?;
 
-        if amount <= 0 || amount > balance {
+        if !amount.is_positive() || amount > balance {
             return bad_req!("Invalid invoice input");
         }
 
-        self.client
-            .post("/payments/bolt11", &InvoiceBody { invoice })
+        let review_id = self
+            .withdrawal_repository
+            .debit_withdrawal(user_id, amount, invoice.clone(), self.limits.clone())
             .await?;
 
-        self.wallet_repository
-            .save_outgoing(SaveOutgoing { user_id, amount })
-            .await?;
+        if let Some(review_id) = review_id {
+            return Ok(WithdrawStatus::PendingReview { review_id });
+        }
+
+        self.pay(user_id, amount, invoice).await
+    }
+
+    /// Pays a withdrawal already debited from the user. It is refunded only
+    /// if the node confirms the payment failed, since a timeout can hide one
+    /// that went through. Otherwise it stays debited until reconciliation
+    /// finds out how it ended.
+    pub async fn pay(
+        &self,
+        user_id: Uuid,
+        amount: Msat,
+        invoice: String,
+    ) -> Result<WithdrawStatus> {
+        let Err(err) = self.lightning_client.pay_invoice(invoice.clone()).await else {
+            return Ok(WithdrawStatus::Paid);
+        };
+
+        let payment_hash = DecodedInvoice::decode(&invoice)?.payment_hash;
+
+        match self.lightning_client.get_invoice(payment_hash).await {
+            Ok(PaymentStatus::Settled) => Ok(WithdrawStatus::Paid),
+            Ok(PaymentStatus::Failed) => {
+                self.wallet_repository
+                    .save_transfer(SaveTransfer::withdrawal_refund(user_id, amount, invoice))
+                    .await?;
 
-        Ok(())
+                Err(err)
+            }
+            Ok(PaymentStatus::Pending) | Err(_) => {
+                tracing::warn!("Withdrawal of {user_id} has an unknown outcome: {err}");
+
+                Ok(WithdrawStatus::Pending)
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::repositories::MockWalletRepositoryTrait;
-    use reqwest::Client;
+    use crate::http::MockLightningClient;
+    use crate::repositories::{
+        MockWalletRepositoryTrait, MockWithdrawalRepositoryTrait, TransferKind,
+    };
     use uuid::uuid;
 
+    // BOLT11 specification test vector for 250000 sats.
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+    const PAYMENT_HASH: &str = "0001020304050607080900010203040506070809000102030405060708090102";
+
     #[tokio::test]
     async fn test_withdraw_service_1() {
         let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
@@ -71,15 +134,20 @@ mod tests {
             .expect_get_balance()
             .once()
             .withf(|id| id == &uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"))
-            .returning(|_| Ok(1000));
+            .returning(|_| Ok(Msat::new(1000)));
 
         let input = WithdrawInput {
             user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
             invoice: String::new(),
-            amount: 2000,
+            amount: Msat::new(2000),
         };
 
-        let service = WithdrawService::new(mock_wallet_repository, Client::new());
+        let service = WithdrawService::new(
+            mock_wallet_repository,
+            MockLightningClient::new(),
+            MockWithdrawalRepositoryTrait::new(),
+            WithdrawalLimits::default(),
+        );
 
         let result = service.execute(input).await.ok();
 
@@ -94,18 +162,195 @@ mod tests {
             .expect_get_balance()
             .once()
             .withf(|id| id == &uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"))
-            .returning(|_| Ok(1000));
+            .returning(|_| Ok(Msat::new(1000)));
 
         let input = WithdrawInput {
             user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
             invoice: String::new(),
-            amount: -2000,
+            amount: Msat::new(-2000),
         };
 
-        let service = WithdrawService::new(mock_wallet_repository, Client::new());
+        let service = WithdrawService::new(
+            mock_wallet_repository,
+            MockLightningClient::new(),
+            MockWithdrawalRepositoryTrait::new(),
+            WithdrawalLimits::default(),
+        );
 
         let result = service.execute(input).await.ok();
 
         assert!(result.is_none())
     }
+
+    fn paying_service(
+        mock_wallet_repository: MockWalletRepositoryTrait,
+        mock_lightning_client: MockLightningClient,
+    ) -> WithdrawService<
+        MockWalletRepositoryTrait,
+        MockLightningClient,
+        MockWithdrawalRepositoryTrait,
+    > {
+        let mut mock_withdrawal_repository = MockWithdrawalRepositoryTrait::new();
+
+        mock_withdrawal_repository
+            .expect_debit_withdrawal()
+            .once()
+            .returning(|_, _, _, _| Ok(None));
+
+        WithdrawService::new(
+            mock_wallet_repository,
+            mock_lightning_client,
+            mock_withdrawal_repository,
+            WithdrawalLimits::default(),
+        )
+    }
+
+    fn input(amount: i64) -> WithdrawInput {
+        WithdrawInput {
+            user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+            invoice: String::from(INVOICE),
+            amount: Msat::new(amount),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_refunds_failed_payment() {
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .once()
+            .returning(|_| Ok(Msat::new(1000)));
+
+        mock_wallet_repository
+            .expect_save_transfer()
+            .once()
+            .withf(|transfer| {
+                transfer.amount == Msat::new(500) && transfer.kind == TransferKind::Refund
+            })
+            .returning(|_| Ok(Uuid::new_v4()));
+
+        mock_lightning_client
+            .expect_pay_invoice()
+            .once()
+            .returning(|_| Err(Error::InternalServerError));
+
+        mock_lightning_client
+            .expect_get_invoice()
+            .once()
+            .withf(|payment_hash| payment_hash == PAYMENT_HASH)
+            .returning(|_| Ok(PaymentStatus::Failed));
+
+        let service = paying_service(mock_wallet_repository, mock_lightning_client);
+
+        assert!(service.execute(input(500)).await.is_err())
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_with_unknown_outcome_stays_debited() {
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(1000)));
+
+        mock_wallet_repository.expect_save_transfer().never();
+
+        mock_lightning_client
+            .expect_pay_invoice()
+            .once()
+            .returning(|_| Err(Error::InternalServerError));
+
+        mock_lightning_client
+            .expect_get_invoice()
+            .once()
+            .returning(|_| Ok(PaymentStatus::Pending));
+
+        let service = paying_service(mock_wallet_repository, mock_lightning_client);
+
+        assert_eq!(
+            service.execute(input(500)).await.ok(),
+            Some(WithdrawStatus::Pending)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_over_daily_limit() {
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_withdrawal_repository = MockWithdrawalRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(10_000)));
+
+        mock_wallet_repository.expect_save_transfer().never();
+
+        mock_withdrawal_repository
+            .expect_debit_withdrawal()
+            .once()
+            .withf(|_, amount, _, limits| {
+                *amount == Msat::new(501) && limits.daily == Some(Msat::new(1_000))
+            })
+            .returning(|_, _, _, _| bad_req!("This withdrawal exceeds your daily limit"));
+
+        mock_lightning_client.expect_pay_invoice().never();
+
+        let input = WithdrawInput {
+            user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+            invoice: String::new(),
+            amount: Msat::new(501),
+        };
+
+        let service = WithdrawService::new(
+            mock_wallet_repository,
+            mock_lightning_client,
+            mock_withdrawal_repository,
+            WithdrawalLimits::parse("", "1", "", "", "").unwrap(),
+        );
+
+        assert!(service.execute(input).await.is_err())
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_above_threshold_waits_for_review() {
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_withdrawal_repository = MockWithdrawalRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+        let review_id = Uuid::new_v4();
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(10_000)));
+
+        mock_wallet_repository.expect_save_transfer().never();
+
+        mock_withdrawal_repository
+            .expect_debit_withdrawal()
+            .once()
+            .withf(|_, amount, _, _| *amount == Msat::new(2000))
+            .returning(move |_, _, _, _| Ok(Some(review_id)));
+
+        mock_lightning_client.expect_pay_invoice().never();
+
+        let input = WithdrawInput {
+            user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+            invoice: String::new(),
+            amount: Msat::new(2000),
+        };
+
+        let service = WithdrawService::new(
+            mock_wallet_repository,
+            mock_lightning_client,
+            mock_withdrawal_repository,
+            WithdrawalLimits::parse("", "", "", "", "1").unwrap(),
+        );
+
+        assert_eq!(
+            service.execute(input).await.ok(),
+            Some(WithdrawStatus::PendingReview { review_id })
+        );
+    }
 }
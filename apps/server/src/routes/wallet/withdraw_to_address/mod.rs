@@ -0,0 +1,49 @@
+use crate::http::{GenericError, Result};
+use crate::models::{AuthUser, Sats};
+use crate::repositories::{WalletRepository, WithdrawalRepository};
+use aide::transform::TransformOperation;
+use axum::Json;
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use service::{WithdrawToAddressInput, WithdrawToAddressService};
+
+use super::withdraw;
+use super::withdraw::service::WithdrawStatus;
+
+mod service;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WithdrawToAddressBody {
+    /// Lightning Address or LNURL-pay string.
+    destination: String,
+    amount: Sats,
+}
+
+fn resource() -> WithdrawToAddressService<Client, WalletRepository, Client, WithdrawalRepository> {
+    WithdrawToAddressService::new(Client::new(), withdraw::resource())
+}
+
+pub async fn route(
+    auth_user: AuthUser,
+    Json(payload): Json<WithdrawToAddressBody>,
+) -> Result<Json<WithdrawStatus>> {
+    let withdraw_to_address_service = resource();
+
+    let status = withdraw_to_address_service
+        .execute(WithdrawToAddressInput {
+            user_id: auth_user.user_id,
+            destination: payload.destination,
+            amount: payload.amount,
+        })
+        .await?;
+
+    Ok(Json(status))
+}
+
+pub fn docs(op: TransformOperation) -> TransformOperation {
+    op.tag("Withdraw")
+        .description("Withdraw satoshis to a Lightning Address or LNURL-pay destination")
+        .response::<200, Json<WithdrawStatus>>()
+        .response::<400, Json<GenericError>>()
+}
@@ -0,0 +1,245 @@
+use crate::bad_req;
+use crate::http::{request_pay_invoice, Error, LightningClient, LnurlClient, Result};
+use crate::models::Sats;
+use crate::repositories::{WalletRepositoryTrait, WithdrawalRepositoryTrait};
+use uuid::Uuid;
+
+use super::super::withdraw::service::{WithdrawInput, WithdrawService, WithdrawStatus};
+
+pub struct WithdrawToAddressService<
+    C: LnurlClient,
+    R: WalletRepositoryTrait,
+    L: LightningClient,
+    W: WithdrawalRepositoryTrait,
+> {
+    lnurl_client: C,
+    withdraw_service: WithdrawService<R, L, W>,
+}
+
+pub struct WithdrawToAddressInput {
+    pub user_id: Uuid,
+    pub destination: String,
+    pub amount: Sats,
+}
+
+impl<
+        C: LnurlClient,
+        R: WalletRepositoryTrait,
+        L: LightningClient,
+        W: WithdrawalRepositoryTrait,
+    > WithdrawToAddressService<C, R, L, W>
+{
+    pub fn new(lnurl_client: C, withdraw_service: WithdrawService<R, L, W>) -> Self {
+        Self {
+            lnurl_client,
+            withdraw_service,
+        }
+    }
+
+    /// Requests an invoice for `amount` sats from a Lightning Address or
+    /// LNURL-pay service and pays it like any other withdrawal.
+    pub async fn execute(
+        &self,
+        WithdrawToAddressInput {
+            user_id,
+            destination,
+            amount,
+        }: WithdrawToAddressInput,
+    ) -> Result<WithdrawStatus> {
+        if !amount.is_positive() {
+            return bad_req!("Invalid withdraw amount");
+        }
+
+        let invoice =
+            request_pay_invoice(&self.lnurl_client, &destination, amount.to_msat()?).await?;
+
+        self.withdraw_service
+            .execute(WithdrawInput {
+                user_id,
+                amount: invoice.amount,
+                invoice: invoice.payment_request,
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{MockLightningClient, MockLnurlClient};
+    use crate::models::{LnurlTag, Msat, PayRequest, PayRequestInvoice, WithdrawalLimits};
+    use crate::repositories::{MockWalletRepositoryTrait, MockWithdrawalRepositoryTrait};
+    use uuid::uuid;
+
+    // BOLT11 specification test vector for 2000000 sats committing to the hash
+    // of the description below.
+    const INVOICE: &str = "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqs9qrsgq7ea976txfraylvgzuxs8kgcw23ezlrszfnh8r6qtfpr6cxga50aj6txm9rxrydzd06dfeawfk6swupvz4erwnyutnjq7x39ymw6j38gp7ynn44";
+    const METADATA: &str = "One piece of chocolate cake, one icecream cone, one pickle, one slice of swiss cheese, one slice of salami, one lollypop, one piece of cherry pie, one sausage, one cupcake, and one slice of watermelon";
+
+    fn pay_request(metadata: &str) -> PayRequest {
+        PayRequest {
+            tag: LnurlTag::PayRequest,
+            callback: String::from("https://chesu.com/lnurl/pay/bob/callback"),
+            min_sendable: Msat::new(1_000),
+            max_sendable: Msat::new(10_000_000_000),
+            metadata: String::from(metadata),
+        }
+    }
+
+    fn input(amount: i64) -> WithdrawToAddressInput {
+        WithdrawToAddressInput {
+            user_id: uuid!("55bc0856-6b5a-4e5a-b294-bf82921a996a"),
+            destination: String::from("bob@chesu.com"),
+            amount: Sats::new(amount),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_to_address() {
+        let mut mock_lnurl_client = MockLnurlClient::new();
+        let mut mock_wallet_repository = MockWalletRepositoryTrait::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_lnurl_client
+            .expect_get_pay_request()
+            .once()
+            .withf(|url| url.as_str() == "https://chesu.com/.well-known/lnurlp/bob")
+            .returning(|_| Ok(pay_request(METADATA)));
+
+        mock_lnurl_client
+            .expect_get_pay_invoice()
+            .once()
+            .withf(|_, amount| *amount == Msat::new(2_000_000_000))
+            .returning(|_, _| {
+                Ok(PayRequestInvoice {
+                    pr: String::from(INVOICE),
+                    routes: vec![],
+                })
+            });
+
+        mock_wallet_repository
+            .expect_get_balance()
+            .returning(|_| Ok(Msat::new(3_000_000_000)));
+
+        mock_lightning_client
+            .expect_pay_invoice()
+            .once()
+            .withf(|invoice| invoice == INVOICE)
+            .returning(|_| Ok(()));
+
+        let mut mock_withdrawal_repository = MockWithdrawalRepositoryTrait::new();
+
+        mock_withdrawal_repository
+            .expect_debit_withdrawal()
+            .returning(|_, _, _, _| Ok(None));
+
+        let service = WithdrawToAddressService::new(
+            mock_lnurl_client,
+            WithdrawService::new(
+                mock_wallet_repository,
+                mock_lightning_client,
+                mock_withdrawal_repository,
+                WithdrawalLimits::default(),
+            ),
+        );
+
+        assert_eq!(
+            service.execute(input(2_000_000)).await.ok(),
+            Some(WithdrawStatus::Paid)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_out_of_bounds() {
+        let mut mock_lnurl_client = MockLnurlClient::new();
+
+        mock_lnurl_client
+            .expect_get_pay_request()
+            .once()
+            .returning(|_| Ok(pay_request(METADATA)));
+
+        mock_lnurl_client.expect_get_pay_invoice().never();
+
+        let service = WithdrawToAddressService::new(
+            mock_lnurl_client,
+            WithdrawService::new(
+                MockWalletRepositoryTrait::new(),
+                MockLightningClient::new(),
+                MockWithdrawalRepositoryTrait::new(),
+                WithdrawalLimits::default(),
+            ),
+        );
+
+        assert!(service.execute(input(20_000_000)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_rejects_mismatched_description_hash() {
+        let mut mock_lnurl_client = MockLnurlClient::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_lnurl_client
+            .expect_get_pay_request()
+            .once()
+            .returning(|_| Ok(pay_request(r#"[["text/plain","Something else"]]"#)));
+
+        mock_lnurl_client
+            .expect_get_pay_invoice()
+            .once()
+            .returning(|_, _| {
+                Ok(PayRequestInvoice {
+                    pr: String::from(INVOICE),
+                    routes: vec![],
+                })
+            });
+
+        mock_lightning_client.expect_pay_invoice().never();
+
+        let service = WithdrawToAddressService::new(
+            mock_lnurl_client,
+            WithdrawService::new(
+                MockWalletRepositoryTrait::new(),
+                mock_lightning_client,
+                MockWithdrawalRepositoryTrait::new(),
+                WithdrawalLimits::default(),
+            ),
+        );
+
+        assert!(service.execute(input(2_000_000)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_rejects_mismatched_amount() {
+        let mut mock_lnurl_client = MockLnurlClient::new();
+        let mut mock_lightning_client = MockLightningClient::new();
+
+        mock_lnurl_client
+            .expect_get_pay_request()
+            .once()
+            .returning(|_| Ok(pay_request(METADATA)));
+
+        mock_lnurl_client
+            .expect_get_pay_invoice()
+            .once()
+            .returning(|_, _| {
+                Ok(PayRequestInvoice {
+                    pr: String::from(INVOICE),
+                    routes: vec![],
+                })
+            });
+
+        mock_lightning_client.expect_pay_invoice().never();
+
+        let service = WithdrawToAddressService::new(
+            mock_lnurl_client,
+            WithdrawService::new(
+                MockWalletRepositoryTrait::new(),
+                mock_lightning_client,
+                MockWithdrawalRepositoryTrait::new(),
+                WithdrawalLimits::default(),
+            ),
+        );
+
+        assert!(service.execute(input(1_000)).await.is_err());
+    }
+}
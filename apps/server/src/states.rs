@@ -21,41 +21,186 @@ pub mod db {
     }
 }
 
+pub mod rake {
+    use crate::models::Rake;
+    use std::sync::OnceLock;
+
+    static RAKE: OnceLock<Rake> = OnceLock::new();
+
+    /// Parses the rake once at startup, so a bad config stops the server
+    /// instead of every game connection.
+    pub fn init() {
+        RAKE.set(Rake::from_env()).unwrap();
+    }
+
+    pub fn get() -> Rake {
+        RAKE.get().expect("Rake has not been initialized").clone()
+    }
+}
+
+pub mod max_concurrent_games {
+    use std::sync::OnceLock;
+
+    static MAX_CONCURRENT_GAMES: OnceLock<usize> = OnceLock::new();
+
+    /// Parses MAX_CONCURRENT_GAMES at startup, so a bad value fails the boot
+    /// rather than a pairing request.
+    pub fn init() {
+        MAX_CONCURRENT_GAMES
+            .set(crate::models::max_concurrent_games())
+            .unwrap();
+    }
+
+    pub fn get() -> usize {
+        *MAX_CONCURRENT_GAMES
+            .get()
+            .expect("Max concurrent games has not been initialized")
+    }
+}
+
+pub mod seek_ttl {
+    use chrono::Duration;
+    use std::sync::OnceLock;
+
+    static SEEK_TTL: OnceLock<Duration> = OnceLock::new();
+
+    /// Parses SEEK_TTL_MINUTES at startup, so a bad value fails the boot
+    /// instead of the expiry job.
+    pub fn init() {
+        SEEK_TTL.set(crate::models::seek_ttl()).unwrap();
+    }
+
+    pub fn get() -> Duration {
+        *SEEK_TTL.get().expect("Seek TTL has not been initialized")
+    }
+}
+
+pub mod invite_ttl {
+    use chrono::Duration;
+    use std::sync::OnceLock;
+
+    static INVITE_TTL: OnceLock<Duration> = OnceLock::new();
+
+    /// Parses INVITE_TTL_MINUTES at startup, next to the seek TTL.
+    pub fn init() {
+        INVITE_TTL.set(crate::models::invite_ttl()).unwrap();
+    }
+
+    pub fn get() -> Duration {
+        *INVITE_TTL
+            .get()
+            .expect("Invite TTL has not been initialized")
+    }
+}
+
+pub mod challenge_ttl {
+    use chrono::Duration;
+    use std::sync::OnceLock;
+
+    static CHALLENGE_TTL: OnceLock<Duration> = OnceLock::new();
+
+    /// Parses CHALLENGE_TTL_MINUTES at startup, next to the seek TTL.
+    pub fn init() {
+        CHALLENGE_TTL.set(crate::models::challenge_ttl()).unwrap();
+    }
+
+    pub fn get() -> Duration {
+        *CHALLENGE_TTL
+            .get()
+            .expect("Challenge TTL has not been initialized")
+    }
+}
+
+pub mod cooling_off {
+    use chrono::Duration;
+    use std::sync::OnceLock;
+
+    static COOLING_OFF: OnceLock<Duration> = OnceLock::new();
+
+    /// Parses LIMIT_COOLING_OFF_HOURS at startup, so a bad value fails the
+    /// boot rather than a limit update.
+    pub fn init() {
+        COOLING_OFF.set(crate::models::cooling_off()).unwrap();
+    }
+
+    pub fn get() -> Duration {
+        *COOLING_OFF
+            .get()
+            .expect("Cooling-off period has not been initialized")
+    }
+}
+
+pub mod withdrawal_limits {
+    use crate::models::WithdrawalLimits;
+    use std::sync::OnceLock;
+
+    static WITHDRAWAL_LIMITS: OnceLock<WithdrawalLimits> = OnceLock::new();
+
+    /// Parses the WITHDRAWAL_* limits at startup, so a bad value fails the
+    /// boot rather than a withdrawal.
+    pub fn init() {
+        WITHDRAWAL_LIMITS.set(WithdrawalLimits::from_env()).unwrap();
+    }
+
+    pub fn get() -> WithdrawalLimits {
+        WITHDRAWAL_LIMITS
+            .get()
+            .expect("Withdrawal limits have not been initialized")
+            .clone()
+    }
+}
+
 pub mod rooms_manager {
-    use crate::models::{GameRooms, Requests};
+    use crate::models::{GameRooms, MatchmakingPool};
     use std::sync::Mutex;
     use std::{collections::HashMap, sync::Arc};
     use tokio::sync::OnceCell;
 
     static GAME_ROOMS: OnceCell<GameRooms> = OnceCell::const_new();
-    static REQUESTS: OnceCell<Requests> = OnceCell::const_new();
+    static MATCHMAKING_POOL: OnceCell<MatchmakingPool> = OnceCell::const_new();
 
     fn init() {
         GAME_ROOMS
             .set(Arc::new(Mutex::new(HashMap::new())))
             .unwrap();
-        REQUESTS.set(Arc::new(Mutex::new(HashMap::new()))).unwrap();
+        MATCHMAKING_POOL
+            .set(Arc::new(Mutex::new(HashMap::new())))
+            .unwrap();
     }
 
-    fn get_rooms_manager() -> (GameRooms, Requests) {
+    fn get_rooms_manager() -> (GameRooms, MatchmakingPool) {
         let game_rooms = GAME_ROOMS
             .get()
             .expect("Game rooms has not been initialized")
             .clone();
 
-        let requests = REQUESTS
+        let matchmaking_pool = MATCHMAKING_POOL
             .get()
-            .expect("Waiting rooms has not been initialized")
+            .expect("Matchmaking pool has not been initialized")
             .clone();
 
-        (game_rooms, requests)
+        (game_rooms, matchmaking_pool)
     }
 
-    pub fn get() -> (GameRooms, Requests) {
-        if GAME_ROOMS.get().is_none() || REQUESTS.get().is_none() {
+    pub fn get() -> (GameRooms, MatchmakingPool) {
+        if GAME_ROOMS.get().is_none() || MATCHMAKING_POOL.get().is_none() {
             init();
         }
 
         get_rooms_manager()
     }
 }
+
+pub mod lobby {
+    use crate::models::LobbyChannels;
+    use std::sync::OnceLock;
+    use std::sync::{Arc, Mutex};
+
+    static LOBBY_CHANNELS: OnceLock<LobbyChannels> = OnceLock::new();
+
+    pub fn get() -> LobbyChannels {
+        LOBBY_CHANNELS
+            .get_or_init(|| Arc::new(Mutex::new(Default::default())))
+            .clone()
+    }
+}